@@ -7,9 +7,16 @@ logoutLink.addEventListener("click", (e) => {
 
     let url = logoutLink.href;
 
+    // Echo the CSRF cookie issued by the auth service at login (double-submit CSRF protection)
+    const csrfToken = document.cookie
+        .split("; ")
+        .find((cookie) => cookie.startsWith("csrf_token=") || cookie.startsWith("__Host-csrf_token="))
+        ?.split("=")[1];
+
     fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
+        headers: csrfToken ? { 'X-CSRF-Token': csrfToken } : {},
     }).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
@@ -2,7 +2,7 @@ use std::env;
 
 use askama::Template;
 use axum::{
-    http::StatusCode,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
@@ -46,9 +46,18 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
-        Some(cookie) => cookie,
+async fn protected(headers: HeaderMap, jar: CookieJar) -> impl IntoResponse {
+    // Prefer an `Authorization: Bearer` header, falling back to the JWT cookie
+    let bearer_token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // The auth service may be configured to set the cookie with the `__Host-` prefix
+    let jwt_cookie = jar.get("__Host-jwt").or_else(|| jar.get("jwt"));
+
+    let token = match bearer_token.or_else(|| jwt_cookie.map(|cookie| cookie.value())) {
+        Some(token) => token,
         None => {
             return StatusCode::UNAUTHORIZED.into_response();
         }
@@ -57,13 +66,19 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     let api_client = reqwest::Client::builder().build().unwrap();
 
     let verify_token_body = serde_json::json!({
-        "token": &jwt_cookie.value(),
+        "token": token,
     });
 
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
     let url = format!("http://{auth_hostname}:3000/verify-token");
 
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
+    let response = match api_client
+        .post(&url)
+        .headers(trace_context(&headers))
+        .json(&verify_token_body)
+        .send()
+        .await
+    {
         Ok(response) => response,
         Err(_) => {
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
@@ -82,6 +97,24 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     }
 }
 
+// The caller's W3C trace context, passed on unchanged so the auth service continues its trace.
+// This service doesn't create spans of its own, so it has no parent ID or sampling decision to
+// add; without a `traceparent` the auth service starts a new trace.
+fn trace_context(headers: &HeaderMap) -> reqwest::header::HeaderMap {
+    TRACE_CONTEXT_HEADERS
+        .iter()
+        .filter_map(|&name| {
+            let value = headers.get(name)?.to_str().ok()?;
+            Some((
+                reqwest::header::HeaderName::from_static(name),
+                reqwest::header::HeaderValue::from_str(value).ok()?,
+            ))
+        })
+        .collect()
+}
+
+const TRACE_CONTEXT_HEADERS: &[&str] = &["traceparent", "tracestate"];
+
 #[derive(Serialize)]
 pub struct ProtectedRouteResponse {
     pub img_url: String,
@@ -32,6 +32,16 @@ signupLoginLink.addEventListener("click", (e) => {
 
 // -----------------------------------------------------
 
+// Echo the CSRF cookie issued at login back in a header (double-submit CSRF protection)
+function csrfHeaders() {
+    const csrfToken = document.cookie
+        .split("; ")
+        .find((cookie) => cookie.startsWith("csrf_token=") || cookie.startsWith("__Host-csrf_token="))
+        ?.split("=")[1];
+
+    return csrfToken ? { 'X-CSRF-Token': csrfToken } : {};
+}
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -41,16 +51,19 @@ loginButton.addEventListener("click", (e) => {
 
     const email = loginForm.email.value;
     const password = loginForm.password.value;
+    const rememberMe = loginForm.rememberMe.checked;
 
     fetch('/login', {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
+            ...csrfHeaders(),
         },
-        body: JSON.stringify({ email, password }),
+        body: JSON.stringify({ email, password, rememberMe }),
     }).then(response => {
         if (response.status === 206) {
             TwoFAForm.email.value = email;
+            TwoFAForm.remember_me.value = rememberMe;
             response.json().then(data => {
                 TwoFAForm.login_attempt_id.value = data.loginAttemptId;
             });
@@ -96,6 +109,7 @@ signupButton.addEventListener("click", (e) => {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
+            ...csrfHeaders(),
         },
         body: JSON.stringify({ email, password, requires2FA }),
     }).then(response => {
@@ -132,18 +146,23 @@ TwoFAButton.addEventListener("click", (e) => {
     const email = TwoFAForm.email.value;
     const loginAttemptId = TwoFAForm.login_attempt_id.value;
     const TwoFACode = TwoFAForm.email_code.value;
+    const rememberMe = TwoFAForm.remember_me.value === "true";
+    const trustDevice = TwoFAForm.trustDevice.checked;
 
     fetch('/verify-2fa', {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
+            ...csrfHeaders(),
         },
-        body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
+        body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode, rememberMe, trustDevice }),
     }).then(response => {
         if (response.ok) {
             TwoFAForm.email.value = "";
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
+            TwoFAForm.remember_me.value = "";
+            TwoFAForm.trustDevice.checked = false;
             TwoFAErrAlter.style.display = "none";
             alert("You have successfully logged in.");
             loginSection.style.display = "block";
@@ -161,4 +180,36 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+const TwoFAResendLink = document.getElementById("2fa-resend-link");
+
+TwoFAResendLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const loginAttemptId = TwoFAForm.login_attempt_id.value;
+
+    fetch('/resend-2fa', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+            ...csrfHeaders(),
+        },
+        body: JSON.stringify({ loginAttemptId }),
+    }).then(response => {
+        if (response.ok) {
+            TwoFAErrAlter.style.display = "none";
+            alert("A new code has been sent to your email.");
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    TwoFAErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    TwoFAErrAlter.style.display = "block";
+                } else {
+                    TwoFAErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
@@ -0,0 +1,4 @@
+ALTER TABLE users
+  ADD COLUMN phone_number TEXT,
+  ADD COLUMN two_fa_channel TEXT NOT NULL DEFAULT 'email'
+    CHECK (two_fa_channel IN ('email', 'sms'));
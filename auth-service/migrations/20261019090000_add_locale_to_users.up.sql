@@ -0,0 +1,3 @@
+ALTER TABLE users
+  ADD COLUMN locale TEXT NOT NULL DEFAULT 'en'
+    CHECK (locale IN ('en', 'es'));
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS email_outbox (
+  id UUID PRIMARY KEY,
+  idempotency_key TEXT NOT NULL,
+  recipient TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  html_body TEXT NOT NULL,
+  text_body TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending'
+    CHECK (status IN ('pending', 'sent', 'dead')),
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_error TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  sent_at TIMESTAMPTZ
+);
+
+-- The same email can't be queued twice while it's waiting to be sent
+CREATE UNIQUE INDEX IF NOT EXISTS email_outbox_pending_idempotency_key
+  ON email_outbox (idempotency_key)
+  WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS email_outbox_due
+  ON email_outbox (next_attempt_at)
+  WHERE status = 'pending';
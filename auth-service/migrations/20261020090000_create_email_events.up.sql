@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS email_events (
+  id UUID PRIMARY KEY,
+  email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE,
+  kind TEXT NOT NULL
+    CHECK (kind IN ('delivery', 'hard_bounce', 'soft_bounce', 'spam_complaint')),
+  details TEXT,
+  occurred_at TIMESTAMPTZ NOT NULL,
+  received_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS email_events_email ON email_events (email);
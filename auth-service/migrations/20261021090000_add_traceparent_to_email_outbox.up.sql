@@ -0,0 +1,3 @@
+-- The trace of the request that queued the email, continued when it's delivered
+ALTER TABLE email_outbox
+  ADD COLUMN traceparent TEXT;
@@ -0,0 +1,26 @@
+-- Not tied to users, so failed logins for unknown addresses are recorded and the trail
+-- outlives deleted accounts
+CREATE TABLE IF NOT EXISTS audit_events (
+  id UUID PRIMARY KEY,
+  kind TEXT NOT NULL,
+  email TEXT,
+  reason TEXT,
+  ip_address TEXT,
+  user_agent TEXT,
+  request_id TEXT,
+  occurred_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at ON audit_events (occurred_at);
+CREATE INDEX IF NOT EXISTS audit_events_email ON audit_events (email, occurred_at);
+
+-- The audit log is append-only
+CREATE OR REPLACE FUNCTION reject_audit_event_changes() RETURNS TRIGGER AS $$
+BEGIN
+  RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+  BEFORE UPDATE OR DELETE ON audit_events
+  FOR EACH ROW EXECUTE FUNCTION reject_audit_event_changes();
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  id UUID PRIMARY KEY,
+  event_id UUID NOT NULL,
+  kind TEXT NOT NULL
+    CHECK (kind IN ('user.created', 'user.login', 'user.2fa_enabled', 'session.revoked')),
+  url TEXT NOT NULL,
+  payload TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending'
+    CHECK (status IN ('pending', 'sent', 'dead')),
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  traceparent TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_due
+  ON webhook_deliveries (next_attempt_at)
+  WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_event_id ON webhook_deliveries (event_id);
+
+-- The delivery log: every attempt and how the subscriber responded
+CREATE TABLE IF NOT EXISTS webhook_delivery_attempts (
+  delivery_id UUID NOT NULL REFERENCES webhook_deliveries (id) ON DELETE CASCADE,
+  attempted_at TIMESTAMPTZ NOT NULL,
+  status_code INTEGER,
+  error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_attempts_delivery_id
+  ON webhook_delivery_attempts (delivery_id, attempted_at);
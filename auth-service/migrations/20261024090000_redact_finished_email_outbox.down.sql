@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS email_outbox_finished;
+
+UPDATE email_outbox
+SET html_body = COALESCE(html_body, ''), text_body = COALESCE(text_body, '');
+
+ALTER TABLE email_outbox
+  ALTER COLUMN html_body SET NOT NULL,
+  ALTER COLUMN text_body SET NOT NULL;
@@ -0,0 +1,14 @@
+-- Sent and dead-lettered emails don't keep their bodies, which contain 2FA and phone
+-- verification codes
+ALTER TABLE email_outbox
+  ALTER COLUMN html_body DROP NOT NULL,
+  ALTER COLUMN text_body DROP NOT NULL;
+
+UPDATE email_outbox
+SET html_body = NULL, text_body = NULL
+WHERE status <> 'pending';
+
+-- Finished emails are purged once they're older than the retention period
+CREATE INDEX IF NOT EXISTS email_outbox_finished
+  ON email_outbox (created_at)
+  WHERE status <> 'pending';
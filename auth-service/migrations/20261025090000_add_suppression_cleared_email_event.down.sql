@@ -0,0 +1,6 @@
+DELETE FROM email_events WHERE kind = 'suppression_cleared';
+
+ALTER TABLE email_events DROP CONSTRAINT IF EXISTS email_events_kind_check;
+
+ALTER TABLE email_events ADD CONSTRAINT email_events_kind_check
+  CHECK (kind IN ('delivery', 'hard_bounce', 'soft_bounce', 'spam_complaint'));
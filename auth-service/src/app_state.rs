@@ -10,15 +10,64 @@ pub type TwoFACodeStoreType<TwoFACodeStoreImpl> = Arc<RwLock<TwoFACodeStoreImpl>
 
 pub type EmailClientType<EmailClientImpl> = Arc<EmailClientImpl>;
 
-pub struct AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl> {
+pub type SessionStoreType<SessionStoreImpl> = Arc<RwLock<SessionStoreImpl>>;
+
+pub type TrustedDeviceStoreType<TrustedDeviceStoreImpl> = Arc<RwLock<TrustedDeviceStoreImpl>>;
+
+pub type SmsClientType<SmsClientImpl> = Arc<SmsClientImpl>;
+
+pub type EmailOutboxStoreType<EmailOutboxStoreImpl> = Arc<RwLock<EmailOutboxStoreImpl>>;
+
+pub type EmailEventStoreType<EmailEventStoreImpl> = Arc<RwLock<EmailEventStoreImpl>>;
+
+pub type AuditSinkType<AuditSinkImpl> = Arc<RwLock<AuditSinkImpl>>;
+
+pub type WebhookDeliveryStoreType<WebhookDeliveryStoreImpl> = Arc<RwLock<WebhookDeliveryStoreImpl>>;
+
+pub struct AppState<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+> {
     pub user_store: UserStoreType<UserStoreImpl>,
     pub banned_token_store: BannedTokenStoreType<BannedTokenStoreImpl>,
     pub two_fa_code_store: TwoFACodeStoreType<TwoFACodeStoreImpl>,
     pub email_client: EmailClientType<EmailClientImpl>,
+    pub session_store: SessionStoreType<SessionStoreImpl>,
+    pub trusted_device_store: TrustedDeviceStoreType<TrustedDeviceStoreImpl>,
+    pub sms_client: SmsClientType<SmsClientImpl>,
+    pub email_event_store: EmailEventStoreType<EmailEventStoreImpl>,
+    pub audit_sink: AuditSinkType<AuditSinkImpl>,
 }
 
-impl<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl> Clone
-    for AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>
+impl<
+        UserStoreImpl,
+        BannedTokenStoreImpl,
+        TwoFACodeStoreImpl,
+        EmailClientImpl,
+        SessionStoreImpl,
+        TrustedDeviceStoreImpl,
+        SmsClientImpl,
+        EmailEventStoreImpl,
+        AuditSinkImpl,
+    > Clone
+    for AppState<
+        UserStoreImpl,
+        BannedTokenStoreImpl,
+        TwoFACodeStoreImpl,
+        EmailClientImpl,
+        SessionStoreImpl,
+        TrustedDeviceStoreImpl,
+        SmsClientImpl,
+        EmailEventStoreImpl,
+        AuditSinkImpl,
+    >
 {
     fn clone(&self) -> Self {
         Self {
@@ -26,24 +75,60 @@ impl<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl> C
             banned_token_store: self.banned_token_store.clone(),
             two_fa_code_store: self.two_fa_code_store.clone(),
             email_client: self.email_client.clone(),
+            session_store: self.session_store.clone(),
+            trusted_device_store: self.trusted_device_store.clone(),
+            sms_client: self.sms_client.clone(),
+            email_event_store: self.email_event_store.clone(),
+            audit_sink: self.audit_sink.clone(),
         }
     }
 }
 
-impl<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>
-    AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>
+impl<
+        UserStoreImpl,
+        BannedTokenStoreImpl,
+        TwoFACodeStoreImpl,
+        EmailClientImpl,
+        SessionStoreImpl,
+        TrustedDeviceStoreImpl,
+        SmsClientImpl,
+        EmailEventStoreImpl,
+        AuditSinkImpl,
+    >
+    AppState<
+        UserStoreImpl,
+        BannedTokenStoreImpl,
+        TwoFACodeStoreImpl,
+        EmailClientImpl,
+        SessionStoreImpl,
+        TrustedDeviceStoreImpl,
+        SmsClientImpl,
+        EmailEventStoreImpl,
+        AuditSinkImpl,
+    >
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType<UserStoreImpl>,
         banned_token_store: BannedTokenStoreType<BannedTokenStoreImpl>,
         two_fa_code_store: TwoFACodeStoreType<TwoFACodeStoreImpl>,
         email_client: EmailClientType<EmailClientImpl>,
+        session_store: SessionStoreType<SessionStoreImpl>,
+        trusted_device_store: TrustedDeviceStoreType<TrustedDeviceStoreImpl>,
+        sms_client: SmsClientType<SmsClientImpl>,
+        email_event_store: EmailEventStoreType<EmailEventStoreImpl>,
+        audit_sink: AuditSinkType<AuditSinkImpl>,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            session_store,
+            trusted_device_store,
+            sms_client,
+            email_event_store,
+            audit_sink,
         }
     }
 }
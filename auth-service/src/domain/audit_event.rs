@@ -0,0 +1,116 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
+
+use super::Email;
+
+// A security-relevant thing that happened to an account, kept in the audit log for good
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub kind: AuditEventKind,
+    // Missing when the request didn't name a valid address, e.g. a malformed login
+    pub email: Option<Email>,
+    // Why a failure happened, e.g. `incorrect_credentials`
+    pub reason: Option<String>,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    // The request's `X-Request-Id`, to find its logs
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(kind: AuditEventKind, email: Option<Email>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            email,
+            reason: None,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditEventKind {
+    Signup,
+    LoginSucceeded,
+    LoginFailed,
+    TwoFACodeSent,
+    TwoFACodeVerified,
+    // Missing, expired or wrong
+    TwoFACodeFailed,
+    Logout,
+    SessionRevoked,
+    TrustedDeviceRevoked,
+    PasswordChanged,
+    Reauthenticated,
+    ReauthenticationFailed,
+    TwoFAEnabled,
+    TwoFADisabled,
+    PhoneNumberVerified,
+    TwoFAChannelChanged,
+}
+
+impl AuditEventKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "signup" => Ok(Self::Signup),
+            "login_succeeded" => Ok(Self::LoginSucceeded),
+            "login_failed" => Ok(Self::LoginFailed),
+            "2fa_code_sent" => Ok(Self::TwoFACodeSent),
+            "2fa_code_verified" => Ok(Self::TwoFACodeVerified),
+            "2fa_code_failed" => Ok(Self::TwoFACodeFailed),
+            "logout" => Ok(Self::Logout),
+            "session_revoked" => Ok(Self::SessionRevoked),
+            "trusted_device_revoked" => Ok(Self::TrustedDeviceRevoked),
+            "password_changed" => Ok(Self::PasswordChanged),
+            "reauthenticated" => Ok(Self::Reauthenticated),
+            "reauthentication_failed" => Ok(Self::ReauthenticationFailed),
+            "2fa_enabled" => Ok(Self::TwoFAEnabled),
+            "2fa_disabled" => Ok(Self::TwoFADisabled),
+            "phone_number_verified" => Ok(Self::PhoneNumberVerified),
+            "2fa_channel_changed" => Ok(Self::TwoFAChannelChanged),
+            _ => Err(eyre!("{kind} is not a valid audit event kind")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signup => "signup",
+            Self::LoginSucceeded => "login_succeeded",
+            Self::LoginFailed => "login_failed",
+            Self::TwoFACodeSent => "2fa_code_sent",
+            Self::TwoFACodeVerified => "2fa_code_verified",
+            Self::TwoFACodeFailed => "2fa_code_failed",
+            Self::Logout => "logout",
+            Self::SessionRevoked => "session_revoked",
+            Self::TrustedDeviceRevoked => "trusted_device_revoked",
+            Self::PasswordChanged => "password_changed",
+            Self::Reauthenticated => "reauthenticated",
+            Self::ReauthenticationFailed => "reauthentication_failed",
+            Self::TwoFAEnabled => "2fa_enabled",
+            Self::TwoFADisabled => "2fa_disabled",
+            Self::PhoneNumberVerified => "phone_number_verified",
+            Self::TwoFAChannelChanged => "2fa_channel_changed",
+        }
+    }
+}
+
+// Which audit events to return, newest first
+#[derive(Clone, Debug, Default)]
+pub struct AuditEventFilter {
+    pub email: Option<Email>,
+    pub kind: Option<AuditEventKind>,
+    // Inclusive
+    pub since: Option<DateTime<Utc>>,
+    // Exclusive, so the oldest `occurred_at` of a page fetches the next one
+    pub until: Option<DateTime<Utc>>,
+    pub limit: u32,
+}
@@ -1,10 +1,15 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Context, Report, Result};
 use secrecy::{ExposeSecret, SecretString};
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::{Email, Password, User};
+use super::{
+    AuditEvent, AuditEventFilter, DeviceId, Email, EmailEvent, OutboxEmail, OutboxStatus, Password,
+    PhoneNumber, Session, SessionId, TrustedDevice, TwoFAChannel, User, WebhookAttempt,
+    WebhookDelivery,
+};
 
 #[async_trait]
 pub trait UserStore {
@@ -13,6 +18,30 @@ pub trait UserStore {
 
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+
+    async fn update_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+
+    async fn update_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError>;
+
+    async fn update_two_fa_channel(
+        &mut self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -43,6 +72,19 @@ impl PartialEq for UserStoreError {
 pub trait BannedTokenStore {
     async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError>;
+
+    // Revoke the user's tokens authenticated before `time`, e.g. once their password changes.
+    // Tokens tied to a session are revoked by removing the session instead.
+    async fn revoke_tokens_before(
+        &mut self,
+        email: &Email,
+        time: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError>;
+
+    async fn get_tokens_revoked_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -51,6 +93,9 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] Report),
 }
 
+// Pending 2FA codes, one per login attempt. A user may have several login attempts pending at
+// once (e.g. on different devices), up to `MAX_PENDING_LOGIN_ATTEMPTS`, past which the oldest
+// attempt is dropped.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(
@@ -58,20 +103,112 @@ pub trait TwoFACodeStore {
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        purpose: TwoFACodePurpose,
     ) -> Result<(), TwoFACodeStoreError>;
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError>;
 
     async fn get_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode, TwoFACodePurpose), TwoFACodeStoreError>;
+
+    // Replace the attempt's code with a freshly sent one, counting it as a resend
+    async fn update_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    async fn get_resend_status(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<ResendStatus, TwoFACodeStoreError>;
+
+    // Count an incorrect code, removing the attempt after `max_attempts` so its code can't be
+    // guessed. Resending doesn't reset the count.
+    async fn record_failed_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    // Codes sent to a phone number the user is enrolling for SMS 2FA, one pending number per user.
+    // Replacing a pending verification counts as a resend, so the resend limit holds across
+    // numbers until the verification expires.
+    async fn add_phone_verification(
+        &mut self,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    async fn get_phone_verification(
+        &self,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), TwoFACodeStoreError>;
+
+    async fn get_phone_verification_resend_status(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    ) -> Result<ResendStatus, TwoFACodeStoreError>;
+
+    // Count an incorrect code, invalidating the pending one after `max_attempts`. The
+    // verification is kept until it expires so its resend limit still applies.
+    async fn record_failed_phone_verification(
+        &mut self,
+        email: &Email,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    async fn remove_phone_verification(&mut self, email: &Email)
+        -> Result<(), TwoFACodeStoreError>;
+}
+
+// When the attempt's current code was sent, and how many times it has been resent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResendStatus {
+    pub last_sent_at: DateTime<Utc>,
+    pub resend_count: u32,
+}
+
+impl ResendStatus {
+    // Check whether another code may be sent, given the cooldown between sends and the resend limit
+    pub fn check_resend(
+        &self,
+        now: DateTime<Utc>,
+        cooldown: chrono::Duration,
+        max_resends: u32,
+    ) -> Result<(), ResendError> {
+        if self.resend_count >= max_resends {
+            return Err(ResendError::TooManyResends);
+        }
+
+        if now < self.last_sent_at + cooldown {
+            return Err(ResendError::Cooldown);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ResendError {
+    #[error("Code sent too recently")]
+    Cooldown,
+    #[error("Too many codes sent")]
+    TooManyResends,
 }
 
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Phone verification not found")]
+    PhoneVerificationNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -81,11 +218,202 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (
+                    Self::PhoneVerificationNotFound,
+                    Self::PhoneVerificationNotFound
+                )
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
 }
 
+#[async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn remove_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait]
+pub trait TrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError>;
+    async fn get_device(&self, id: &DeviceId) -> Result<TrustedDevice, TrustedDeviceStoreError>;
+
+    async fn get_devices(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError>;
+
+    async fn remove_device(&mut self, id: &DeviceId) -> Result<(), TrustedDeviceStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TrustedDeviceStoreError {
+    #[error("Device not found")]
+    DeviceNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TrustedDeviceStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::DeviceNotFound, Self::DeviceNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait]
+pub trait EmailOutboxStore {
+    // Queue an email, unless one with the same idempotency key is still waiting to be sent
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError>;
+
+    // Take up to `limit` emails due for delivery, counting the attempt and hiding them
+    // from other workers until `lease_until`, in case this one dies mid-delivery
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+
+    // Sent and dead-lettered emails drop their bodies, which may contain codes
+    async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError>;
+
+    // Record a failed attempt, to be retried at `retry_at`, or dead-lettered without one
+    async fn mark_failed(
+        &mut self,
+        id: Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError>;
+
+    async fn get_status(&self, id: Uuid) -> Result<OutboxStatus, EmailOutboxStoreError>;
+
+    // Delete sent and dead-lettered emails queued before `before`, returning how many
+    async fn purge_finished(&mut self, before: DateTime<Utc>)
+        -> Result<u64, EmailOutboxStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxStoreError {
+    #[error("Email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailOutboxStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::EmailNotFound, Self::EmailNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait]
+pub trait EmailEventStore {
+    async fn add_event(&mut self, event: EmailEvent) -> Result<(), EmailEventStoreError>;
+    async fn get_events(&self, email: &Email) -> Result<Vec<EmailEvent>, EmailEventStoreError>;
+    // Whether emails to the address must not be sent, because it hard-bounced and hasn't
+    // received an email or had the suppression cleared since
+    async fn is_suppressed(&self, email: &Email) -> Result<bool, EmailEventStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailEventStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Where audit events are written. Events are only ever appended, never changed or removed.
+#[async_trait]
+pub trait AuditSink {
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditSinkError>;
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditSinkError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditSinkError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Webhook deliveries waiting to be sent, and the log of every attempt at sending them
+#[async_trait]
+pub trait WebhookDeliveryStore {
+    async fn enqueue(&mut self, delivery: WebhookDelivery)
+        -> Result<(), WebhookDeliveryStoreError>;
+
+    // Take up to `limit` deliveries that are due, counting the attempt and hiding them
+    // from other workers until `lease_until`, in case this one dies mid-delivery
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDelivery>, WebhookDeliveryStoreError>;
+
+    async fn mark_delivered(
+        &mut self,
+        id: Uuid,
+        attempt: WebhookAttempt,
+    ) -> Result<(), WebhookDeliveryStoreError>;
+
+    // Log a failed attempt, to be retried at `retry_at`, or dead-lettered without one
+    async fn mark_failed(
+        &mut self,
+        id: Uuid,
+        attempt: WebhookAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookDeliveryStoreError>;
+
+    async fn get_status(&self, id: Uuid) -> Result<OutboxStatus, WebhookDeliveryStoreError>;
+
+    // Every subscriber's delivery of an event
+    async fn get_deliveries(
+        &self,
+        event_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, WebhookDeliveryStoreError>;
+
+    // The delivery log, oldest attempt first
+    async fn get_attempts(
+        &self,
+        id: Uuid,
+    ) -> Result<Vec<WebhookAttempt>, WebhookDeliveryStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookDeliveryStoreError {
+    #[error("Webhook delivery not found")]
+    DeliveryNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
 #[derive(Debug, Clone)]
 pub struct LoginAttemptId(SecretString);
 
@@ -114,6 +442,34 @@ impl PartialEq for LoginAttemptId {
     }
 }
 
+// What a 2FA code was sent for. A code may only be redeemed for that, so e.g. the code
+// confirming that 2FA is being turned off can't complete a login.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TwoFACodePurpose {
+    Login,
+    Enable2FA,
+    Disable2FA,
+}
+
+impl TwoFACodePurpose {
+    pub fn parse(purpose: &str) -> Result<Self> {
+        match purpose {
+            "login" => Ok(Self::Login),
+            "enable_2fa" => Ok(Self::Enable2FA),
+            "disable_2fa" => Ok(Self::Disable2FA),
+            _ => Err(eyre!("{purpose} is not a valid 2FA code purpose")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::Enable2FA => "enable_2fa",
+            Self::Disable2FA => "disable_2fa",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TwoFACode(SecretString);
 
@@ -146,3 +502,53 @@ impl PartialEq for TwoFACode {
         self.0.expose_secret() == other.0.expose_secret()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_allow_resend_after_cooldown() {
+        let now = Utc::now();
+        let cooldown = chrono::Duration::seconds(30);
+
+        let status = ResendStatus {
+            last_sent_at: now - cooldown,
+            resend_count: 0,
+        };
+
+        assert!(status.check_resend(now, cooldown, 3).is_ok());
+    }
+
+    #[test]
+    fn should_reject_resend_during_cooldown() {
+        let now = Utc::now();
+        let cooldown = chrono::Duration::seconds(30);
+
+        let status = ResendStatus {
+            last_sent_at: now - chrono::Duration::seconds(29),
+            resend_count: 0,
+        };
+
+        assert!(matches!(
+            status.check_resend(now, cooldown, 3),
+            Err(ResendError::Cooldown)
+        ));
+    }
+
+    #[test]
+    fn should_reject_resend_past_limit() {
+        let now = Utc::now();
+        let cooldown = chrono::Duration::seconds(30);
+
+        let status = ResendStatus {
+            last_sent_at: now - chrono::Duration::hours(1),
+            resend_count: 3,
+        };
+
+        assert!(matches!(
+            status.check_resend(now, cooldown, 3),
+            Err(ResendError::TooManyResends)
+        ));
+    }
+}
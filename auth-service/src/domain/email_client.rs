@@ -1,9 +1,31 @@
 use async_trait::async_trait;
 use color_eyre::eyre::Result;
+use thiserror::Error;
 
 use super::Email;
 
 #[async_trait]
 pub trait EmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()>;
 }
+
+// Lets the client be picked at startup, as a `Box<dyn EmailClient + Send + Sync>`
+#[async_trait]
+impl<T: EmailClient + Send + Sync + ?Sized> EmailClient for Box<T> {
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()> {
+        (**self).send_email(recipient, content).await
+    }
+}
+
+// A rendered email, with HTML and plain-text versions of the same message
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailContent {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+// Returned instead of sending to an address that hard-bounced before
+#[derive(Debug, Error)]
+#[error("Email address is suppressed after a hard bounce")]
+pub struct EmailSuppressed;
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
+
+use super::Email;
+
+// Something the email provider reported about an email sent to a user
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailEvent {
+    pub id: Uuid,
+    pub email: Email,
+    pub kind: EmailEventKind,
+    // The provider's description, e.g. why the email bounced
+    pub details: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl EmailEvent {
+    pub fn new(
+        email: Email,
+        kind: EmailEventKind,
+        details: Option<String>,
+        occurred_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            email,
+            kind,
+            details,
+            occurred_at,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmailEventKind {
+    Delivery,
+    // The address doesn't exist or permanently rejects email
+    HardBounce,
+    // A temporary failure, e.g. a full inbox
+    SoftBounce,
+    SpamComplaint,
+    // An operator lifted the suppression a hard bounce put on the address
+    SuppressionCleared,
+}
+
+impl EmailEventKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "delivery" => Ok(Self::Delivery),
+            "hard_bounce" => Ok(Self::HardBounce),
+            "soft_bounce" => Ok(Self::SoftBounce),
+            "spam_complaint" => Ok(Self::SpamComplaint),
+            "suppression_cleared" => Ok(Self::SuppressionCleared),
+            _ => Err(eyre!("{kind} is not a valid email event kind")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Delivery => "delivery",
+            Self::HardBounce => "hard_bounce",
+            Self::SoftBounce => "soft_bounce",
+            Self::SpamComplaint => "spam_complaint",
+            Self::SuppressionCleared => "suppression_cleared",
+        }
+    }
+}
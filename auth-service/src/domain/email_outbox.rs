@@ -0,0 +1,116 @@
+use color_eyre::eyre::{eyre, Result};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{Email, EmailContent};
+
+// An email waiting in the outbox to be delivered by the background worker
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    // Identifies the email by recipient and content, so queuing it twice sends it once
+    pub idempotency_key: String,
+    pub recipient: Email,
+    pub content: EmailContent,
+    // Delivery attempts so far, including one in progress
+    pub attempts: u32,
+    // The W3C `traceparent` of the request that queued the email, so delivery continues its trace
+    pub traceparent: Option<String>,
+}
+
+impl OutboxEmail {
+    pub fn new(recipient: Email, content: EmailContent) -> Self {
+        let mut hasher = Sha256::new();
+
+        for part in [
+            recipient.as_ref().expose_secret(),
+            &content.subject,
+            &content.html_body,
+            &content.text_body,
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+
+        Self {
+            id: Uuid::new_v4(),
+            idempotency_key: format!("{:x}", hasher.finalize()),
+            recipient,
+            content,
+            attempts: 0,
+            traceparent: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    // Delivery failed too many times and won't be retried
+    Dead,
+}
+
+impl OutboxStatus {
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "dead" => Ok(Self::Dead),
+            _ => Err(eyre!("{status} is not a valid outbox status")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(subject: &str) -> EmailContent {
+        EmailContent {
+            subject: subject.to_owned(),
+            html_body: "<p>Hello</p>".to_owned(),
+            text_body: "Hello".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_idempotency_key_identifies_recipient_and_content() {
+        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
+        let other_email = Email::parse("other@example.com".to_owned().into()).unwrap();
+
+        let key = OutboxEmail::new(email.clone(), content("Hi")).idempotency_key;
+
+        assert_eq!(
+            OutboxEmail::new(email.clone(), content("Hi")).idempotency_key,
+            key
+        );
+        assert_ne!(OutboxEmail::new(email, content("Bye")).idempotency_key, key);
+        assert_ne!(
+            OutboxEmail::new(other_email, content("Hi")).idempotency_key,
+            key
+        );
+    }
+
+    #[test]
+    fn test_parse_outbox_status() {
+        for status in [
+            OutboxStatus::Pending,
+            OutboxStatus::Sent,
+            OutboxStatus::Dead,
+        ] {
+            assert_eq!(OutboxStatus::parse(status.as_str()).unwrap(), status);
+        }
+
+        assert!(OutboxStatus::parse("failed").is_err());
+    }
+}
@@ -13,6 +13,36 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Invalid CSRF token")]
+    InvalidCsrfToken,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Device not found")]
+    DeviceNotFound,
+    #[error("2FA code resent too recently")]
+    ResendCooldown,
+    #[error("Too many 2FA code resends")]
+    TooManyResends,
+    #[error("Invalid phone number")]
+    InvalidPhoneNumber,
+    #[error("Invalid verification code")]
+    InvalidVerificationCode,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    #[error("2FA is already enabled")]
+    TwoFAAlreadyEnabled,
+    #[error("2FA is not enabled")]
+    TwoFANotEnabled,
+    #[error("Re-authentication required")]
+    ReauthenticationRequired,
+    #[error("Email address is undeliverable")]
+    EmailUndeliverable,
+    #[error("Invalid webhook credentials")]
+    InvalidWebhookCredentials,
+    #[error("Invalid webhook payload")]
+    InvalidWebhookPayload,
+    #[error("Invalid audit event query")]
+    InvalidAuditQuery,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
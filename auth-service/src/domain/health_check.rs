@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+
+// A dependency `/health/ready` reports on
+#[async_trait]
+pub trait HealthCheck {
+    fn name(&self) -> &str;
+
+    // Whether the service can't handle requests while the dependency is down
+    fn is_critical(&self) -> bool {
+        true
+    }
+
+    async fn check(&self) -> Result<()>;
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HealthCheckReport {
+    pub name: String,
+    pub status: HealthStatus,
+    pub critical: bool,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -1,16 +1,37 @@
+mod audit_event;
 pub mod data_stores;
 pub mod email;
 mod email_client;
+mod email_event;
+mod email_outbox;
 mod error;
+mod health_check;
 mod password;
+mod phone_number;
+mod session;
+mod sms_client;
+mod trusted_device;
 mod user;
+mod webhook;
 
+pub use audit_event::{AuditEvent, AuditEventFilter, AuditEventKind};
 pub use data_stores::{
-    BannedTokenStore, BannedTokenStoreError, LoginAttemptId, TwoFACode, TwoFACodeStore,
-    TwoFACodeStoreError, UserStore, UserStoreError,
+    AuditSink, AuditSinkError, BannedTokenStore, BannedTokenStoreError, EmailEventStore,
+    EmailEventStoreError, EmailOutboxStore, EmailOutboxStoreError, LoginAttemptId, ResendError,
+    ResendStatus, SessionStore, SessionStoreError, TrustedDeviceStore, TrustedDeviceStoreError,
+    TwoFACode, TwoFACodePurpose, TwoFACodeStore, TwoFACodeStoreError, UserStore, UserStoreError,
+    WebhookDeliveryStore, WebhookDeliveryStoreError,
 };
 pub use email::Email;
-pub use email_client::EmailClient;
+pub use email_client::{EmailClient, EmailContent, EmailSuppressed};
+pub use email_event::{EmailEvent, EmailEventKind};
+pub use email_outbox::{OutboxEmail, OutboxStatus};
 pub use error::AuthAPIError;
+pub use health_check::{HealthCheck, HealthCheckReport, HealthStatus};
 pub use password::Password;
-pub use user::User;
+pub use phone_number::PhoneNumber;
+pub use session::{Session, SessionId};
+pub use sms_client::SmsClient;
+pub use trusted_device::{DeviceId, TrustedDevice};
+pub use user::{Locale, TwoFAChannel, User};
+pub use webhook::{WebhookAttempt, WebhookDelivery, WebhookEventKind, WebhookSubscription};
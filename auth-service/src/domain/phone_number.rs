@@ -0,0 +1,107 @@
+use std::hash::Hash;
+
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, SecretString};
+
+// Phone number in E.164 format, e.g. +14155552671
+#[derive(Clone, Debug)]
+pub struct PhoneNumber(SecretString);
+
+impl PhoneNumber {
+    // Spaces, dashes, dots and parentheses are accepted as separators and stripped
+    pub fn parse(phone_number: SecretString) -> Result<Self> {
+        let normalized: String = phone_number
+            .expose_secret()
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+
+        if !is_e164(&normalized) {
+            return Err(eyre!(
+                "{} is not a valid phone number",
+                phone_number.expose_secret()
+            ));
+        }
+
+        Ok(Self(normalized.into()))
+    }
+}
+
+fn is_e164(phone_number: &str) -> bool {
+    let Some(digits) = phone_number.strip_prefix('+') else {
+        return false;
+    };
+
+    (MIN_DIGITS..=MAX_DIGITS).contains(&digits.len())
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && !digits.starts_with('0')
+}
+
+const MIN_DIGITS: usize = 8;
+const MAX_DIGITS: usize = 15;
+
+impl AsRef<SecretString> for PhoneNumber {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Hash for PhoneNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state)
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+
+    use super::*;
+
+    #[quickcheck]
+    fn should_parse_valid_phone_number(country_code: u8, subscriber: u64) -> TestResult {
+        let phone_number = format!("+{}{:08}", country_code % 99 + 1, subscriber % 100_000_000);
+
+        TestResult::from_bool(PhoneNumber::parse(phone_number.into()).is_ok())
+    }
+
+    #[quickcheck]
+    fn should_fail_to_parse_invalid_phone_number(phone_number: String) -> TestResult {
+        if phone_number.starts_with('+') {
+            return TestResult::discard();
+        }
+
+        TestResult::from_bool(PhoneNumber::parse(phone_number.into()).is_err())
+    }
+
+    #[test]
+    fn should_strip_separators() {
+        let phone_number = PhoneNumber::parse("+1 (415) 555-2671".to_owned().into()).unwrap();
+        assert_eq!(phone_number.as_ref().expose_secret(), "+14155552671");
+    }
+
+    #[test]
+    fn should_fail_to_parse_malformed_phone_numbers() {
+        for phone_number in [
+            "+",
+            "+1234567",
+            "+0123456789",
+            "+1234567890123456",
+            "+1415abc2671",
+        ] {
+            assert!(
+                PhoneNumber::parse(phone_number.to_owned().into()).is_err(),
+                "Parsed {phone_number}"
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Result};
+use secrecy::{ExposeSecret, SecretString};
+use uuid::Uuid;
+
+use super::Email;
+
+// Server-side record of a persistent ("remember me") login, which can be revoked
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: SessionId,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn new(email: Email, ttl: chrono::Duration) -> Self {
+        let created_at = Utc::now();
+
+        Self {
+            id: SessionId::default(),
+            email,
+            created_at,
+            expires_at: created_at + ttl,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionId(SecretString);
+
+impl SessionId {
+    pub fn parse(id: SecretString) -> Result<Self> {
+        Uuid::parse_str(id.expose_secret()).wrap_err("Invalid session id")?;
+        Ok(Self(id))
+    }
+}
+
+impl Default for SessionId {
+    fn default() -> Self {
+        Self(Uuid::new_v4().to_string().into())
+    }
+}
+
+impl AsRef<SecretString> for SessionId {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl PartialEq for SessionId {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_default_session_id() {
+        assert!(SessionId::parse(SessionId::default().as_ref().to_owned()).is_ok())
+    }
+
+    #[test]
+    fn should_fail_to_parse_invalid_session_id() {
+        assert!(SessionId::parse("not-a-uuid".to_owned().into()).is_err())
+    }
+
+    #[test]
+    fn should_expire_after_ttl() {
+        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
+        let session = Session::new(email.clone(), chrono::Duration::days(30));
+        assert!(!session.is_expired());
+        assert_eq!(
+            session.expires_at - session.created_at,
+            chrono::Duration::days(30)
+        );
+
+        let session = Session::new(email, chrono::Duration::zero());
+        assert!(session.is_expired());
+    }
+}
@@ -0,0 +1,9 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Result;
+
+use super::PhoneNumber;
+
+#[async_trait]
+pub trait SmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<()>;
+}
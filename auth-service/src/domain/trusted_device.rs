@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Result};
+use secrecy::{ExposeSecret, SecretString};
+use uuid::Uuid;
+
+use super::Email;
+
+// Device on which a user completed 2FA and asked not to be prompted again, which can be revoked
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustedDevice {
+    pub id: DeviceId,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TrustedDevice {
+    pub fn new(email: Email, ttl: chrono::Duration) -> Self {
+        let created_at = Utc::now();
+
+        Self {
+            id: DeviceId::default(),
+            email,
+            created_at,
+            expires_at: created_at + ttl,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceId(SecretString);
+
+impl DeviceId {
+    pub fn parse(id: SecretString) -> Result<Self> {
+        Uuid::parse_str(id.expose_secret()).wrap_err("Invalid device id")?;
+        Ok(Self(id))
+    }
+}
+
+impl Default for DeviceId {
+    fn default() -> Self {
+        Self(Uuid::new_v4().to_string().into())
+    }
+}
+
+impl AsRef<SecretString> for DeviceId {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl PartialEq for DeviceId {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_default_device_id() {
+        assert!(DeviceId::parse(DeviceId::default().as_ref().to_owned()).is_ok())
+    }
+
+    #[test]
+    fn should_fail_to_parse_invalid_device_id() {
+        assert!(DeviceId::parse("not-a-uuid".to_owned().into()).is_err())
+    }
+
+    #[test]
+    fn should_expire_after_ttl() {
+        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
+        let device = TrustedDevice::new(email.clone(), chrono::Duration::days(30));
+        assert!(!device.is_expired());
+
+        let device = TrustedDevice::new(email, chrono::Duration::zero());
+        assert!(device.is_expired());
+    }
+}
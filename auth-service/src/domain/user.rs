@@ -1,10 +1,18 @@
-use super::{Email, Password};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{Email, Password, PhoneNumber};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    // Only set once the user has proven they own the number
+    pub phone_number: Option<PhoneNumber>,
+    pub two_fa_channel: TwoFAChannel,
+    // Language of the emails sent to the user
+    pub locale: Locale,
 }
 
 impl User {
@@ -13,6 +21,60 @@ impl User {
             email,
             password,
             requires_2fa,
+            phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+// Where the user receives their 2FA codes
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn parse(channel: &str) -> Result<Self> {
+        match channel {
+            "email" => Ok(Self::Email),
+            "sms" => Ok(Self::Sms),
+            _ => Err(eyre!("{channel} is not a valid 2FA channel")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Sms => "sms",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub fn parse(locale: &str) -> Result<Self> {
+        match locale {
+            "en" => Ok(Self::En),
+            "es" => Ok(Self::Es),
+            _ => Err(eyre!("{locale} is not a supported locale")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
         }
     }
 }
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{AuditEvent, AuditEventKind};
+
+// What downstream systems, e.g. the CRM or fraud detection, can subscribe to
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub enum WebhookEventKind {
+    UserCreated,
+    // A token was issued after checking the password, and the 2FA code if required
+    UserLogin,
+    TwoFAEnabled,
+    SessionRevoked,
+}
+
+impl WebhookEventKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "user.created" => Ok(Self::UserCreated),
+            "user.login" => Ok(Self::UserLogin),
+            "user.2fa_enabled" => Ok(Self::TwoFAEnabled),
+            "session.revoked" => Ok(Self::SessionRevoked),
+            _ => Err(eyre!("{kind} is not a valid webhook event")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UserCreated => "user.created",
+            Self::UserLogin => "user.login",
+            Self::TwoFAEnabled => "user.2fa_enabled",
+            Self::SessionRevoked => "session.revoked",
+        }
+    }
+
+    // The webhook event an audit event is published as, if any. Logins completed with a
+    // 2FA code and re-authentications issue a token too, so they count as logins.
+    pub fn from_audit_event(kind: AuditEventKind) -> Option<Self> {
+        match kind {
+            AuditEventKind::Signup => Some(Self::UserCreated),
+            AuditEventKind::LoginSucceeded
+            | AuditEventKind::TwoFACodeVerified
+            | AuditEventKind::Reauthenticated => Some(Self::UserLogin),
+            AuditEventKind::TwoFAEnabled => Some(Self::TwoFAEnabled),
+            AuditEventKind::SessionRevoked => Some(Self::SessionRevoked),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<String> for WebhookEventKind {
+    type Error = Report;
+
+    fn try_from(kind: String) -> Result<Self> {
+        Self::parse(&kind)
+    }
+}
+
+// An endpoint events are POSTed to, configured in `webhooks.subscriptions`
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookSubscription {
+    pub url: String,
+    // Shared with the subscriber, who checks each request's signature with it
+    pub secret: SecretString,
+    pub events: Vec<WebhookEventKind>,
+}
+
+impl WebhookSubscription {
+    pub fn is_subscribed(&self, kind: WebhookEventKind) -> bool {
+        self.events.contains(&kind)
+    }
+
+    pub fn has_secret(&self) -> bool {
+        !self.secret.expose_secret().is_empty()
+    }
+}
+
+// An event waiting to be POSTed to one subscriber by the background worker
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    // The audit event published, the same for every subscriber, so receivers can
+    // recognise a retry of an event they already handled
+    pub event_id: Uuid,
+    pub kind: WebhookEventKind,
+    pub url: String,
+    // The JSON body, built when the event is queued so every attempt sends the same bytes
+    pub payload: String,
+    // Delivery attempts so far, including one in progress
+    pub attempts: u32,
+    // The W3C `traceparent` of the request that caused the event
+    pub traceparent: Option<String>,
+}
+
+impl WebhookDelivery {
+    pub fn new(url: String, kind: WebhookEventKind, event: &AuditEvent) -> Self {
+        let payload = serde_json::json!({
+            "id": event.id,
+            "type": kind.as_str(),
+            "occurredAt": event.occurred_at.to_rfc3339(),
+            "data": {
+                "email": event.email.as_ref().map(|email| email.as_ref().expose_secret()),
+                "ipAddress": event.ip_address,
+                "userAgent": event.user_agent,
+            },
+        });
+
+        Self {
+            id: Uuid::new_v4(),
+            event_id: event.id,
+            kind,
+            url,
+            payload: payload.to_string(),
+            attempts: 0,
+            traceparent: None,
+        }
+    }
+}
+
+// One try at delivering a webhook, kept in the delivery log
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookAttempt {
+    pub attempted_at: DateTime<Utc>,
+    // The subscriber's response status, missing when no response came back
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Email;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_webhook_event_kind() {
+        for kind in [
+            WebhookEventKind::UserCreated,
+            WebhookEventKind::UserLogin,
+            WebhookEventKind::TwoFAEnabled,
+            WebhookEventKind::SessionRevoked,
+        ] {
+            assert_eq!(WebhookEventKind::parse(kind.as_str()).unwrap(), kind);
+        }
+
+        assert!(WebhookEventKind::parse("user.deleted").is_err());
+    }
+
+    #[test]
+    fn test_payload_describes_the_event() {
+        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
+        let event = AuditEvent {
+            ip_address: Some([203, 0, 113, 7].into()),
+            ..AuditEvent::new(AuditEventKind::LoginSucceeded, Some(email))
+        };
+
+        let delivery = WebhookDelivery::new(
+            "https://crm.example.com/hooks".to_owned(),
+            WebhookEventKind::UserLogin,
+            &event,
+        );
+        let payload: serde_json::Value = serde_json::from_str(&delivery.payload).unwrap();
+
+        assert_eq!(delivery.event_id, event.id);
+        assert_eq!(payload["id"], event.id.to_string());
+        assert_eq!(payload["type"], "user.login");
+        assert_eq!(payload["data"]["email"], "test@example.com");
+        assert_eq!(payload["data"]["ipAddress"], "203.0.113.7");
+        assert!(payload["data"]["userAgent"].is_null());
+    }
+}
@@ -1,27 +1,50 @@
-use std::error::Error;
+use std::{
+    error::Error,
+    future::{Future, IntoFuture},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::StatusCode,
+    middleware,
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post, put},
     serve::Serve,
-    Json, Router,
+    Extension, Json, Router,
+};
+use domain::{
+    AuditSink, AuthAPIError, BannedTokenStore, EmailEventStore, SessionStore, TrustedDeviceStore,
+    UserStore,
 };
-use domain::{AuthAPIError, BannedTokenStore, UserStore};
 use redis::{Client, RedisResult};
 use routes::{signup, verify_token};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use tokio::net::TcpListener;
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use utils::constants::AUTH_SERVICE_IP;
+use tokio::{net::TcpListener, sync::oneshot, time::Instant};
+use tower_http::{services::ServeDir, trace::TraceLayer};
+use utils::settings::{RedisSettings, Settings};
 
 use crate::{
     app_state::AppState,
-    domain::{EmailClient, TwoFACodeStore},
-    routes::{login, logout, verify_2fa},
-    utils::tracing::{make_span_with_request_id, on_request, on_response},
+    domain::{EmailClient, SmsClient, TwoFACodeStore},
+    routes::{
+        add_phone_number, change_password, clear_email_suppression, disable_2fa, enable_2fa,
+        get_mailbox_email, list_audit_events, list_mailbox, list_sessions, list_trusted_devices,
+        live, login, logout, metrics, postmark_webhook, preview_email, ready, reauthenticate,
+        resend_2fa, revoke_session, revoke_trusted_device, update_two_fa_channel, verify_2fa,
+        verify_disable_2fa, verify_enable_2fa, verify_phone_number,
+    },
+    services::health_checks::HealthChecks,
+    utils::{
+        csrf::csrf_protection,
+        metrics::{label_response, prometheus_handle},
+        request_id::request_id,
+        tracing::{log_error_chain, make_span_with_request_id, on_request, on_response},
+    },
 };
 
 pub mod app_state;
@@ -32,47 +55,120 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<
+        TcpListener,
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        middleware::AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
+    drain_timeout: Duration,
 }
 
 impl Application {
-    pub async fn build<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>(
+    pub async fn build<
+        UserStoreImpl,
+        BannedTokenStoreImpl,
+        TwoFACodeStoreImpl,
+        EmailClientImpl,
+        SessionStoreImpl,
+        TrustedDeviceStoreImpl,
+        SmsClientImpl,
+        EmailEventStoreImpl,
+        AuditSinkImpl,
+    >(
         app_state: AppState<
             UserStoreImpl,
             BannedTokenStoreImpl,
             TwoFACodeStoreImpl,
             EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
         >,
-        address: &str,
+        settings: Settings,
+        health_checks: HealthChecks,
     ) -> Result<Self, Box<dyn Error>>
     where
         UserStoreImpl: UserStore + Send + Sync + 'static,
         BannedTokenStoreImpl: BannedTokenStore + Send + Sync + 'static,
         TwoFACodeStoreImpl: TwoFACodeStore + Send + Sync + 'static,
         EmailClientImpl: EmailClient + Send + Sync + 'static,
+        SessionStoreImpl: SessionStore + Send + Sync + 'static,
+        TrustedDeviceStoreImpl: TrustedDeviceStore + Send + Sync + 'static,
+        SmsClientImpl: SmsClient + Send + Sync + 'static,
+        EmailEventStoreImpl: EmailEventStore + Send + Sync + 'static,
+        AuditSinkImpl: AuditSink + Send + Sync + 'static,
     {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            format!("http://{}:8000", AUTH_SERVICE_IP.as_str()).parse()?,
-        ];
+        let cors = settings.cors.layer();
 
-        let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
-            .allow_credentials(true)
-            .allow_origin(allowed_origins);
+        // Start recording metrics before the first request
+        prometheus_handle();
 
-        let router = Router::new()
+        let mut router = Router::new()
             .fallback_service(ServeDir::new("assets"))
             .route("/signup", post(signup))
             .route("/login", post(login))
             .route("/verify-2fa", post(verify_2fa))
+            .route("/resend-2fa", post(resend_2fa))
             .route("/logout", post(logout))
+            .route("/reauthenticate", post(reauthenticate))
+            .route("/password", put(change_password))
             .route("/verify-token", post(verify_token))
+            .route("/sessions", get(list_sessions))
+            .route("/sessions/{id}", delete(revoke_session))
+            .route("/trusted-devices", get(list_trusted_devices))
+            .route("/trusted-devices/{id}", delete(revoke_trusted_device))
+            .route("/2fa/enable", post(enable_2fa))
+            .route("/2fa/enable/verify", post(verify_enable_2fa))
+            .route("/2fa/disable", post(disable_2fa))
+            .route("/2fa/disable/verify", post(verify_disable_2fa))
+            .route("/health/live", get(live))
+            .route("/health/ready", get(ready));
+
+        if settings.application.dev_mode {
+            router = router
+                .route("/dev/emails/{name}", get(preview_email))
+                .route("/dev/mailbox", get(list_mailbox))
+                .route("/dev/mailbox/{id}", get(get_mailbox_email));
+        }
+
+        if settings.email.postmark.receives_webhooks() {
+            router = router.route("/webhooks/postmark", post(postmark_webhook));
+        }
+
+        if settings.sms.is_enabled() {
+            router = router
+                .route("/phone-number", post(add_phone_number))
+                .route("/phone-number/verify", post(verify_phone_number))
+                .route("/2fa-channel", put(update_two_fa_channel));
+        }
+
+        if settings.admin.is_enabled() {
+            // Metrics reveal traffic and failure patterns, so they're only for operators too
+            router = router
+                .route("/metrics", get(metrics))
+                .route("/admin/audit-events", get(list_audit_events))
+                .route(
+                    "/admin/email-suppressions/{email}",
+                    delete(clear_email_suppression),
+                );
+        }
+
+        let address = settings.application.address.clone();
+        let drain_timeout = settings.application.drain_timeout();
+
+        let router = router
             .with_state(app_state)
+            .layer(middleware::from_fn(csrf_protection))
+            // Handlers and middleware read the settings from the request extensions
+            .layer(Extension(Arc::new(settings)))
+            .layer(Extension(Arc::new(health_checks)))
             .layer(cors)
+            .layer(middleware::from_fn(label_response))
             .layer(
                 // Add a TraceLayer for HTTP requests to enable detailed tracing
                 // This layer will create spans for each request using the make_span_with_request_id function,
@@ -81,17 +177,79 @@ impl Application {
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            // Outermost, so the request span and every response have the request ID
+            .layer(middleware::from_fn(request_id));
 
-        let listener = TcpListener::bind(address).await?;
+        let listener = TcpListener::bind(&address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
-        Ok(Self { server, address })
+        // The peer address is recorded in the audit log
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
+        Ok(Self {
+            server,
+            address,
+            drain_timeout,
+        })
     }
 
     pub async fn run(self) -> Result<(), std::io::Error> {
+        self.run_until_stopped(std::future::pending()).await
+    }
+
+    // Serve until `signal` resolves, then stop accepting connections and give in-flight
+    // requests up to the drain timeout to finish before returning
+    pub async fn run_until_stopped<F>(self, signal: F) -> Result<(), std::io::Error>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let drain_timeout = self.drain_timeout;
+
+        self.run_until_deadline(async move {
+            signal.await;
+            Instant::now() + drain_timeout
+        })
+        .await
+    }
+
+    // Like `run_until_stopped`, but `signal` resolves with the deadline for in-flight requests,
+    // so the caller can share it with whatever else has to wind down
+    pub async fn run_until_deadline<F>(self, signal: F) -> Result<(), std::io::Error>
+    where
+        F: Future<Output = Instant> + Send + 'static,
+    {
         tracing::info!("listening on {}", &self.address);
-        self.server.await
+
+        let (stopping_tx, stopping_rx) = oneshot::channel();
+
+        let server = self
+            .server
+            .with_graceful_shutdown(async move {
+                let _ = stopping_tx.send(signal.await);
+            })
+            .into_future();
+
+        tokio::pin!(server);
+
+        let deadline = tokio::select! {
+            result = &mut server => return result,
+            deadline = stopping_rx => match deadline {
+                Ok(deadline) => deadline,
+                Err(_) => return server.await,
+            },
+        };
+
+        match tokio::time::timeout_at(deadline, server).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!(
+                    "Connections still open at the drain deadline, shutting down anyway"
+                );
+                Ok(())
+            }
+        }
     }
 }
 
@@ -112,6 +270,40 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::InvalidCsrfToken => (StatusCode::FORBIDDEN, "Invalid CSRF token"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::DeviceNotFound => (StatusCode::NOT_FOUND, "Device not found"),
+            AuthAPIError::ResendCooldown => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Please wait before requesting another code",
+            ),
+            AuthAPIError::TooManyResends => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many 2FA code resends")
+            }
+            AuthAPIError::InvalidPhoneNumber => (StatusCode::BAD_REQUEST, "Invalid phone number"),
+            AuthAPIError::InvalidVerificationCode => {
+                (StatusCode::BAD_REQUEST, "Invalid verification code")
+            }
+            AuthAPIError::PhoneNumberNotVerified => {
+                (StatusCode::BAD_REQUEST, "Phone number not verified")
+            }
+            AuthAPIError::TwoFAAlreadyEnabled => (StatusCode::CONFLICT, "2FA is already enabled"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::CONFLICT, "2FA is not enabled"),
+            AuthAPIError::ReauthenticationRequired => {
+                (StatusCode::UNAUTHORIZED, "Re-authentication required")
+            }
+            AuthAPIError::EmailUndeliverable => {
+                (StatusCode::BAD_REQUEST, "Email address is undeliverable")
+            }
+            AuthAPIError::InvalidWebhookCredentials => {
+                (StatusCode::UNAUTHORIZED, "Invalid webhook credentials")
+            }
+            AuthAPIError::InvalidWebhookPayload => {
+                (StatusCode::BAD_REQUEST, "Invalid webhook payload")
+            }
+            AuthAPIError::InvalidAuditQuery => {
+                (StatusCode::BAD_REQUEST, "Invalid audit event query")
+            }
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
@@ -123,20 +315,6 @@ impl IntoResponse for AuthAPIError {
     }
 }
 
-fn log_error_chain(e: &(dyn Error + 'static)) {
-    let separator =
-        "\n-----------------------------------------------------------------------------------\n";
-    let mut report = format!("{}{:?}\n", separator, e);
-    let mut current = e.source();
-    while let Some(cause) = current {
-        let str = format!("Caused by:\n\n{:?}", cause);
-        report = format!("{}\n{}", report, str);
-        current = cause.source();
-    }
-    report = format!("{}\n{}", report, separator);
-    tracing::error!("{}", report);
-}
-
 pub async fn get_postgres_pool(url: &SecretString) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new()
         .max_connections(5)
@@ -148,3 +326,14 @@ pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
 }
+
+// Connect to Redis, with every command bounded by the configured timeout
+pub fn get_redis_connection(settings: &RedisSettings) -> RedisResult<redis::Connection> {
+    let conn = get_redis_client(settings.host_name.clone())?
+        .get_connection_with_timeout(settings.timeout())?;
+
+    conn.set_read_timeout(Some(settings.timeout()))?;
+    conn.set_write_timeout(Some(settings.timeout()))?;
+
+    Ok(conn)
+}
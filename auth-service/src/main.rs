@@ -1,55 +1,206 @@
 use std::sync::Arc;
 
 use auth_service::{
-    app_state::AppState,
-    domain::Email,
-    get_postgres_pool, get_redis_client,
+    app_state::{AppState, EmailEventStoreType, WebhookDeliveryStoreType},
+    domain::EmailClient,
+    get_postgres_pool, get_redis_connection,
     services::{
         data_stores::{
-            postgres_user_store::PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore,
+            postgres_user_store::PostgresUserStore, PostgresAuditSink, PostgresEmailEventStore,
+            PostgresEmailOutboxStore, PostgresWebhookDeliveryStore, RedisBannedTokenStore,
+            RedisSessionStore, RedisTrustedDeviceStore, RedisTwoFACodeStore,
         },
+        email_outbox_worker::EmailOutboxWorker,
+        failover_email_client::FailoverEmailClient,
+        file_email_client::FileEmailClient,
+        health_checks::HealthChecks,
+        outbox_email_client::OutboxEmailClient,
         postmark_email_client::PostmarkEmailClient,
+        smtp_email_client::SmtpEmailClient,
+        twilio_sms_client::TwilioSmsClient,
+        webhook_audit_sink::WebhookAuditSink,
+        webhook_worker::WebhookWorker,
     },
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME},
+        settings::{EmailProvider, Settings},
+        shutdown::shutdown_signal,
         tracing::init_tracing,
     },
     Application,
 };
 use reqwest::Client;
 use sqlx::PgPool;
-use tokio::sync::RwLock;
+use tokio::{
+    sync::{watch, RwLock},
+    time::Instant,
+};
 
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
-    init_tracing().expect("Failed to initialize tracing");
+    let settings = Settings::load().expect("Failed to load settings");
+
+    // `auth-service healthcheck` is the container's health check, the image has no curl
+    if std::env::args().nth(1).as_deref() == Some("healthcheck") {
+        std::process::exit(healthcheck(&settings).await);
+    }
 
-    let pg_pool = configure_postgresql().await;
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let redis_conn = Arc::new(RwLock::new(configure_redis()));
+    let tracing_guard = init_tracing(&settings.telemetry).expect("Failed to initialize tracing");
+
+    let pg_pool = configure_postgresql(&settings).await;
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+    let email_outbox = Arc::new(RwLock::new(PostgresEmailOutboxStore::new(pg_pool.clone())));
+    let email_event_store = Arc::new(RwLock::new(PostgresEmailEventStore::new(pg_pool.clone())));
+    let webhook_deliveries = Arc::new(RwLock::new(PostgresWebhookDeliveryStore::new(
+        pg_pool.clone(),
+    )));
+    // Audit events subscribed to are also published as webhooks, sent in the background
+    let audit_sink = Arc::new(RwLock::new(WebhookAuditSink::new(
+        PostgresAuditSink::new(pg_pool.clone()),
+        webhook_deliveries.clone(),
+        settings.webhooks.subscriptions.clone(),
+    )));
+    let webhook_worker = configure_webhook_worker(&settings, webhook_deliveries);
+    let redis_conn = Arc::new(RwLock::new(configure_redis(&settings)));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
-    let email_client = Arc::new(configure_postmark_email_client());
+    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+    let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+    let trusted_device_store = Arc::new(RwLock::new(RedisTrustedDeviceStore::new(
+        redis_conn.clone(),
+    )));
+    // Emails are queued by the request handlers and delivered in the background
+    let email_client = Arc::new(OutboxEmailClient::new(
+        email_outbox.clone(),
+        email_event_store.clone(),
+    ));
+    let email_outbox_worker = EmailOutboxWorker::new(
+        email_outbox,
+        configure_email_client(&settings, email_event_store.clone()),
+    );
+    let sms_client = Arc::new(configure_twilio_sms_client(&settings));
+    let health_checks = HealthChecks::from_settings(&settings, pg_pool.clone(), redis_conn.clone());
 
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
+        session_store,
+        trusted_device_store,
+        sms_client,
+        email_event_store,
+        audit_sink,
     );
 
-    let _pg_pool = configure_postgresql().await;
+    // Every task that has to wind down on shutdown waits for this to change. It carries one
+    // deadline, set when the signal arrives, which the server drain, the worker flush and closing
+    // the connections all share, so shutdown fits in the orchestrator's grace period.
+    let (shutdown_tx, shutdown_rx) = watch::channel(None);
+    let drain_timeout = settings.application.drain_timeout();
 
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        shutdown_tx.send_replace(Some(Instant::now() + drain_timeout));
+    });
+
+    let email_outbox_worker = tokio::spawn(email_outbox_worker.run(stopped(shutdown_rx.clone())));
+    let webhook_worker = tokio::spawn(webhook_worker.run(stopped(shutdown_rx.clone())));
+
+    let app = Application::build(app_state, settings, health_checks)
         .await
         .expect("Failed to build app");
 
-    app.run().await.expect("Failed to run app");
+    app.run_until_deadline(deadline(shutdown_rx.clone()))
+        .await
+        .expect("Failed to run app");
+
+    let deadline = deadline(shutdown_rx).await;
+
+    if tokio::time::timeout_at(deadline, email_outbox_worker)
+        .await
+        .is_err()
+    {
+        tracing::warn!("Email outbox still flushing at the drain deadline, shutting down anyway");
+    }
+
+    if tokio::time::timeout_at(deadline, webhook_worker)
+        .await
+        .is_err()
+    {
+        tracing::warn!("Webhooks still flushing at the drain deadline, shutting down anyway");
+    }
+
+    close_connections(pg_pool, redis_conn, deadline).await;
+    tracing::info!("Shutdown complete");
+
+    // Last, so the spans of everything above are exported too
+    tracing_guard.shutdown();
+}
+
+// Ask the running service whether it's ready, returning the exit code
+async fn healthcheck(settings: &Settings) -> i32 {
+    let port = settings
+        .application
+        .address
+        .rsplit_once(':')
+        .map_or("3000", |(_, port)| port);
+
+    let response = Client::builder()
+        .timeout(settings.health.timeout() * 2)
+        .build()
+        .expect("Failed to build HTTP client")
+        .get(format!("http://127.0.0.1:{port}/health/ready"))
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => 0,
+        Ok(response) => {
+            eprintln!("Not ready: {}", response.text().await.unwrap_or_default());
+            1
+        }
+        Err(e) => {
+            eprintln!("Health check failed: {e}");
+            1
+        }
+    }
 }
 
-async fn configure_postgresql() -> PgPool {
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
+async fn stopped(mut shutdown_rx: watch::Receiver<Option<Instant>>) {
+    // An error means the sender is gone, which only happens after shutdown
+    let _ = shutdown_rx.changed().await;
+}
+
+// Resolves with the shutdown deadline once the signal has arrived
+async fn deadline(mut shutdown_rx: watch::Receiver<Option<Instant>>) -> Instant {
+    match shutdown_rx.wait_for(Option::is_some).await {
+        Ok(deadline) => deadline.unwrap_or_else(Instant::now),
+        Err(_) => Instant::now(),
+    }
+}
+
+async fn close_connections(
+    pg_pool: PgPool,
+    redis_conn: Arc<RwLock<redis::Connection>>,
+    deadline: Instant,
+) {
+    // Waits for connections still checked out to be returned
+    if tokio::time::timeout_at(deadline, pg_pool.close())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Postgres connections still in use at the drain deadline");
+    }
+
+    // The stores hold the other handles, which are gone once the app has stopped
+    match Arc::try_unwrap(redis_conn) {
+        Ok(redis_conn) => drop(redis_conn.into_inner()),
+        Err(_) => tracing::warn!("Redis connection still in use, leaving it to the OS"),
+    }
+}
+
+async fn configure_postgresql(settings: &Settings) -> PgPool {
+    let pg_pool = get_postgres_pool(&settings.database.url)
         .await
         .expect("Failed to create Postgres connection pool!");
 
@@ -61,23 +212,87 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
+fn configure_redis(settings: &Settings) -> redis::Connection {
+    get_redis_connection(&settings.redis).expect("Failed to get Redis connection")
+}
+
+fn configure_email_client(
+    settings: &Settings,
+    email_event_store: EmailEventStoreType<PostgresEmailEventStore>,
+) -> FailoverEmailClient {
+    let providers = settings
+        .email
+        .providers
+        .iter()
+        .map(|provider| {
+            let client: Box<dyn EmailClient + Send + Sync> = match provider {
+                EmailProvider::Postmark => Box::new(configure_postmark_email_client(
+                    settings,
+                    email_event_store.clone(),
+                )),
+                EmailProvider::Smtp => Box::new(configure_smtp_email_client(settings)),
+                EmailProvider::File => Box::new(FileEmailClient::new(
+                    settings.application.dev_mailbox_dir.clone(),
+                )),
+            };
+
+            (provider.as_str(), client)
+        })
+        .collect();
+
+    FailoverEmailClient::new(providers, settings.email.circuit_breaker())
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
+fn configure_postmark_email_client(
+    settings: &Settings,
+    email_event_store: EmailEventStoreType<PostgresEmailEventStore>,
+) -> PostmarkEmailClient<PostgresEmailEventStore> {
     let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
+        .timeout(settings.email.timeout())
         .build()
         .expect("Failed to build HTTP client");
 
     PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        Email::parse(prod::email_client::SENDER.to_owned().into()).unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
+        settings.email.postmark.base_url.clone(),
+        settings.email.sender.clone(),
+        settings.email.postmark.auth_token.clone(),
+        http_client,
+        email_event_store,
+    )
+}
+
+fn configure_smtp_email_client(settings: &Settings) -> SmtpEmailClient {
+    SmtpEmailClient::new(&settings.email.smtp(), settings.email.sender.clone())
+        .expect("Failed to build SMTP email client")
+}
+
+fn configure_twilio_sms_client(settings: &Settings) -> TwilioSmsClient {
+    let http_client = Client::builder()
+        .timeout(settings.sms.timeout())
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        settings.sms.base_url.clone(),
+        settings.sms.sender.clone(),
+        settings.sms.twilio.account_sid.clone(),
+        settings.sms.twilio.auth_token.clone(),
+        http_client,
+    )
+}
+
+fn configure_webhook_worker(
+    settings: &Settings,
+    webhook_deliveries: WebhookDeliveryStoreType<PostgresWebhookDeliveryStore>,
+) -> WebhookWorker<PostgresWebhookDeliveryStore> {
+    let http_client = Client::builder()
+        .timeout(settings.webhooks.timeout())
+        .build()
+        .expect("Failed to build HTTP client");
+
+    WebhookWorker::new(
+        webhook_deliveries,
+        settings.webhooks.subscriptions.clone(),
         http_client,
     )
 }
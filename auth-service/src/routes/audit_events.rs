@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{rejection::QueryRejection, Extension, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{AuditEvent, AuditEventFilter, AuditEventKind, AuditSink, AuthAPIError, Email},
+    utils::{
+        auth::require_admin_token,
+        constants::{AUDIT_EVENTS_DEFAULT_LIMIT, AUDIT_EVENTS_MAX_LIMIT},
+        settings::Settings,
+    },
+    AppState,
+};
+
+// Search the audit log, newest events first. Only for operators holding the admin API token.
+#[tracing::instrument(name = "List audit events", skip_all)]
+pub async fn list_audit_events<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    headers: HeaderMap,
+    query: Result<Query<AuditEventsQuery>, QueryRejection>,
+) -> Result<Json<AuditEventsResponse>, AuthAPIError>
+where
+    AuditSinkImpl: AuditSink,
+{
+    require_admin_token(&headers, &settings.admin)?;
+
+    let Query(query) = query.map_err(|_| AuthAPIError::InvalidAuditQuery)?;
+    let filter = query.filter()?;
+
+    let events = state
+        .audit_sink
+        .read()
+        .await
+        .query(&filter)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(AuditEventsResponse {
+        events: events.iter().map(AuditEventResponse::new).collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct AuditEventsQuery {
+    pub email: Option<SecretString>,
+    pub kind: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+impl AuditEventsQuery {
+    fn filter(self) -> Result<AuditEventFilter, AuthAPIError> {
+        let invalid = |_| AuthAPIError::InvalidAuditQuery;
+
+        let limit = self.limit.unwrap_or(AUDIT_EVENTS_DEFAULT_LIMIT);
+        if !(1..=AUDIT_EVENTS_MAX_LIMIT).contains(&limit) {
+            return Err(AuthAPIError::InvalidAuditQuery);
+        }
+
+        Ok(AuditEventFilter {
+            email: self.email.map(Email::parse).transpose().map_err(invalid)?,
+            kind: self
+                .kind
+                .as_deref()
+                .map(AuditEventKind::parse)
+                .transpose()
+                .map_err(invalid)?,
+            since: self.since,
+            until: self.until,
+            limit,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditEventsResponse {
+    pub events: Vec<AuditEventResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditEventResponse {
+    pub id: String,
+    pub kind: String,
+    pub email: Option<String>,
+    pub reason: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: String,
+}
+
+impl AuditEventResponse {
+    fn new(event: &AuditEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            kind: event.kind.as_str().to_owned(),
+            email: event
+                .email
+                .as_ref()
+                .map(|email| email.as_ref().expose_secret().to_owned()),
+            reason: event.reason.clone(),
+            ip_address: event.ip_address.map(|ip| ip.to_string()),
+            user_agent: event.user_agent.clone(),
+            request_id: event.request_id.clone(),
+            occurred_at: event.occurred_at.to_rfc3339(),
+        }
+    }
+}
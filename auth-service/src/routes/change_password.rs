@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, Password, SessionStore,
+        TrustedDeviceStore, UserStore,
+    },
+    routes::{issue_auth_token, TokenDelivery},
+    utils::{
+        audit::AuditContext,
+        auth::{require_recent_auth, validate_token, AuthToken},
+        settings::Settings,
+    },
+    AppState,
+};
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    jar: CookieJar,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+    TrustedDeviceStoreImpl: TrustedDeviceStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.clone().into()).map_err(AuthAPIError::UnexpectedError)?;
+
+    let password =
+        Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    require_recent_auth(&claims, user.requires_2fa, &settings.auth)?;
+
+    user_store
+        .update_password(&email, password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    // Tokens without a session can't be removed one by one, so revoke all those issued so far
+    state
+        .banned_token_store
+        .write()
+        .await
+        .revoke_tokens_before(&email, Utc::now())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::PasswordChanged,
+            Some(&email),
+        )
+        .await;
+
+    // Whoever knew the old password may still be signed in elsewhere, so end every other
+    // session and stop trusting the user's devices, this one included
+    let mut session_store = state.session_store.write().await;
+
+    let sessions = session_store
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let other_sessions = sessions
+        .iter()
+        .filter(|session| claims.sid.as_deref() != Some(session.id.as_ref().expose_secret()));
+
+    for session in other_sessions {
+        session_store
+            .remove_session(&session.id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        audit
+            .record(
+                &state.audit_sink,
+                AuditEventKind::SessionRevoked,
+                Some(&email),
+            )
+            .await;
+    }
+
+    drop(session_store);
+
+    let mut trusted_device_store = state.trusted_device_store.write().await;
+
+    let devices = trusted_device_store
+        .get_devices(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    for device in &devices {
+        trusted_device_store
+            .remove_device(&device.id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        audit
+            .record(
+                &state.audit_sink,
+                AuditEventKind::TrustedDeviceRevoked,
+                Some(&email),
+            )
+            .await;
+    }
+
+    drop(trusted_device_store);
+
+    // Keep this client signed in, the same way, if the token it used was just revoked
+    let (jar, token) = if claims.sid.is_some() {
+        (jar, None)
+    } else {
+        let token_delivery = match auth_token {
+            AuthToken::Cookie(_) => TokenDelivery::Cookie,
+            AuthToken::Bearer(_) => TokenDelivery::Body,
+        };
+
+        let (jar, response) = issue_auth_token(
+            &email,
+            &claims.amr,
+            token_delivery,
+            false,
+            &state.session_store,
+            jar,
+            &settings.auth,
+        )
+        .await?;
+
+        (jar, response.map(|response| response.token))
+    };
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed successfully!".to_owned(),
+        token,
+    });
+
+    Ok((StatusCode::OK, jar, response))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "newPassword")]
+    pub new_password: SecretString,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+    // A new bearer token replacing the one the request was made with, which is revoked along
+    // with the others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
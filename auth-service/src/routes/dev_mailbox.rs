@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    domain::AuthAPIError,
+    routes::PreviewFormat,
+    services::file_email_client::{CapturedEmail, FileEmailClient},
+    utils::settings::Settings,
+};
+
+// List the emails saved by the file email client. Only served in dev mode.
+#[tracing::instrument(name = "List mailbox", skip_all)]
+pub async fn list_mailbox(
+    Extension(settings): Extension<Arc<Settings>>,
+) -> Result<Html<String>, AuthAPIError> {
+    let emails = FileEmailClient::new(settings.application.dev_mailbox_dir.clone())
+        .list_emails()
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let page = MailboxHtml { emails: &emails }
+        .render()
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Html(page))
+}
+
+// Render a saved email, e.g. `/dev/mailbox/{id}?format=text`
+#[tracing::instrument(name = "Get mailbox email", skip_all)]
+pub async fn get_mailbox_email(
+    Extension(settings): Extension<Arc<Settings>>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<MailboxEmailParams>,
+) -> Result<Response, AuthAPIError> {
+    let Some(email) = FileEmailClient::new(settings.application.dev_mailbox_dir.clone())
+        .get_email(id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let response = match params.format {
+        PreviewFormat::Html => Html(email.html_body).into_response(),
+        PreviewFormat::Text => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            email.text_body,
+        )
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct MailboxEmailParams {
+    #[serde(default)]
+    pub format: PreviewFormat,
+}
+
+#[derive(Template)]
+#[template(path = "dev/mailbox.html")]
+struct MailboxHtml<'a> {
+    emails: &'a [CapturedEmail],
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::Utc;
+use secrecy::SecretString;
+
+use crate::{
+    domain::{AuthAPIError, Email, EmailEvent, EmailEventKind, EmailEventStore},
+    utils::{auth::require_admin_token, settings::Settings},
+    AppState,
+};
+
+// Lift the suppression a hard bounce put on an address, e.g. once the user fixed their mailbox.
+// Only for operators holding the admin API token.
+#[tracing::instrument(name = "Clear email suppression", skip_all)]
+pub async fn clear_email_suppression<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    headers: HeaderMap,
+    Path(email): Path<SecretString>,
+) -> Result<StatusCode, AuthAPIError>
+where
+    EmailEventStoreImpl: EmailEventStore,
+{
+    require_admin_token(&headers, &settings.admin)?;
+
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut email_event_store = state.email_event_store.write().await;
+
+    let suppressed = email_event_store
+        .is_suppressed(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Nothing to clear, and addresses without a user can't have events
+    if !suppressed {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    tracing::info!("Clearing suppression of hard-bounced address");
+
+    email_event_store
+        .add_event(EmailEvent::new(
+            email,
+            EmailEventKind::SuppressionCleared,
+            None,
+            Utc::now(),
+        ))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
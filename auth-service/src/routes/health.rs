@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{HealthCheckReport, HealthStatus},
+    services::health_checks::HealthChecks,
+};
+
+// Whether the process is up and serving requests. Doesn't touch any dependency, so a restart
+// is only triggered when the service itself is stuck.
+pub async fn live() -> impl IntoResponse {
+    Json(LivenessResponse { status: "live" })
+}
+
+// Whether the service can handle requests, i.e. every critical dependency is reachable
+#[tracing::instrument(name = "Readiness check", skip_all)]
+pub async fn ready(Extension(health_checks): Extension<Arc<HealthChecks>>) -> impl IntoResponse {
+    let checks = health_checks.run().await;
+
+    let is_ready = checks
+        .iter()
+        .all(|check| !check.critical || check.status == HealthStatus::Up);
+
+    for check in checks.iter().filter(|c| c.status == HealthStatus::Down) {
+        tracing::warn!(
+            "Health check {} failed: {}",
+            check.name,
+            check.error.as_deref().unwrap_or_default()
+        );
+    }
+
+    let (status, status_code) = if is_ready {
+        ("ready", StatusCode::OK)
+    } else {
+        ("not_ready", StatusCode::SERVICE_UNAVAILABLE)
+    };
+
+    (
+        status_code,
+        Json(ReadinessResponse {
+            status: status.to_owned(),
+            checks,
+        }),
+    )
+}
+
+#[derive(Serialize)]
+struct LivenessResponse {
+    status: &'static str,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReadinessResponse {
+    pub status: String,
+    pub checks: Vec<HealthCheckReport>,
+}
@@ -1,96 +1,207 @@
-use axum::{extract::State, http::StatusCode, Json};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
 use axum_extra::extract::CookieJar;
 use color_eyre::eyre::eyre;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    app_state::{EmailClientType, TwoFACodeStoreType},
+    app_state::{EmailClientType, SessionStoreType, SmsClientType, TwoFACodeStoreType},
     domain::{
-        data_stores::LoginAttemptId, AuthAPIError, Email, EmailClient, Password, TwoFACode,
-        TwoFACodeStore, UserStore,
+        data_stores::{LoginAttemptId, TwoFACodeStoreError},
+        AuditEventKind, AuditSink, AuthAPIError, Email, EmailClient, EmailSuppressed, Password,
+        Session, SessionStore, SmsClient, TrustedDeviceStore, TwoFAChannel, TwoFACode,
+        TwoFACodePurpose, TwoFACodeStore, User, UserStore,
+    },
+    utils::{
+        audit::AuditContext,
+        auth::{
+            create_auth_cookie, create_persistent_auth_cookie, generate_auth_token,
+            generate_session_token, AuthMethod,
+        },
+        constants::MAX_TWO_FA_CODE_ATTEMPTS,
+        csrf::generate_csrf_cookie,
+        email_templates::EmailTemplate,
+        metrics::{record_2fa_code, record_login, LoginFailure, LoginOutcome, TwoFACodeEvent},
+        settings::{AuthSettings, Settings},
+        trusted_device::validate_trusted_device,
     },
-    utils::auth::generate_auth_cookie,
     AppState,
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
-pub async fn login<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>(
+pub async fn login<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
     State(state): State<
-        AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>,
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
     >,
+    Extension(settings): Extension<Arc<Settings>>,
     jar: CookieJar,
+    audit: AuditContext,
     Json(request): Json<LoginRequest>,
 ) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError>
 where
     UserStoreImpl: UserStore,
     TwoFACodeStoreImpl: TwoFACodeStore,
     EmailClientImpl: EmailClient,
+    SessionStoreImpl: SessionStore,
+    TrustedDeviceStoreImpl: TrustedDeviceStore,
+    SmsClientImpl: SmsClient,
+    AuditSinkImpl: AuditSink,
 {
-    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    // Failed attempts are audited against the address tried, when it's a valid one
+    let attempted_email = Email::parse(request.email.clone()).ok();
 
-    let password =
-        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let result = async {
+        let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user_store = state.user_store.read().await;
+        let password =
+            Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    user_store
-        .validate_user(&email, &password)
-        .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+        let user_store = state.user_store.read().await;
 
-    let user = user_store
-        .get_user(&email)
-        .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+        user_store
+            .validate_user(&email, &password)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
-    drop(user_store);
+        let user = user_store
+            .get_user(&email)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
-    if user.requires_2fa {
-        handle_2fa(
-            &user.email,
-            &state.two_fa_code_store,
-            &state.email_client,
-            jar,
-        )
-        .await
-    } else {
-        handle_no_2fa(&user.email, jar).await
+        drop(user_store);
+
+        // Devices the user chose to trust when completing 2FA don't get another code
+        let trusted_device = user.requires_2fa
+            && validate_trusted_device(
+                &jar,
+                &user.email,
+                &state.trusted_device_store,
+                &settings.auth,
+            )
+            .await
+            .is_ok();
+
+        if user.requires_2fa && !trusted_device {
+            handle_2fa(
+                &user,
+                &state.two_fa_code_store,
+                &state.email_client,
+                &state.sms_client,
+                jar,
+                &settings,
+            )
+            .await
+        } else {
+            handle_no_2fa(
+                &user.email,
+                request.token_delivery,
+                request.remember_me,
+                &state.session_store,
+                jar,
+                &settings,
+            )
+            .await
+        }
+    }
+    .await;
+
+    let outcome = login_outcome(&result);
+    record_login(outcome);
+
+    let email = attempted_email.as_ref();
+    match outcome {
+        LoginOutcome::Success => {
+            audit
+                .record(&state.audit_sink, AuditEventKind::LoginSucceeded, email)
+                .await
+        }
+        LoginOutcome::TwoFARequired => {
+            audit
+                .record(&state.audit_sink, AuditEventKind::TwoFACodeSent, email)
+                .await
+        }
+        LoginOutcome::Failure(reason) => {
+            audit
+                .record_failure(
+                    &state.audit_sink,
+                    AuditEventKind::LoginFailed,
+                    email,
+                    reason.as_str(),
+                )
+                .await
+        }
+    }
+
+    result
+}
+
+// How a login, or a re-authentication, with the password went
+pub(crate) fn login_outcome(
+    result: &Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError>,
+) -> LoginOutcome {
+    match result {
+        Ok((StatusCode::PARTIAL_CONTENT, ..)) => LoginOutcome::TwoFARequired,
+        Ok(_) => LoginOutcome::Success,
+        Err(AuthAPIError::InvalidCredentials) => LoginOutcome::Failure(LoginFailure::InvalidInput),
+        Err(AuthAPIError::IncorrectCredentials) => {
+            LoginOutcome::Failure(LoginFailure::IncorrectCredentials)
+        }
+        Err(AuthAPIError::EmailUndeliverable) => {
+            LoginOutcome::Failure(LoginFailure::EmailUndeliverable)
+        }
+        Err(_) => LoginOutcome::Failure(LoginFailure::Error),
     }
 }
 
 #[tracing::instrument(name = "Handle 2FA", skip_all)]
-async fn handle_2fa<TwoFACodeStoreImpl, EmailClientImpl>(
-    email: &Email,
+pub(crate) async fn handle_2fa<TwoFACodeStoreImpl, EmailClientImpl, SmsClientImpl>(
+    user: &User,
     two_fa_code_store: &TwoFACodeStoreType<TwoFACodeStoreImpl>,
     email_client: &EmailClientType<EmailClientImpl>,
+    sms_client: &SmsClientType<SmsClientImpl>,
     jar: CookieJar,
+    settings: &Settings,
 ) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError>
 where
     TwoFACodeStoreImpl: TwoFACodeStore,
     EmailClientImpl: EmailClient,
+    SmsClientImpl: SmsClient,
 {
-    let login_attempt_id = LoginAttemptId::default();
-    let two_fa_code = TwoFACode::default();
-    let mut lock = two_fa_code_store.write().await;
-
-    lock.add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
-        .await
-        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-
-    drop(lock);
-
-    email_client
-        .send_email(
-            email,
-            "Two FA code",
-            &format!(
-                "Your two FA code is `{}`.",
-                two_fa_code.as_ref().expose_secret()
-            ),
-        )
-        .await
-        .map_err(|e| AuthAPIError::UnexpectedError(eyre!(e)))?;
+    let login_attempt_id = start_2fa_attempt(
+        user,
+        TwoFACodePurpose::Login,
+        two_fa_code_store,
+        email_client,
+        sms_client,
+        settings,
+    )
+    .await?;
 
     Ok((
         StatusCode::PARTIAL_CONTENT,
@@ -102,25 +213,215 @@ where
     ))
 }
 
+// Store a code for a new login attempt, or 2FA settings change, and send it to the user
+#[tracing::instrument(name = "Start 2FA attempt", skip_all)]
+pub(crate) async fn start_2fa_attempt<TwoFACodeStoreImpl, EmailClientImpl, SmsClientImpl>(
+    user: &User,
+    purpose: TwoFACodePurpose,
+    two_fa_code_store: &TwoFACodeStoreType<TwoFACodeStoreImpl>,
+    email_client: &EmailClientType<EmailClientImpl>,
+    sms_client: &SmsClientType<SmsClientImpl>,
+    settings: &Settings,
+) -> Result<LoginAttemptId, AuthAPIError>
+where
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SmsClientImpl: SmsClient,
+{
+    let login_attempt_id = LoginAttemptId::default();
+    let two_fa_code = TwoFACode::default();
+    let mut lock = two_fa_code_store.write().await;
+
+    lock.add_code(
+        user.email.clone(),
+        login_attempt_id.clone(),
+        two_fa_code.clone(),
+        purpose,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(lock);
+
+    send_2fa_code(email_client, sms_client, user, &two_fa_code, settings).await?;
+
+    Ok(login_attempt_id)
+}
+
+// Count an incorrect code against the attempt, if it's still pending, so that its code can
+// only be guessed a few times
+pub(crate) async fn record_failed_2fa_code<TwoFACodeStoreImpl>(
+    two_fa_code_store: &mut TwoFACodeStoreImpl,
+    login_attempt_id: &LoginAttemptId,
+) -> Result<(), AuthAPIError>
+where
+    TwoFACodeStoreImpl: TwoFACodeStore,
+{
+    match two_fa_code_store
+        .record_failed_code(login_attempt_id, MAX_TWO_FA_CODE_ATTEMPTS)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => Ok(()),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Send the code over the channel the user chose, falling back to email without a verified phone
+// or with SMS turned off
+#[tracing::instrument(name = "Send 2FA code", skip_all)]
+pub(crate) async fn send_2fa_code<EmailClientImpl, SmsClientImpl>(
+    email_client: &EmailClientType<EmailClientImpl>,
+    sms_client: &SmsClientType<SmsClientImpl>,
+    user: &User,
+    two_fa_code: &TwoFACode,
+    settings: &Settings,
+) -> Result<(), AuthAPIError>
+where
+    EmailClientImpl: EmailClient,
+    SmsClientImpl: SmsClient,
+{
+    let sent = match (user.two_fa_channel, &user.phone_number) {
+        (TwoFAChannel::Sms, Some(phone_number)) if settings.sms.is_enabled() => sms_client
+            .send_sms(
+                phone_number,
+                &format!(
+                    "Your two FA code is {}.",
+                    two_fa_code.as_ref().expose_secret()
+                ),
+            )
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(eyre!(e))),
+        _ => {
+            let content = EmailTemplate::TwoFACode { code: two_fa_code }
+                .render(user.locale, &settings.email.branding)
+                .map_err(AuthAPIError::UnexpectedError)?;
+
+            email_client
+                .send_email(&user.email, &content)
+                .await
+                .map_err(|e| match e.downcast_ref::<EmailSuppressed>() {
+                    Some(_) => AuthAPIError::EmailUndeliverable,
+                    None => AuthAPIError::UnexpectedError(e),
+                })
+        }
+    };
+
+    if sent.is_ok() {
+        record_2fa_code(TwoFACodeEvent::Issued);
+    }
+
+    sent
+}
+
 #[tracing::instrument(name = "Handle no 2FA", skip_all)]
-async fn handle_no_2fa(
+pub(crate) async fn handle_no_2fa<SessionStoreImpl>(
     email: &Email,
+    token_delivery: TokenDelivery,
+    remember_me: bool,
+    session_store: &SessionStoreType<SessionStoreImpl>,
     jar: CookieJar,
-) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError> {
-    let auth_cookie = generate_auth_cookie(email).map_err(AuthAPIError::UnexpectedError)?;
-    let updated_jar = jar.add(auth_cookie);
+    settings: &Settings,
+) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError>
+where
+    SessionStoreImpl: SessionStore,
+{
+    let (updated_jar, bearer_auth) = issue_auth_token(
+        email,
+        &[AuthMethod::Pwd],
+        token_delivery,
+        remember_me,
+        session_store,
+        jar,
+        &settings.auth,
+    )
+    .await?;
 
-    Ok((
-        StatusCode::OK,
-        updated_jar,
-        Json(LoginResponse::RegularAuth),
-    ))
+    let response = match bearer_auth {
+        Some(bearer_auth) => LoginResponse::BearerAuth(bearer_auth),
+        None => LoginResponse::RegularAuth,
+    };
+
+    Ok((StatusCode::OK, updated_jar, Json(response)))
+}
+
+// Hand a new JWT to the client once authentication is complete, either as a cookie or in the
+// response body. "Remember me" logins get a long-lived token backed by a revocable session.
+// `amr` records how the user authenticated, for step-up checks on sensitive operations.
+#[tracing::instrument(name = "Issue auth token", skip_all)]
+pub(crate) async fn issue_auth_token<SessionStoreImpl>(
+    email: &Email,
+    amr: &[AuthMethod],
+    token_delivery: TokenDelivery,
+    remember_me: bool,
+    session_store: &SessionStoreType<SessionStoreImpl>,
+    jar: CookieJar,
+    settings: &AuthSettings,
+) -> Result<(CookieJar, Option<BearerAuthResponse>), AuthAPIError>
+where
+    SessionStoreImpl: SessionStore,
+{
+    let (token, max_age) = if remember_me {
+        let session = Session::new(
+            email.clone(),
+            chrono::Duration::seconds(settings.remember_me_ttl_seconds),
+        );
+
+        let token = generate_session_token(&session, amr, settings)
+            .map_err(AuthAPIError::UnexpectedError)?;
+        let mut session_store = session_store.write().await;
+
+        session_store
+            .add_session(session)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        drop(session_store);
+
+        (
+            token,
+            Some(time::Duration::seconds(settings.remember_me_ttl_seconds)),
+        )
+    } else {
+        let token =
+            generate_auth_token(email, amr, settings).map_err(AuthAPIError::UnexpectedError)?;
+        (token, None)
+    };
+
+    match token_delivery {
+        TokenDelivery::Cookie => {
+            let auth_cookie = match max_age {
+                Some(max_age) => create_persistent_auth_cookie(token, max_age, settings),
+                None => create_auth_cookie(token, settings),
+            };
+
+            let updated_jar = jar
+                .add(auth_cookie)
+                .add(generate_csrf_cookie(max_age, settings));
+            Ok((updated_jar, None))
+        }
+        TokenDelivery::Body => Ok((jar, Some(BearerAuthResponse::new(token)))),
+    }
 }
 
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: SecretString,
     pub password: SecretString,
+    #[serde(rename = "tokenDelivery", default)]
+    pub token_delivery: TokenDelivery,
+    #[serde(rename = "rememberMe", default)]
+    pub remember_me: bool,
+}
+
+// Where the JWT is handed back to the client once authentication completes
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenDelivery {
+    // Set the token as an HTTP-only cookie (browsers)
+    #[default]
+    Cookie,
+    // Return the token in the JSON body, to be sent back as a bearer token (mobile apps, CLIs)
+    Body,
 }
 
 #[derive(Debug, Serialize)]
@@ -128,6 +429,7 @@ pub struct LoginRequest {
 pub enum LoginResponse {
     RegularAuth,
     TwoFactorAuth(TwoFactorAuthResponse),
+    BearerAuth(BearerAuthResponse),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -136,3 +438,19 @@ pub struct TwoFactorAuthResponse {
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BearerAuthResponse {
+    pub token: String,
+    #[serde(rename = "tokenType")]
+    pub token_type: String,
+}
+
+impl BearerAuthResponse {
+    pub fn new(token: SecretString) -> Self {
+        Self {
+            token: token.expose_secret().to_owned(),
+            token_type: "Bearer".to_owned(),
+        }
+    }
+}
@@ -1,39 +1,107 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
 use axum_extra::extract::CookieJar;
-use secrecy::SecretString;
 
 use crate::{
-    domain::{AuthAPIError, BannedTokenStore},
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, SessionId, SessionStore,
+    },
+    utils::{
+        audit::AuditContext,
+        auth::{remove_auth_cookie, validate_token, AuthToken},
+        csrf::remove_csrf_cookie,
+        settings::Settings,
+    },
     AppState,
 };
 
 #[tracing::instrument(name = "Logout", skip_all)]
-pub async fn logout<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>(
+pub async fn logout<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
     State(state): State<
-        AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>,
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
     >,
+    Extension(settings): Extension<Arc<Settings>>,
     jar: CookieJar,
+    auth_token: AuthToken,
+    audit: AuditContext,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError>
 where
     BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+    AuditSinkImpl: AuditSink,
 {
-    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
-    let token: SecretString = cookie.value().into();
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
 
-    validate_token(&token, &state.banned_token_store)
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    // Bearer clients manage the token themselves, so there is no cookie to clear
+    let updated_jar = match auth_token {
+        AuthToken::Cookie(_) => jar
+            .remove(remove_auth_cookie(&settings.auth))
+            .remove(remove_csrf_cookie(&settings.auth)),
+        AuthToken::Bearer(_) => jar,
+    };
 
-    let updated_jar = jar.remove(JWT_COOKIE_NAME);
     let mut banned_token_store = state.banned_token_store.write().await;
 
     banned_token_store
-        .add_token(token)
+        .add_token(auth_token.as_ref().clone())
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
     drop(banned_token_store);
 
+    // Revoke the "remember me" session, so the long-lived token can't be used anymore
+    if let Some(sid) = claims.sid {
+        let session_id = SessionId::parse(sid.into()).map_err(AuthAPIError::UnexpectedError)?;
+        let mut session_store = state.session_store.write().await;
+
+        session_store
+            .remove_session(&session_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        drop(session_store);
+    }
+
+    // The token was revoked along with the session, if any
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::Logout,
+            Email::parse(claims.sub.into()).ok().as_ref(),
+        )
+        .await;
+
     Ok((updated_jar, StatusCode::OK))
 }
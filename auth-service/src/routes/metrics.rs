@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Extension,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+
+use crate::{
+    domain::AuthAPIError,
+    utils::{auth::require_admin_token, metrics::prometheus_handle, settings::Settings},
+};
+
+// Every metric in the Prometheus text format, for scraping with the admin API token
+pub async fn metrics(
+    Extension(settings): Extension<Arc<Settings>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    require_admin_token(&headers, &settings.admin)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        prometheus_handle().render(),
+    ))
+}
@@ -1,11 +1,41 @@
+mod audit_events;
+mod change_password;
+mod dev_mailbox;
+mod email_suppressions;
+mod health;
 mod login;
 mod logout;
+mod metrics;
+mod phone_number;
+mod postmark_webhook;
+mod preview_email;
+mod reauthenticate;
+mod resend_2fa;
+mod sessions;
 mod signup;
+mod trusted_devices;
+mod two_fa_channel;
+mod two_fa_settings;
 mod verify_2fa;
 mod verify_token;
 
+pub use audit_events::*;
+pub use change_password::*;
+pub use dev_mailbox::*;
+pub use email_suppressions::*;
+pub use health::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use phone_number::*;
+pub use postmark_webhook::*;
+pub use preview_email::*;
+pub use reauthenticate::*;
+pub use resend_2fa::*;
+pub use sessions::*;
 pub use signup::*;
+pub use trusted_devices::*;
+pub use two_fa_channel::*;
+pub use two_fa_settings::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
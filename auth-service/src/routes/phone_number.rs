@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, PhoneNumber, ResendError,
+        SessionStore, SmsClient, TwoFAChannel, TwoFACode, TwoFACodeStore, TwoFACodeStoreError,
+        UserStore,
+    },
+    utils::{
+        audit::AuditContext,
+        auth::{require_recent_auth, validate_token, AuthToken},
+        constants::{
+            MAX_PHONE_VERIFICATION_ATTEMPTS, MAX_TWO_FA_RESENDS, TWO_FA_RESEND_COOLDOWN_SECONDS,
+        },
+        settings::Settings,
+    },
+    AppState,
+};
+
+// Start enrolling a phone number for SMS 2FA by texting it a verification code
+#[tracing::instrument(name = "Add phone number", skip_all)]
+pub async fn add_phone_number<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    Json(request): Json<AddPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    SessionStoreImpl: SessionStore,
+    SmsClientImpl: SmsClient,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.clone().into()).map_err(AuthAPIError::UnexpectedError)?;
+    let user_store = state.user_store.read().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    drop(user_store);
+
+    // Changing where 2FA codes go is as sensitive as changing the password
+    require_recent_auth(&claims, user.requires_2fa, &settings.auth)?;
+
+    let phone_number =
+        PhoneNumber::parse(request.phone_number).map_err(|_| AuthAPIError::InvalidPhoneNumber)?;
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    // Each text costs money, so starting over is limited like resending a 2FA code
+    match two_fa_code_store
+        .get_phone_verification_resend_status(&email)
+        .await
+    {
+        Ok(status) => status
+            .check_resend(
+                Utc::now(),
+                chrono::Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS),
+                MAX_TWO_FA_RESENDS,
+            )
+            .map_err(|e| match e {
+                ResendError::Cooldown => AuthAPIError::ResendCooldown,
+                ResendError::TooManyResends => AuthAPIError::TooManyResends,
+            })?,
+        Err(TwoFACodeStoreError::PhoneVerificationNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let code = TwoFACode::default();
+
+    two_fa_code_store
+        .add_phone_verification(email, phone_number.clone(), code.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(two_fa_code_store);
+
+    state
+        .sms_client
+        .send_sms(
+            &phone_number,
+            &format!(
+                "Your phone verification code is {}.",
+                code.as_ref().expose_secret()
+            ),
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!(e)))?;
+
+    let response = Json(PhoneNumberResponse {
+        message: "Verification code sent".to_owned(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+// Finish enrolling the phone number and switch the user's 2FA codes to SMS
+#[tracing::instrument(name = "Verify phone number", skip_all)]
+pub async fn verify_phone_number<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    SessionStoreImpl: SessionStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.into()).map_err(AuthAPIError::UnexpectedError)?;
+
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidVerificationCode)?;
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    let (phone_number, expected_code) = two_fa_code_store
+        .get_phone_verification(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidVerificationCode)?;
+
+    if code != expected_code {
+        two_fa_code_store
+            .record_failed_phone_verification(&email, MAX_PHONE_VERIFICATION_ATTEMPTS)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        return Err(AuthAPIError::InvalidVerificationCode);
+    }
+
+    two_fa_code_store
+        .remove_phone_verification(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(two_fa_code_store);
+
+    let mut user_store = state.user_store.write().await;
+
+    user_store
+        .update_phone_number(&email, phone_number)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    user_store
+        .update_two_fa_channel(&email, TwoFAChannel::Sms)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::PhoneNumberVerified,
+            Some(&email),
+        )
+        .await;
+
+    let response = Json(PhoneNumberResponse {
+        message: "Phone number verified".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct AddPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    pub code: SecretString,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct PhoneNumberResponse {
+    pub message: String,
+}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{Extension, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        AuthAPIError, Email, EmailEvent, EmailEventKind, EmailEventStore, UserStore, UserStoreError,
+    },
+    utils::{csrf::constant_time_eq, settings::Settings},
+    AppState,
+};
+
+// Receive bounce, spam complaint and delivery events from Postmark and record them against
+// the user. Postmark is configured to send the shared secret either as the basic auth password
+// or in the `X-Postmark-Webhook-Secret` header.
+#[tracing::instrument(name = "Postmark webhook", skip_all)]
+pub async fn postmark_webhook<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    headers: HeaderMap,
+    // Parsed only once the request is authenticated
+    body: Bytes,
+) -> Result<StatusCode, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    EmailEventStoreImpl: EmailEventStore,
+{
+    if !is_authenticated(&headers, &settings.email.postmark.webhook_secret) {
+        return Err(AuthAPIError::InvalidWebhookCredentials);
+    }
+
+    let payload: PostmarkWebhookPayload =
+        serde_json::from_slice(&body).map_err(|_| AuthAPIError::InvalidWebhookPayload)?;
+
+    let Some((email, kind, details, occurred_at)) = payload.into_event_parts() else {
+        tracing::debug!("Ignoring unsupported Postmark webhook");
+        return Ok(StatusCode::OK);
+    };
+
+    // Postmark retries anything but a 200, so events that can't be recorded are dropped
+    let Ok(email) = Email::parse(email.into()) else {
+        tracing::warn!("Ignoring Postmark webhook with an invalid email address");
+        return Ok(StatusCode::OK);
+    };
+
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => {
+            tracing::debug!("Ignoring Postmark webhook for unknown user");
+            return Ok(StatusCode::OK);
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    if kind == EmailEventKind::HardBounce {
+        tracing::warn!("Suppressing emails to hard-bounced address");
+    }
+
+    state
+        .email_event_store
+        .write()
+        .await
+        .add_event(EmailEvent::new(email, kind, details, occurred_at))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::OK)
+}
+
+fn is_authenticated(headers: &HeaderMap, secret: &SecretString) -> bool {
+    let secret = secret.expose_secret();
+
+    if let Some(header_secret) = headers
+        .get(SECRET_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+    {
+        return constant_time_eq(header_secret, secret);
+    }
+
+    // Any username is accepted, only the password is checked
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|credentials| STANDARD.decode(credentials).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .and_then(|credentials| {
+            let (_, password) = credentials.split_once(':')?;
+            Some(constant_time_eq(password, secret))
+        })
+        .unwrap_or(false)
+}
+
+const SECRET_HEADER_NAME: &str = "x-postmark-webhook-secret";
+
+// Bounce types that mean the address will never accept email,
+// see https://postmarkapp.com/developer/api/bounce-api#bounce-types
+const HARD_BOUNCE_TYPES: [&str; 2] = ["HardBounce", "BadEmailAddress"];
+
+// For more information about the payloads, see the webhook docs: https://postmarkapp.com/developer/webhooks/webhooks-overview
+#[derive(Debug, Deserialize)]
+#[serde(tag = "RecordType")]
+enum PostmarkWebhookPayload {
+    #[serde(rename_all = "PascalCase")]
+    Bounce {
+        r#type: String,
+        email: String,
+        bounced_at: DateTime<Utc>,
+        description: Option<String>,
+    },
+    #[serde(rename_all = "PascalCase")]
+    SpamComplaint {
+        email: String,
+        bounced_at: DateTime<Utc>,
+    },
+    #[serde(rename_all = "PascalCase")]
+    Delivery {
+        recipient: String,
+        delivered_at: DateTime<Utc>,
+        details: Option<String>,
+    },
+    // Opens, clicks and subscription changes aren't tracked
+    #[serde(other)]
+    Other,
+}
+
+impl PostmarkWebhookPayload {
+    fn into_event_parts(self) -> Option<(String, EmailEventKind, Option<String>, DateTime<Utc>)> {
+        match self {
+            Self::Bounce {
+                r#type,
+                email,
+                bounced_at,
+                description,
+            } => {
+                let kind = if HARD_BOUNCE_TYPES.contains(&r#type.as_str()) {
+                    EmailEventKind::HardBounce
+                } else {
+                    EmailEventKind::SoftBounce
+                };
+
+                let details = match description {
+                    Some(description) => format!("{type}: {description}"),
+                    None => r#type,
+                };
+
+                Some((email, kind, Some(details), bounced_at))
+            }
+            Self::SpamComplaint { email, bounced_at } => {
+                Some((email, EmailEventKind::SpamComplaint, None, bounced_at))
+            }
+            Self::Delivery {
+                recipient,
+                delivered_at,
+                details,
+            } => Some((recipient, EmailEventKind::Delivery, details, delivered_at)),
+            Self::Other => None,
+        }
+    }
+}
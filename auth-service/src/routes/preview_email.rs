@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    domain::{AuthAPIError, Locale},
+    utils::{email_templates::render_preview, settings::Settings},
+};
+
+// Render an email template with sample values, e.g. `/dev/emails/two-fa-code?locale=es`.
+// Only served in dev mode.
+#[tracing::instrument(name = "Preview email", skip_all)]
+pub async fn preview_email(
+    Extension(settings): Extension<Arc<Settings>>,
+    Path(name): Path<String>,
+    Query(params): Query<PreviewEmailParams>,
+) -> Result<Response, AuthAPIError> {
+    let Some(content) = render_preview(&name, params.locale, &settings.email.branding)
+        .map_err(AuthAPIError::UnexpectedError)?
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let response = match params.format {
+        PreviewFormat::Html => Html(content.html_body).into_response(),
+        PreviewFormat::Text => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            content.text_body,
+        )
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct PreviewEmailParams {
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default)]
+    pub format: PreviewFormat,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewFormat {
+    #[default]
+    Html,
+    Text,
+}
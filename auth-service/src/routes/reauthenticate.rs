@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::SecretString;
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, EmailClient, Password,
+        SessionId, SessionStore, SmsClient, TwoFACodeStore, UserStore,
+    },
+    routes::{handle_2fa, handle_no_2fa, login_outcome, LoginResponse, TokenDelivery},
+    utils::{
+        audit::AuditContext,
+        auth::{validate_token, AuthToken},
+        metrics::LoginOutcome,
+        settings::Settings,
+    },
+    AppState,
+};
+
+// Log the signed-in user in again, to get a fresh token for sensitive operations.
+// Unlike `/login`, a trusted device doesn't skip 2FA: users with 2FA complete it with
+// `/verify-2fa` as usual.
+#[tracing::instrument(name = "Reauthenticate", skip_all)]
+pub async fn reauthenticate<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    jar: CookieJar,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<ReauthenticateRequest>,
+) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SessionStoreImpl: SessionStore,
+    SmsClientImpl: SmsClient,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.into()).map_err(AuthAPIError::UnexpectedError)?;
+    let superseded_session = claims.sid;
+
+    let result = async {
+        let password =
+            Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        let user_store = state.user_store.read().await;
+
+        user_store
+            .validate_user(&email, &password)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+        let user = user_store
+            .get_user(&email)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+        drop(user_store);
+
+        if user.requires_2fa {
+            handle_2fa(
+                &user,
+                &state.two_fa_code_store,
+                &state.email_client,
+                &state.sms_client,
+                jar,
+                &settings,
+            )
+            .await
+        } else {
+            handle_no_2fa(
+                &user.email,
+                request.token_delivery,
+                request.remember_me,
+                &state.session_store,
+                jar,
+                &settings,
+            )
+            .await
+        }
+    }
+    .await;
+
+    match login_outcome(&result) {
+        LoginOutcome::Success => {
+            // The client replaces its token, so the session behind the old one is done with
+            if let Some(sid) = superseded_session {
+                let session_id =
+                    SessionId::parse(sid.into()).map_err(AuthAPIError::UnexpectedError)?;
+
+                state
+                    .session_store
+                    .write()
+                    .await
+                    .remove_session(&session_id)
+                    .await
+                    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            }
+
+            audit
+                .record(
+                    &state.audit_sink,
+                    AuditEventKind::Reauthenticated,
+                    Some(&email),
+                )
+                .await
+        }
+        LoginOutcome::TwoFARequired => {
+            audit
+                .record(
+                    &state.audit_sink,
+                    AuditEventKind::TwoFACodeSent,
+                    Some(&email),
+                )
+                .await
+        }
+        LoginOutcome::Failure(reason) => {
+            audit
+                .record_failure(
+                    &state.audit_sink,
+                    AuditEventKind::ReauthenticationFailed,
+                    Some(&email),
+                    reason.as_str(),
+                )
+                .await
+        }
+    }
+
+    result
+}
+
+#[derive(Deserialize)]
+pub struct ReauthenticateRequest {
+    pub password: SecretString,
+    #[serde(rename = "tokenDelivery", default)]
+    pub token_delivery: TokenDelivery,
+    #[serde(rename = "rememberMe", default)]
+    pub remember_me: bool,
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, EmailClient, LoginAttemptId, ResendError,
+        SmsClient, TwoFACode, TwoFACodeStore, UserStore,
+    },
+    routes::{send_2fa_code, TwoFactorAuthResponse},
+    utils::{
+        audit::AuditContext,
+        constants::{MAX_TWO_FA_RESENDS, TWO_FA_RESEND_COOLDOWN_SECONDS},
+        settings::Settings,
+    },
+};
+
+#[tracing::instrument(name = "Resend 2FA code", skip_all)]
+pub async fn resend_2fa<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    audit: AuditContext,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<(StatusCode, Json<TwoFactorAuthResponse>), AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SmsClientImpl: SmsClient,
+    AuditSinkImpl: AuditSink,
+{
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    let status = two_fa_code_store
+        .get_resend_status(&login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    status
+        .check_resend(
+            Utc::now(),
+            chrono::Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS),
+            MAX_TWO_FA_RESENDS,
+        )
+        .map_err(|e| match e {
+            ResendError::Cooldown => AuthAPIError::ResendCooldown,
+            ResendError::TooManyResends => AuthAPIError::TooManyResends,
+        })?;
+
+    let (email, _, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    // Send a new code rather than the old one, which may have been intercepted
+    let two_fa_code = TwoFACode::default();
+
+    two_fa_code_store
+        .update_code(&login_attempt_id, two_fa_code.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(two_fa_code_store);
+
+    // Look the user up again, in case they switched 2FA channel since logging in
+    let user_store = state.user_store.read().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    drop(user_store);
+
+    send_2fa_code(
+        &state.email_client,
+        &state.sms_client,
+        &user,
+        &two_fa_code,
+        &settings,
+    )
+    .await?;
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFACodeSent,
+            Some(&user.email),
+        )
+        .await;
+
+    Ok((
+        StatusCode::OK,
+        Json(TwoFactorAuthResponse {
+            message: "2FA code resent".to_owned(),
+            login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct Resend2FARequest {
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: SecretString,
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, Session, SessionId,
+        SessionStore,
+    },
+    utils::{
+        audit::AuditContext,
+        auth::{validate_token, AuthToken},
+        settings::Settings,
+    },
+    AppState,
+};
+
+#[tracing::instrument(name = "List sessions", skip_all)]
+pub async fn list_sessions<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.into()).map_err(AuthAPIError::UnexpectedError)?;
+    let session_store = state.session_store.read().await;
+
+    let sessions = session_store
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(session_store);
+
+    let response = sessions
+        .iter()
+        .map(|session| SessionResponse::new(session, claims.sid.as_deref()))
+        .collect::<Vec<_>>();
+
+    Ok(Json(response))
+}
+
+#[tracing::instrument(name = "Revoke session", skip_all)]
+pub async fn revoke_session<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Path(session_id): Path<SecretString>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let session_id = SessionId::parse(session_id).map_err(|_| AuthAPIError::SessionNotFound)?;
+    let mut session_store = state.session_store.write().await;
+
+    let session = session_store
+        .get_session(&session_id)
+        .await
+        .map_err(|_| AuthAPIError::SessionNotFound)?;
+
+    // Don't reveal whether another user's session exists
+    if *session.email.as_ref().expose_secret() != claims.sub {
+        return Err(AuthAPIError::SessionNotFound);
+    }
+
+    session_store
+        .remove_session(&session_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(session_store);
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::SessionRevoked,
+            Some(&session.email),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionResponse {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    // Whether this is the session the request was authenticated with
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: &Session, current_sid: Option<&str>) -> Self {
+        let session_id = session.id.as_ref().expose_secret().to_owned();
+
+        Self {
+            current: current_sid == Some(session_id.as_str()),
+            session_id,
+            created_at: session.created_at.to_rfc3339(),
+            expires_at: session.expires_at.to_rfc3339(),
+        }
+    }
+}
@@ -3,26 +3,56 @@ use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    domain::{AuthAPIError, Email, Password, User, UserStore, UserStoreError},
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, Email, Locale, Password, User, UserStore,
+        UserStoreError,
+    },
+    utils::audit::AuditContext,
     AppState,
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
-pub async fn signup<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>(
+pub async fn signup<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
     state: State<
-        AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>,
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
     >,
+    audit: AuditContext,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError>
 where
     UserStoreImpl: UserStore,
+    AuditSinkImpl: AuditSink,
 {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
     let password =
         Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User {
+        locale: request.locale,
+        ..User::new(email.clone(), password, request.requires_2fa)
+    };
+
     let mut user_store = state.user_store.write().await;
 
     user_store.add_user(user).await.map_err(|e| match e {
@@ -32,6 +62,10 @@ where
 
     drop(user_store);
 
+    audit
+        .record(&state.audit_sink, AuditEventKind::Signup, Some(&email))
+        .await;
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
     });
@@ -45,6 +79,8 @@ pub struct SignupRequest {
     pub password: SecretString,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    #[serde(default)]
+    pub locale: Locale,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
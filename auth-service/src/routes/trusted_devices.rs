@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, DeviceId, Email, SessionStore,
+        TrustedDevice, TrustedDeviceStore,
+    },
+    utils::{
+        audit::AuditContext,
+        auth::{validate_token, AuthToken},
+        settings::Settings,
+        trusted_device::{get_trusted_device_id, remove_trusted_device_cookie},
+    },
+    AppState,
+};
+
+#[tracing::instrument(name = "List trusted devices", skip_all)]
+pub async fn list_trusted_devices<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    jar: CookieJar,
+    auth_token: AuthToken,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+    TrustedDeviceStoreImpl: TrustedDeviceStore,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.into()).map_err(AuthAPIError::UnexpectedError)?;
+    let trusted_device_store = state.trusted_device_store.read().await;
+
+    let devices = trusted_device_store
+        .get_devices(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(trusted_device_store);
+
+    let current_device_id = get_trusted_device_id(&jar, &settings.auth).ok();
+
+    let response = devices
+        .iter()
+        .map(|device| TrustedDeviceResponse::new(device, current_device_id.as_ref()))
+        .collect::<Vec<_>>();
+
+    Ok(Json(response))
+}
+
+#[tracing::instrument(name = "Revoke trusted device", skip_all)]
+pub async fn revoke_trusted_device<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    jar: CookieJar,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Path(device_id): Path<SecretString>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+    TrustedDeviceStoreImpl: TrustedDeviceStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let device_id = DeviceId::parse(device_id).map_err(|_| AuthAPIError::DeviceNotFound)?;
+    let mut trusted_device_store = state.trusted_device_store.write().await;
+
+    let device = trusted_device_store
+        .get_device(&device_id)
+        .await
+        .map_err(|_| AuthAPIError::DeviceNotFound)?;
+
+    // Don't reveal whether another user's device exists
+    if *device.email.as_ref().expose_secret() != claims.sub {
+        return Err(AuthAPIError::DeviceNotFound);
+    }
+
+    trusted_device_store
+        .remove_device(&device_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(trusted_device_store);
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TrustedDeviceRevoked,
+            Some(&device.email),
+        )
+        .await;
+
+    // Clear the cookie too when the device revokes itself
+    let updated_jar = match get_trusted_device_id(&jar, &settings.auth) {
+        Ok(current_device_id) if current_device_id == device_id => {
+            jar.remove(remove_trusted_device_cookie(&settings.auth))
+        }
+        _ => jar,
+    };
+
+    Ok((StatusCode::NO_CONTENT, updated_jar))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrustedDeviceResponse {
+    #[serde(rename = "deviceId")]
+    pub device_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    // Whether the request was made from this device
+    pub current: bool,
+}
+
+impl TrustedDeviceResponse {
+    fn new(device: &TrustedDevice, current_device_id: Option<&DeviceId>) -> Self {
+        Self {
+            device_id: device.id.as_ref().expose_secret().to_owned(),
+            created_at: device.created_at.to_rfc3339(),
+            expires_at: device.expires_at.to_rfc3339(),
+            current: current_device_id == Some(&device.id),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, SessionStore,
+        TwoFAChannel, UserStore, UserStoreError,
+    },
+    utils::{
+        audit::AuditContext,
+        auth::{require_recent_auth, validate_token, AuthToken},
+        settings::Settings,
+    },
+    AppState,
+};
+
+#[tracing::instrument(name = "Update 2FA channel", skip_all)]
+pub async fn update_two_fa_channel<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<UpdateTwoFAChannelRequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.clone().into()).map_err(AuthAPIError::UnexpectedError)?;
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store.get_user(&email).await.map_err(|e| match e {
+        UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    })?;
+
+    require_recent_auth(&claims, user.requires_2fa, &settings.auth)?;
+
+    // Codes can only be texted to a number the user has verified
+    if request.channel == TwoFAChannel::Sms && user.phone_number.is_none() {
+        return Err(AuthAPIError::PhoneNumberNotVerified);
+    }
+
+    user_store
+        .update_two_fa_channel(&email, request.channel)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFAChannelChanged,
+            Some(&email),
+        )
+        .await;
+
+    Ok((
+        StatusCode::OK,
+        Json(TwoFAChannelResponse {
+            channel: request.channel,
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTwoFAChannelRequest {
+    pub channel: TwoFAChannel,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct TwoFAChannelResponse {
+    pub channel: TwoFAChannel,
+}
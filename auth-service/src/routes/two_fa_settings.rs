@@ -0,0 +1,517 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::{EmailClientType, TwoFACodeStoreType, UserStoreType},
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, BannedTokenStore, Email, EmailClient,
+        LoginAttemptId, Password, SessionStore, SmsClient, TwoFACode, TwoFACodePurpose,
+        TwoFACodeStore, User, UserStore, UserStoreError,
+    },
+    routes::{record_failed_2fa_code, start_2fa_attempt, TwoFactorAuthResponse},
+    utils::{
+        audit::AuditContext,
+        auth::{require_recent_auth, validate_token, AuthToken},
+        email_templates::EmailTemplate,
+        metrics::{record_2fa_code, TwoFACodeEvent},
+        settings::Settings,
+    },
+    AppState,
+};
+
+// Send a code the user must enter to turn 2FA on, proving they can receive it
+#[tracing::instrument(name = "Enable 2FA", skip_all)]
+pub async fn enable_2fa<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SessionStoreImpl: SessionStore,
+    SmsClientImpl: SmsClient,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = get_user(&state.user_store, &claims.sub).await?;
+
+    if user.requires_2fa {
+        return Err(AuthAPIError::TwoFAAlreadyEnabled);
+    }
+
+    require_recent_auth(&claims, user.requires_2fa, &settings.auth)?;
+
+    let login_attempt_id = start_2fa_attempt(
+        &user,
+        TwoFACodePurpose::Enable2FA,
+        &state.two_fa_code_store,
+        &state.email_client,
+        &state.sms_client,
+        &settings,
+    )
+    .await?;
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFACodeSent,
+            Some(&user.email),
+        )
+        .await;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(TwoFactorAuthResponse {
+            message: "2FA code sent".to_owned(),
+            login_attempt_id: login_attempt_id.as_ref().expose_secret().to_string(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Verify enable 2FA", skip_all)]
+pub async fn verify_enable_2fa<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<Verify2FASettingRequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SessionStoreImpl: SessionStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = get_user(&state.user_store, &claims.sub).await?;
+
+    if user.requires_2fa {
+        return Err(AuthAPIError::TwoFAAlreadyEnabled);
+    }
+
+    if let Err(e) = check_2fa_code(
+        &state.two_fa_code_store,
+        &user.email,
+        TwoFACodePurpose::Enable2FA,
+        request,
+    )
+    .await
+    {
+        if matches!(e, AuthAPIError::IncorrectCredentials) {
+            audit
+                .record_failure(
+                    &state.audit_sink,
+                    AuditEventKind::TwoFACodeFailed,
+                    Some(&user.email),
+                    "incorrect_code",
+                )
+                .await;
+        }
+
+        return Err(e);
+    }
+
+    let response = update_requires_2fa(
+        &state.user_store,
+        &state.email_client,
+        &user,
+        true,
+        &settings,
+    )
+    .await?;
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFAEnabled,
+            Some(&user.email),
+        )
+        .await;
+
+    Ok(response)
+}
+
+// Send a code the user must enter to turn 2FA off, once they have re-entered their password
+#[tracing::instrument(name = "Disable 2FA", skip_all)]
+pub async fn disable_2fa<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<Disable2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SessionStoreImpl: SessionStore,
+    SmsClientImpl: SmsClient,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(claims.sub.into()).map_err(AuthAPIError::UnexpectedError)?;
+
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_store = state.user_store.read().await;
+
+    user_store
+        .validate_user(&email, &password)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    drop(user_store);
+
+    if !user.requires_2fa {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let login_attempt_id = start_2fa_attempt(
+        &user,
+        TwoFACodePurpose::Disable2FA,
+        &state.two_fa_code_store,
+        &state.email_client,
+        &state.sms_client,
+        &settings,
+    )
+    .await?;
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFACodeSent,
+            Some(&user.email),
+        )
+        .await;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(TwoFactorAuthResponse {
+            message: "2FA code sent".to_owned(),
+            login_attempt_id: login_attempt_id.as_ref().expose_secret().to_string(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Verify disable 2FA", skip_all)]
+pub async fn verify_disable_2fa<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
+    State(state): State<
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
+    >,
+    Extension(settings): Extension<Arc<Settings>>,
+    auth_token: AuthToken,
+    audit: AuditContext,
+    Json(request): Json<Verify2FASettingRequest>,
+) -> Result<impl IntoResponse, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    BannedTokenStoreImpl: BannedTokenStore,
+    TwoFACodeStoreImpl: TwoFACodeStore,
+    EmailClientImpl: EmailClient,
+    SessionStoreImpl: SessionStore,
+    AuditSinkImpl: AuditSink,
+{
+    let claims = validate_token(
+        auth_token.as_ref(),
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = get_user(&state.user_store, &claims.sub).await?;
+
+    if !user.requires_2fa {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    if let Err(e) = check_2fa_code(
+        &state.two_fa_code_store,
+        &user.email,
+        TwoFACodePurpose::Disable2FA,
+        request,
+    )
+    .await
+    {
+        if matches!(e, AuthAPIError::IncorrectCredentials) {
+            audit
+                .record_failure(
+                    &state.audit_sink,
+                    AuditEventKind::TwoFACodeFailed,
+                    Some(&user.email),
+                    "incorrect_code",
+                )
+                .await;
+        }
+
+        return Err(e);
+    }
+
+    let response = update_requires_2fa(
+        &state.user_store,
+        &state.email_client,
+        &user,
+        false,
+        &settings,
+    )
+    .await?;
+
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFADisabled,
+            Some(&user.email),
+        )
+        .await;
+
+    Ok(response)
+}
+
+async fn get_user<UserStoreImpl>(
+    user_store: &UserStoreType<UserStoreImpl>,
+    sub: &str,
+) -> Result<User, AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+{
+    let email = Email::parse(sub.to_owned().into()).map_err(AuthAPIError::UnexpectedError)?;
+    let user_store = user_store.read().await;
+
+    user_store.get_user(&email).await.map_err(|e| match e {
+        UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    })
+}
+
+// Consume the code sent for the attempt, which must belong to the signed-in user and have been
+// sent for this change
+async fn check_2fa_code<TwoFACodeStoreImpl>(
+    two_fa_code_store: &TwoFACodeStoreType<TwoFACodeStoreImpl>,
+    email: &Email,
+    purpose: TwoFACodePurpose,
+    request: Verify2FASettingRequest,
+) -> Result<(), AuthAPIError>
+where
+    TwoFACodeStoreImpl: TwoFACodeStore,
+{
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let two_fa_code =
+        TwoFACode::parse(request.two_fa_code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut two_fa_code_store = two_fa_code_store.write().await;
+
+    let code_tuple = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .map_err(|_| {
+            record_2fa_code(TwoFACodeEvent::Failed);
+            AuthAPIError::IncorrectCredentials
+        })?;
+
+    if code_tuple != (email.clone(), two_fa_code, purpose) {
+        record_failed_2fa_code(&mut *two_fa_code_store, &login_attempt_id).await?;
+        record_2fa_code(TwoFACodeEvent::Failed);
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    two_fa_code_store
+        .remove_code(&login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    record_2fa_code(TwoFACodeEvent::Verified);
+
+    Ok(())
+}
+
+async fn update_requires_2fa<UserStoreImpl, EmailClientImpl>(
+    user_store: &UserStoreType<UserStoreImpl>,
+    email_client: &EmailClientType<EmailClientImpl>,
+    user: &User,
+    requires_2fa: bool,
+    settings: &Settings,
+) -> Result<(StatusCode, Json<TwoFASettingsResponse>), AuthAPIError>
+where
+    UserStoreImpl: UserStore,
+    EmailClientImpl: EmailClient,
+{
+    let mut user_store = user_store.write().await;
+
+    user_store
+        .update_requires_2fa(&user.email, requires_2fa)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    let template = if requires_2fa {
+        EmailTemplate::TwoFAEnabled
+    } else {
+        EmailTemplate::TwoFADisabled
+    };
+
+    // The change is already saved, so a failed notification doesn't fail the request
+    let sent = match template.render(user.locale, &settings.email.branding) {
+        Ok(content) => email_client.send_email(&user.email, &content).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = sent {
+        tracing::error!("Failed to send 2FA change notification: {:?}", e);
+    }
+
+    Ok((StatusCode::OK, Json(TwoFASettingsResponse { requires_2fa })))
+}
+
+#[derive(Deserialize)]
+pub struct Disable2FARequest {
+    pub password: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct Verify2FASettingRequest {
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: SecretString,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: SecretString,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct TwoFASettingsResponse {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
@@ -1,24 +1,66 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use axum_extra::extract::CookieJar;
 use secrecy::SecretString;
 use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodeStore},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuditEventKind, AuditSink, AuthAPIError, Email, LoginAttemptId, SessionStore,
+        TrustedDevice, TrustedDeviceStore, TwoFACode, TwoFACodePurpose, TwoFACodeStore,
+    },
+    routes::{issue_auth_token, record_failed_2fa_code, TokenDelivery},
+    utils::{
+        audit::AuditContext,
+        auth::AuthMethod,
+        metrics::{record_2fa_code, TwoFACodeEvent},
+        settings::Settings,
+        trusted_device::generate_trusted_device_cookie,
+    },
 };
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
-pub async fn verify_2fa<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>(
+pub async fn verify_2fa<
+    UserStoreImpl,
+    BannedTokenStoreImpl,
+    TwoFACodeStoreImpl,
+    EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
+>(
     State(state): State<
-        AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>,
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
     >,
+    Extension(settings): Extension<Arc<Settings>>,
     jar: CookieJar,
+    audit: AuditContext,
     Json(request): Json<Verify2FARequest>,
-) -> Result<impl IntoResponse, AuthAPIError>
+) -> Result<Response, AuthAPIError>
 where
     TwoFACodeStoreImpl: TwoFACodeStore,
+    SessionStoreImpl: SessionStore,
+    TrustedDeviceStoreImpl: TrustedDeviceStore,
+    AuditSinkImpl: AuditSink,
 {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
@@ -30,25 +72,87 @@ where
 
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
-    let code_tuple = two_fa_code_store
-        .get_code(&email)
+    // The login attempt is unknown or expired, or the code or email is wrong, or the code
+    // was sent to confirm a 2FA settings change rather than a login
+    let is_valid = two_fa_code_store
+        .get_code(&login_attempt_id)
         .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+        .is_ok_and(|code_tuple| {
+            code_tuple == (email.clone(), two_fa_code, TwoFACodePurpose::Login)
+        });
+
+    if !is_valid {
+        record_failed_2fa_code(&mut *two_fa_code_store, &login_attempt_id).await?;
+        drop(two_fa_code_store);
+        record_2fa_code(TwoFACodeEvent::Failed);
+        audit
+            .record_failure(
+                &state.audit_sink,
+                AuditEventKind::TwoFACodeFailed,
+                Some(&email),
+                "incorrect_code",
+            )
+            .await;
 
-    if code_tuple != (login_attempt_id, two_fa_code) {
         return Err(AuthAPIError::IncorrectCredentials);
     }
 
     two_fa_code_store
-        .remove_code(&email)
+        .remove_code(&login_attempt_id)
         .await
         .map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
+    record_2fa_code(TwoFACodeEvent::Verified);
+
     drop(two_fa_code_store);
 
-    let auth_cookie = generate_auth_cookie(&email).map_err(AuthAPIError::UnexpectedError)?;
-    let updated_jar = jar.add(auth_cookie);
-    Ok((StatusCode::OK, updated_jar))
+    // Completes the login, or the re-authentication, the code was sent for
+    audit
+        .record(
+            &state.audit_sink,
+            AuditEventKind::TwoFACodeVerified,
+            Some(&email),
+        )
+        .await;
+
+    let jar = if request.trust_device {
+        let device = TrustedDevice::new(
+            email.clone(),
+            chrono::Duration::seconds(settings.auth.trusted_device_ttl_seconds),
+        );
+
+        let cookie = generate_trusted_device_cookie(&device, &settings.auth)
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+        let mut trusted_device_store = state.trusted_device_store.write().await;
+
+        trusted_device_store
+            .add_device(device)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        drop(trusted_device_store);
+
+        jar.add(cookie)
+    } else {
+        jar
+    };
+
+    let (updated_jar, bearer_auth) = issue_auth_token(
+        &email,
+        &[AuthMethod::Pwd, AuthMethod::Otp],
+        request.token_delivery,
+        request.remember_me,
+        &state.session_store,
+        jar,
+        &settings.auth,
+    )
+    .await?;
+
+    match bearer_auth {
+        Some(bearer_auth) => Ok((StatusCode::OK, updated_jar, Json(bearer_auth)).into_response()),
+        None => Ok((StatusCode::OK, updated_jar).into_response()),
+    }
 }
 
 #[derive(Deserialize)]
@@ -58,4 +162,11 @@ pub struct Verify2FARequest {
     pub login_attempt_id: SecretString,
     #[serde(rename = "2FACode")]
     pub two_fa_code: SecretString,
+    #[serde(rename = "tokenDelivery", default)]
+    pub token_delivery: TokenDelivery,
+    #[serde(rename = "rememberMe", default)]
+    pub remember_me: bool,
+    // Skip 2FA on future logins from this device
+    #[serde(rename = "trustDevice", default)]
+    pub trust_device: bool,
 }
@@ -1,10 +1,17 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
 use secrecy::SecretString;
 use serde::Deserialize;
 
 use crate::{
-    domain::{AuthAPIError, BannedTokenStore},
-    utils::auth::validate_token,
+    domain::{AuthAPIError, BannedTokenStore, SessionStore},
+    utils::{auth::validate_token, settings::Settings},
     AppState,
 };
 
@@ -14,18 +21,40 @@ pub async fn verify_token<
     BannedTokenStoreImpl,
     TwoFACodeStoreImpl,
     EmailClientImpl,
+    SessionStoreImpl,
+    TrustedDeviceStoreImpl,
+    SmsClientImpl,
+    EmailEventStoreImpl,
+    AuditSinkImpl,
 >(
     State(state): State<
-        AppState<UserStoreImpl, BannedTokenStoreImpl, TwoFACodeStoreImpl, EmailClientImpl>,
+        AppState<
+            UserStoreImpl,
+            BannedTokenStoreImpl,
+            TwoFACodeStoreImpl,
+            EmailClientImpl,
+            SessionStoreImpl,
+            TrustedDeviceStoreImpl,
+            SmsClientImpl,
+            EmailEventStoreImpl,
+            AuditSinkImpl,
+        >,
     >,
+    Extension(settings): Extension<Arc<Settings>>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError>
 where
     BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
 {
-    validate_token(&request.token, &state.banned_token_store)
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    validate_token(
+        &request.token,
+        &state.banned_token_store,
+        &state.session_store,
+        &settings.auth,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
 
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::domain::{Email, EmailEvent, EmailEventKind, EmailEventStore, EmailEventStoreError};
+
+#[derive(Default)]
+pub struct HashmapEmailEventStore {
+    events: HashMap<Email, Vec<EmailEvent>>,
+}
+
+#[async_trait]
+impl EmailEventStore for HashmapEmailEventStore {
+    async fn add_event(&mut self, event: EmailEvent) -> Result<(), EmailEventStoreError> {
+        self.events
+            .entry(event.email.clone())
+            .or_default()
+            .push(event);
+
+        Ok(())
+    }
+
+    async fn get_events(&self, email: &Email) -> Result<Vec<EmailEvent>, EmailEventStoreError> {
+        let mut events = self.events.get(email).cloned().unwrap_or_default();
+        events.sort_by_key(|event| event.occurred_at);
+
+        Ok(events)
+    }
+
+    async fn is_suppressed(&self, email: &Email) -> Result<bool, EmailEventStoreError> {
+        let latest = self.events.get(email).and_then(|events| {
+            events
+                .iter()
+                .filter(|event| {
+                    matches!(
+                        event.kind,
+                        EmailEventKind::HardBounce
+                            | EmailEventKind::Delivery
+                            | EmailEventKind::SuppressionCleared
+                    )
+                })
+                .max_by_key(|event| event.occurred_at)
+        });
+
+        Ok(latest.is_some_and(|event| event.kind == EmailEventKind::HardBounce))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned().into()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_events_oldest_first() {
+        let mut store = HashmapEmailEventStore::default();
+        let now = Utc::now();
+
+        let delivery = EmailEvent::new(email(), EmailEventKind::Delivery, None, now);
+        let bounce = EmailEvent::new(
+            email(),
+            EmailEventKind::SoftBounce,
+            Some("Mailbox full".to_owned()),
+            now - Duration::minutes(1),
+        );
+
+        store.add_event(delivery.clone()).await.unwrap();
+        store.add_event(bounce.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_events(&email()).await.unwrap(),
+            [bounce, delivery]
+        );
+
+        let other = Email::parse("other@example.com".to_owned().into()).unwrap();
+        assert!(store.get_events(&other).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_hard_bounces_suppress() {
+        let mut store = HashmapEmailEventStore::default();
+
+        for kind in [
+            EmailEventKind::Delivery,
+            EmailEventKind::SoftBounce,
+            EmailEventKind::SpamComplaint,
+        ] {
+            store
+                .add_event(EmailEvent::new(email(), kind, None, Utc::now()))
+                .await
+                .unwrap();
+        }
+
+        assert!(!store.is_suppressed(&email()).await.unwrap());
+
+        store
+            .add_event(EmailEvent::new(
+                email(),
+                EmailEventKind::HardBounce,
+                None,
+                Utc::now(),
+            ))
+            .await
+            .unwrap();
+
+        assert!(store.is_suppressed(&email()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_later_delivery_or_clearing_lifts_suppression() {
+        let mut store = HashmapEmailEventStore::default();
+        let now = Utc::now();
+
+        let event = |kind, minutes_ago| {
+            EmailEvent::new(email(), kind, None, now - Duration::minutes(minutes_ago))
+        };
+
+        store
+            .add_event(event(EmailEventKind::HardBounce, 10))
+            .await
+            .unwrap();
+        // Events can arrive out of order, an older delivery doesn't count
+        store
+            .add_event(event(EmailEventKind::Delivery, 20))
+            .await
+            .unwrap();
+        assert!(store.is_suppressed(&email()).await.unwrap());
+
+        store
+            .add_event(event(EmailEventKind::Delivery, 5))
+            .await
+            .unwrap();
+        assert!(!store.is_suppressed(&email()).await.unwrap());
+
+        store
+            .add_event(event(EmailEventKind::HardBounce, 3))
+            .await
+            .unwrap();
+        assert!(store.is_suppressed(&email()).await.unwrap());
+
+        store
+            .add_event(event(EmailEventKind::SuppressionCleared, 1))
+            .await
+            .unwrap();
+        assert!(!store.is_suppressed(&email()).await.unwrap());
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxStatus};
+
+#[derive(Default)]
+pub struct HashmapEmailOutboxStore {
+    emails: HashMap<Uuid, Entry>,
+}
+
+struct Entry {
+    email: OutboxEmail,
+    status: OutboxStatus,
+    next_attempt_at: DateTime<Utc>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl Entry {
+    fn redact(&mut self) {
+        self.email.content.html_body.clear();
+        self.email.content.text_body.clear();
+    }
+}
+
+impl HashmapEmailOutboxStore {
+    fn get_entry(&mut self, id: Uuid) -> Result<&mut Entry, EmailOutboxStoreError> {
+        self.emails
+            .get_mut(&id)
+            .ok_or(EmailOutboxStoreError::EmailNotFound)
+    }
+}
+
+#[async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        let duplicate = self.emails.values().any(|entry| {
+            entry.status == OutboxStatus::Pending
+                && entry.email.idempotency_key == email.idempotency_key
+        });
+
+        if !duplicate {
+            self.emails.insert(
+                email.id,
+                Entry {
+                    email,
+                    status: OutboxStatus::Pending,
+                    next_attempt_at: Utc::now(),
+                    last_error: None,
+                    created_at: Utc::now(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let mut due: Vec<&mut Entry> = self
+            .emails
+            .values_mut()
+            .filter(|entry| entry.status == OutboxStatus::Pending && entry.next_attempt_at <= now)
+            .collect();
+
+        due.sort_by_key(|entry| entry.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|entry| {
+                entry.email.attempts += 1;
+                entry.next_attempt_at = lease_until;
+                entry.email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        let entry = self.get_entry(id)?;
+        entry.status = OutboxStatus::Sent;
+        entry.last_error = None;
+        entry.redact();
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &mut self,
+        id: Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError> {
+        let entry = self.get_entry(id)?;
+        entry.last_error = Some(error.to_owned());
+
+        match retry_at {
+            Some(retry_at) => entry.next_attempt_at = retry_at,
+            None => {
+                entry.status = OutboxStatus::Dead;
+                entry.redact();
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_status(&self, id: Uuid) -> Result<OutboxStatus, EmailOutboxStoreError> {
+        self.emails
+            .get(&id)
+            .map(|entry| entry.status)
+            .ok_or(EmailOutboxStoreError::EmailNotFound)
+    }
+
+    async fn purge_finished(
+        &mut self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, EmailOutboxStoreError> {
+        let count = self.emails.len();
+
+        self.emails
+            .retain(|_, entry| entry.status == OutboxStatus::Pending || entry.created_at >= before);
+
+        Ok((count - self.emails.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use fake::{faker::internet::en::SafeEmail, Fake};
+
+    use crate::domain::{Email, EmailContent};
+
+    use super::*;
+
+    fn new_example_email() -> OutboxEmail {
+        OutboxEmail::new(
+            Email::parse(SafeEmail().fake::<String>().into()).unwrap(),
+            EmailContent {
+                subject: "Subject".to_owned(),
+                html_body: "<p>Body</p>".to_owned(),
+                text_body: "Body".to_owned(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_skips_pending_duplicates() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = new_example_email();
+
+        store.enqueue(email.clone()).await.unwrap();
+
+        let duplicate = OutboxEmail {
+            id: Uuid::new_v4(),
+            ..email.clone()
+        };
+
+        store.enqueue(duplicate.clone()).await.unwrap();
+        assert_eq!(store.emails.len(), 1);
+
+        // Once sent, the same email may be queued again
+        store.mark_sent(email.id).await.unwrap();
+        store.enqueue(duplicate).await.unwrap();
+        assert_eq!(store.emails.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_leases_emails() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = new_example_email();
+        store.enqueue(email.clone()).await.unwrap();
+
+        let now = Utc::now();
+        let lease_until = now + Duration::seconds(60);
+
+        let claimed = store.claim_due(now, lease_until, 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, email.id);
+        assert_eq!(claimed[0].attempts, 1);
+
+        // Hidden while leased, due again once the lease runs out
+        assert!(store
+            .claim_due(now, lease_until, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let claimed = store
+            .claim_due(lease_until, lease_until + Duration::seconds(60), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(claimed[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_respects_limit() {
+        let mut store = HashmapEmailOutboxStore::default();
+
+        for _ in 0..3 {
+            store.enqueue(new_example_email()).await.unwrap();
+        }
+
+        let now = Utc::now();
+        let claimed = store.claim_due(now, now, 2).await.unwrap();
+
+        assert_eq!(claimed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_mark_failed() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = new_example_email();
+        store.enqueue(email.clone()).await.unwrap();
+
+        let retry_at = Utc::now() + Duration::seconds(30);
+
+        store
+            .mark_failed(email.id, "Provider down", Some(retry_at))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_status(email.id).await.unwrap(),
+            OutboxStatus::Pending
+        );
+        assert_eq!(store.emails[&email.id].next_attempt_at, retry_at);
+
+        store
+            .mark_failed(email.id, "Provider down", None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_status(email.id).await.unwrap(),
+            OutboxStatus::Dead
+        );
+        assert!(store
+            .claim_due(retry_at, retry_at, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finished_emails_are_redacted_and_purged() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let (sent, dead, pending) = (
+            new_example_email(),
+            new_example_email(),
+            new_example_email(),
+        );
+
+        for email in [&sent, &dead, &pending] {
+            store.enqueue(email.clone()).await.unwrap();
+        }
+
+        store.mark_sent(sent.id).await.unwrap();
+        store
+            .mark_failed(dead.id, "Provider down", None)
+            .await
+            .unwrap();
+
+        for email in [&sent, &dead] {
+            let content = &store.emails[&email.id].email.content;
+            assert!(content.html_body.is_empty());
+            assert!(content.text_body.is_empty());
+        }
+        assert_eq!(store.emails[&pending.id].email.content, pending.content);
+
+        // Only finished emails past the retention period are purged
+        assert_eq!(
+            store.purge_finished(Utc::now() - Duration::days(1)).await,
+            Ok(0)
+        );
+        assert_eq!(
+            store
+                .purge_finished(Utc::now() + Duration::seconds(1))
+                .await,
+            Ok(2)
+        );
+        assert_eq!(
+            store.get_status(pending.id).await.unwrap(),
+            OutboxStatus::Pending
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_email() {
+        let mut store = HashmapEmailOutboxStore::default();
+
+        assert_eq!(
+            store.mark_sent(Uuid::new_v4()).await,
+            Err(EmailOutboxStoreError::EmailNotFound)
+        );
+        assert_eq!(
+            store.get_status(Uuid::new_v4()).await,
+            Err(EmailOutboxStoreError::EmailNotFound)
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+
+use crate::domain::{Email, Session, SessionId, SessionStore, SessionStoreError};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+#[async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions
+            .insert(session.id.as_ref().expose_secret().to_owned(), session);
+
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(id.as_ref().expose_secret())
+            .filter(|session| !session.is_expired())
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        Ok(self
+            .sessions
+            .values()
+            .filter(|session| session.email == *email && !session.is_expired())
+            .cloned()
+            .collect())
+    }
+
+    async fn remove_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        self.sessions.remove(id.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::{faker::internet::en::FreeEmail, Fake};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_session() {
+        let mut store = HashmapSessionStore::default();
+        assert!(store.sessions.is_empty());
+        let session = new_example_session();
+
+        store
+            .add_session(session.clone())
+            .await
+            .expect("should add session");
+
+        assert_eq!(
+            store.sessions.get(session.id.as_ref().expose_secret()),
+            Some(&session)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_session() {
+        let session = new_example_session();
+        let expired = Session::new(session.email.clone(), chrono::Duration::zero());
+
+        let store = HashmapSessionStore {
+            sessions: HashMap::from([
+                (
+                    session.id.as_ref().expose_secret().to_owned(),
+                    session.clone(),
+                ),
+                (
+                    expired.id.as_ref().expose_secret().to_owned(),
+                    expired.clone(),
+                ),
+            ]),
+        };
+
+        let actual = store
+            .get_session(&session.id)
+            .await
+            .expect("should get session");
+
+        assert_eq!(actual, session);
+
+        assert_eq!(
+            store.get_session(&expired.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+
+        assert_eq!(store.get_sessions(&session.email).await, Ok(vec![session]));
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let session = new_example_session();
+
+        let mut store = HashmapSessionStore {
+            sessions: HashMap::from([(
+                session.id.as_ref().expose_secret().to_owned(),
+                session.clone(),
+            )]),
+        };
+
+        store
+            .remove_session(&session.id)
+            .await
+            .expect("should remove session");
+
+        assert!(store.sessions.is_empty());
+    }
+
+    fn new_example_session() -> Session {
+        Session::new(
+            Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+            chrono::Duration::days(30),
+        )
+    }
+}
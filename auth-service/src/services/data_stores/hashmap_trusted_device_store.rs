@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+
+use crate::domain::{DeviceId, Email, TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError};
+
+#[derive(Default)]
+pub struct HashmapTrustedDeviceStore {
+    devices: HashMap<String, TrustedDevice>,
+}
+
+#[async_trait]
+impl TrustedDeviceStore for HashmapTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        self.devices
+            .insert(device.id.as_ref().expose_secret().to_owned(), device);
+
+        Ok(())
+    }
+
+    async fn get_device(&self, id: &DeviceId) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        self.devices
+            .get(id.as_ref().expose_secret())
+            .filter(|device| !device.is_expired())
+            .cloned()
+            .ok_or(TrustedDeviceStoreError::DeviceNotFound)
+    }
+
+    async fn get_devices(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        Ok(self
+            .devices
+            .values()
+            .filter(|device| device.email == *email && !device.is_expired())
+            .cloned()
+            .collect())
+    }
+
+    async fn remove_device(&mut self, id: &DeviceId) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.remove(id.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::{faker::internet::en::FreeEmail, Fake};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_device() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        assert!(store.devices.is_empty());
+        let device = new_example_device();
+
+        store
+            .add_device(device.clone())
+            .await
+            .expect("should add device");
+
+        assert_eq!(
+            store.devices.get(device.id.as_ref().expose_secret()),
+            Some(&device)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_device() {
+        let device = new_example_device();
+        let expired = TrustedDevice::new(device.email.clone(), chrono::Duration::zero());
+
+        let store = HashmapTrustedDeviceStore {
+            devices: HashMap::from([
+                (
+                    device.id.as_ref().expose_secret().to_owned(),
+                    device.clone(),
+                ),
+                (
+                    expired.id.as_ref().expose_secret().to_owned(),
+                    expired.clone(),
+                ),
+            ]),
+        };
+
+        let actual = store
+            .get_device(&device.id)
+            .await
+            .expect("should get device");
+
+        assert_eq!(actual, device);
+
+        assert_eq!(
+            store.get_device(&expired.id).await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+
+        assert_eq!(store.get_devices(&device.email).await, Ok(vec![device]));
+    }
+
+    #[tokio::test]
+    async fn test_remove_device() {
+        let device = new_example_device();
+
+        let mut store = HashmapTrustedDeviceStore {
+            devices: HashMap::from([(
+                device.id.as_ref().expose_secret().to_owned(),
+                device.clone(),
+            )]),
+        };
+
+        store
+            .remove_device(&device.id)
+            .await
+            .expect("should remove device");
+
+        assert!(store.devices.is_empty());
+    }
+
+    fn new_example_device() -> TrustedDevice {
+        TrustedDevice::new(
+            Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+            chrono::Duration::days(30),
+        )
+    }
+}
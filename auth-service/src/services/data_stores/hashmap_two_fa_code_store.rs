@@ -1,15 +1,60 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    email::Email,
+use crate::{
+    domain::{
+        data_stores::{
+            LoginAttemptId, ResendStatus, TwoFACode, TwoFACodePurpose, TwoFACodeStore,
+            TwoFACodeStoreError,
+        },
+        email::Email,
+        PhoneNumber,
+    },
+    utils::constants::MAX_PENDING_LOGIN_ATTEMPTS,
 };
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<String, PendingCode>,
+    // Each user's pending login attempts, oldest first
+    attempts: HashMap<Email, VecDeque<String>>,
+    phone_verifications: HashMap<Email, PendingPhoneVerification>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PendingCode {
+    email: Email,
+    code: TwoFACode,
+    purpose: TwoFACodePurpose,
+    sent_at: DateTime<Utc>,
+    resend_count: u32,
+    failed_attempts: u32,
+}
+
+impl PendingCode {
+    fn new(email: Email, code: TwoFACode, purpose: TwoFACodePurpose) -> Self {
+        Self {
+            email,
+            code,
+            purpose,
+            sent_at: Utc::now(),
+            resend_count: 0,
+            failed_attempts: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PendingPhoneVerification {
+    phone_number: PhoneNumber,
+    // Cleared after too many incorrect codes
+    code: Option<TwoFACode>,
+    sent_at: DateTime<Utc>,
+    resend_count: u32,
+    failed_attempts: u32,
 }
 
 #[async_trait]
@@ -19,25 +64,171 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        purpose: TwoFACodePurpose,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        let id = login_attempt_id.as_ref().expose_secret().to_owned();
+        let attempts = self.attempts.entry(email.clone()).or_default();
+        attempts.push_back(id.clone());
+
+        while attempts.len() > MAX_PENDING_LOGIN_ATTEMPTS {
+            if let Some(oldest) = attempts.pop_front() {
+                self.codes.remove(&oldest);
+            }
+        }
+
+        self.codes
+            .insert(id, PendingCode::new(email, code, purpose));
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        self.codes.remove(email);
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let id = login_attempt_id.as_ref().expose_secret();
+
+        if let Some(pending) = self.codes.remove(id) {
+            if let Some(attempts) = self.attempts.get_mut(&pending.email) {
+                attempts.retain(|attempt| attempt != id);
+            }
+        }
+
         Ok(())
     }
 
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode, TwoFACodePurpose), TwoFACodeStoreError> {
         self.codes
-            .get(email)
-            .cloned()
+            .get(login_attempt_id.as_ref().expose_secret())
+            .map(|pending| (pending.email.clone(), pending.code.clone(), pending.purpose))
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    }
+
+    async fn update_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let pending = self
+            .codes
+            .get_mut(login_attempt_id.as_ref().expose_secret())
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        pending.code = code;
+        pending.sent_at = Utc::now();
+        pending.resend_count += 1;
+
+        Ok(())
+    }
+
+    async fn get_resend_status(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<ResendStatus, TwoFACodeStoreError> {
+        self.codes
+            .get(login_attempt_id.as_ref().expose_secret())
+            .map(|pending| ResendStatus {
+                last_sent_at: pending.sent_at,
+                resend_count: pending.resend_count,
+            })
             .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
     }
+
+    async fn record_failed_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let pending = self
+            .codes
+            .get_mut(login_attempt_id.as_ref().expose_secret())
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        pending.failed_attempts += 1;
+
+        if pending.failed_attempts >= max_attempts {
+            self.remove_code(login_attempt_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn add_phone_verification(
+        &mut self,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let resend_count = self
+            .phone_verifications
+            .get(&email)
+            .map_or(0, |pending| pending.resend_count + 1);
+
+        self.phone_verifications.insert(
+            email,
+            PendingPhoneVerification {
+                phone_number,
+                code: Some(code),
+                sent_at: Utc::now(),
+                resend_count,
+                failed_attempts: 0,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn get_phone_verification(
+        &self,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), TwoFACodeStoreError> {
+        self.phone_verifications
+            .get(email)
+            .and_then(|pending| Some((pending.phone_number.clone(), pending.code.clone()?)))
+            .ok_or(TwoFACodeStoreError::PhoneVerificationNotFound)
+    }
+
+    async fn get_phone_verification_resend_status(
+        &self,
+        email: &Email,
+    ) -> Result<ResendStatus, TwoFACodeStoreError> {
+        self.phone_verifications
+            .get(email)
+            .map(|pending| ResendStatus {
+                last_sent_at: pending.sent_at,
+                resend_count: pending.resend_count,
+            })
+            .ok_or(TwoFACodeStoreError::PhoneVerificationNotFound)
+    }
+
+    async fn record_failed_phone_verification(
+        &mut self,
+        email: &Email,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let pending = self
+            .phone_verifications
+            .get_mut(email)
+            .ok_or(TwoFACodeStoreError::PhoneVerificationNotFound)?;
+
+        pending.failed_attempts += 1;
+
+        if pending.failed_attempts >= max_attempts {
+            pending.code = None;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_phone_verification(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), TwoFACodeStoreError> {
+        self.phone_verifications.remove(email);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -55,25 +246,83 @@ mod tests {
         let code = TwoFACode::default();
 
         store
-            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .add_code(
+                email.clone(),
+                login_attempt_id.clone(),
+                code.clone(),
+                TwoFACodePurpose::Login,
+            )
             .await
             .expect("should add code");
 
-        assert_eq!(store.codes.get(&email), Some(&(login_attempt_id, code)));
+        let pending = store
+            .codes
+            .get(login_attempt_id.as_ref().expose_secret())
+            .expect("should store code");
+
+        assert_eq!((&pending.email, &pending.code), (&email, &code));
+        assert_eq!(pending.resend_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_add_code_keeps_concurrent_attempts() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+
+        let attempts = (0..MAX_PENDING_LOGIN_ATTEMPTS + 1)
+            .map(|_| (LoginAttemptId::default(), TwoFACode::default()))
+            .collect::<Vec<_>>();
+
+        for (login_attempt_id, code) in &attempts {
+            store
+                .add_code(
+                    email.clone(),
+                    login_attempt_id.clone(),
+                    code.clone(),
+                    TwoFACodePurpose::Login,
+                )
+                .await
+                .expect("should add code");
+        }
+
+        // Only the oldest attempt is dropped once the limit is exceeded
+        assert_eq!(
+            store.get_code(&attempts[0].0).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+
+        for (login_attempt_id, code) in &attempts[1..] {
+            assert_eq!(
+                store.get_code(login_attempt_id).await,
+                Ok((email.clone(), code.clone(), TwoFACodePurpose::Login))
+            );
+        }
     }
 
     #[tokio::test]
     async fn test_remove_code() {
+        let mut store = HashmapTwoFACodeStore::default();
         let email = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
 
-        let mut store = HashmapTwoFACodeStore {
-            codes: HashMap::from([(email.clone(), (login_attempt_id.clone(), code.clone()))]),
-        };
+        store
+            .add_code(
+                email.clone(),
+                login_attempt_id.clone(),
+                code.clone(),
+                TwoFACodePurpose::Login,
+            )
+            .await
+            .expect("should add code");
+
+        store
+            .remove_code(&login_attempt_id)
+            .await
+            .expect("should remove code");
 
-        store.remove_code(&email).await.expect("should remove code");
         assert!(store.codes.is_empty());
+        assert_eq!(store.attempts.get(&email).map(VecDeque::len), Some(0));
     }
 
     #[tokio::test]
@@ -83,10 +332,184 @@ mod tests {
         let code = TwoFACode::default();
 
         let store = HashmapTwoFACodeStore {
-            codes: HashMap::from([(email.clone(), (login_attempt_id.clone(), code.clone()))]),
+            codes: HashMap::from([(
+                login_attempt_id.as_ref().expose_secret().to_owned(),
+                PendingCode::new(email.clone(), code.clone(), TwoFACodePurpose::Disable2FA),
+            )]),
+            ..Default::default()
         };
 
-        let actual = store.get_code(&email).await.expect("should get code");
-        assert_eq!(actual, (login_attempt_id, code));
+        let actual = store
+            .get_code(&login_attempt_id)
+            .await
+            .expect("should get code");
+
+        assert_eq!(actual, (email, code, TwoFACodePurpose::Disable2FA));
+    }
+
+    #[tokio::test]
+    async fn test_update_code() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+
+        store
+            .add_code(
+                email.clone(),
+                login_attempt_id.clone(),
+                TwoFACode::default(),
+                TwoFACodePurpose::Login,
+            )
+            .await
+            .expect("should add code");
+
+        let before = store
+            .get_resend_status(&login_attempt_id)
+            .await
+            .expect("should get resend status");
+
+        assert_eq!(before.resend_count, 0);
+
+        let code = TwoFACode::default();
+
+        store
+            .update_code(&login_attempt_id, code.clone())
+            .await
+            .expect("should update code");
+
+        assert_eq!(
+            store.get_code(&login_attempt_id).await,
+            Ok((email, code, TwoFACodePurpose::Login))
+        );
+
+        let after = store
+            .get_resend_status(&login_attempt_id)
+            .await
+            .expect("should get resend status");
+
+        assert_eq!(after.resend_count, 1);
+        assert!(after.last_sent_at >= before.last_sent_at);
+
+        assert_eq!(
+            store
+                .update_code(&LoginAttemptId::default(), TwoFACode::default())
+                .await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_code_is_removed_after_failed_attempts() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+
+        store
+            .add_code(
+                email.clone(),
+                login_attempt_id.clone(),
+                TwoFACode::default(),
+                TwoFACodePurpose::Login,
+            )
+            .await
+            .expect("should add code");
+
+        store
+            .record_failed_code(&login_attempt_id, 2)
+            .await
+            .expect("should record failed code");
+
+        // Resending keeps the count
+        store
+            .update_code(&login_attempt_id, TwoFACode::default())
+            .await
+            .expect("should update code");
+
+        assert!(store.get_code(&login_attempt_id).await.is_ok());
+
+        store
+            .record_failed_code(&login_attempt_id, 2)
+            .await
+            .expect("should record failed code");
+
+        assert_eq!(
+            store.get_code(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert!(store.attempts[&email].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_phone_verification() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+        let phone_number = PhoneNumber::parse("+14155552671".to_owned().into()).unwrap();
+        let code = TwoFACode::default();
+
+        store
+            .add_phone_verification(email.clone(), phone_number.clone(), TwoFACode::default())
+            .await
+            .expect("should add phone verification");
+
+        // Starting over replaces the pending verification
+        store
+            .add_phone_verification(email.clone(), phone_number.clone(), code.clone())
+            .await
+            .expect("should add phone verification");
+
+        assert_eq!(
+            store.get_phone_verification(&email).await,
+            Ok((phone_number, code))
+        );
+        assert_eq!(
+            store
+                .get_phone_verification_resend_status(&email)
+                .await
+                .unwrap()
+                .resend_count,
+            1
+        );
+
+        store
+            .remove_phone_verification(&email)
+            .await
+            .expect("should remove phone verification");
+
+        assert_eq!(
+            store.get_phone_verification(&email).await,
+            Err(TwoFACodeStoreError::PhoneVerificationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phone_verification_is_invalidated_after_failed_attempts() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+        let phone_number = PhoneNumber::parse("+14155552671".to_owned().into()).unwrap();
+
+        store
+            .add_phone_verification(email.clone(), phone_number, TwoFACode::default())
+            .await
+            .expect("should add phone verification");
+
+        for _ in 0..2 {
+            assert!(store.get_phone_verification(&email).await.is_ok());
+
+            store
+                .record_failed_phone_verification(&email, 2)
+                .await
+                .expect("should record failed attempt");
+        }
+
+        assert_eq!(
+            store.get_phone_verification(&email).await,
+            Err(TwoFACodeStoreError::PhoneVerificationNotFound)
+        );
+
+        // Still counted against the resend limit
+        assert!(store
+            .get_phone_verification_resend_status(&email)
+            .await
+            .is_ok());
     }
 }
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use async_trait::async_trait;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{Email, Password, PhoneNumber, TwoFAChannel, User, UserStore, UserStoreError};
 
 #[derive(Default)]
 pub struct HashmapUserStore {
@@ -40,6 +40,62 @@ impl UserStore for HashmapUserStore {
 
         Ok(())
     }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.password = password;
+        Ok(())
+    }
+
+    async fn update_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn update_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.phone_number = Some(phone_number);
+        Ok(())
+    }
+
+    async fn update_two_fa_channel(
+        &mut self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.two_fa_channel = channel;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -104,11 +160,95 @@ mod tests {
             .expect_err("should not validate user");
     }
 
+    #[tokio::test]
+    async fn test_update_password() {
+        let user = new_example_user();
+
+        let mut store = HashmapUserStore {
+            users: HashMap::from([(user.email.clone(), user.clone())]),
+        };
+
+        let password = Password::parse("new password".into()).unwrap();
+
+        store
+            .update_password(&user.email, password.clone())
+            .await
+            .expect("should update password");
+
+        store
+            .validate_user(&user.email, &password)
+            .await
+            .expect("should validate user with new password");
+
+        store
+            .validate_user(&user.email, &user.password)
+            .await
+            .expect_err("should not validate user with old password");
+    }
+
+    #[tokio::test]
+    async fn test_update_requires_2fa() {
+        let user = new_example_user();
+
+        let mut store = HashmapUserStore {
+            users: HashMap::from([(user.email.clone(), user.clone())]),
+        };
+
+        store
+            .update_requires_2fa(&user.email, false)
+            .await
+            .expect("should update requires 2FA");
+
+        let actual = store.get_user(&user.email).await.expect("should get user");
+        assert!(!actual.requires_2fa);
+
+        let unknown = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+
+        assert_eq!(
+            store.update_requires_2fa(&unknown, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_phone_number_and_two_fa_channel() {
+        let user = new_example_user();
+
+        let mut store = HashmapUserStore {
+            users: HashMap::from([(user.email.clone(), user.clone())]),
+        };
+
+        let phone_number = PhoneNumber::parse("+14155552671".to_owned().into()).unwrap();
+
+        store
+            .update_phone_number(&user.email, phone_number.clone())
+            .await
+            .expect("should update phone number");
+
+        store
+            .update_two_fa_channel(&user.email, TwoFAChannel::Sms)
+            .await
+            .expect("should update 2FA channel");
+
+        let actual = store.get_user(&user.email).await.expect("should get user");
+        assert_eq!(actual.phone_number, Some(phone_number));
+        assert_eq!(actual.two_fa_channel, TwoFAChannel::Sms);
+
+        let unknown = Email::parse(FreeEmail().fake::<String>().into()).unwrap();
+
+        assert_eq!(
+            store
+                .update_two_fa_channel(&unknown, TwoFAChannel::Email)
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
     fn new_example_user() -> User {
-        User {
-            email: Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
-            password: Password::parse("********".into()).unwrap(),
-            requires_2fa: true,
-        }
+        User::new(
+            Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+            Password::parse("********".into()).unwrap(),
+            true,
+        )
     }
 }
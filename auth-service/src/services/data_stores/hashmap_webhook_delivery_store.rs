@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    OutboxStatus, WebhookAttempt, WebhookDelivery, WebhookDeliveryStore, WebhookDeliveryStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapWebhookDeliveryStore {
+    deliveries: HashMap<Uuid, Entry>,
+}
+
+struct Entry {
+    delivery: WebhookDelivery,
+    status: OutboxStatus,
+    next_attempt_at: DateTime<Utc>,
+    attempts: Vec<WebhookAttempt>,
+}
+
+impl HashmapWebhookDeliveryStore {
+    fn get_entry(&mut self, id: Uuid) -> Result<&mut Entry, WebhookDeliveryStoreError> {
+        self.deliveries
+            .get_mut(&id)
+            .ok_or(WebhookDeliveryStoreError::DeliveryNotFound)
+    }
+}
+
+#[async_trait]
+impl WebhookDeliveryStore for HashmapWebhookDeliveryStore {
+    async fn enqueue(
+        &mut self,
+        delivery: WebhookDelivery,
+    ) -> Result<(), WebhookDeliveryStoreError> {
+        self.deliveries.insert(
+            delivery.id,
+            Entry {
+                delivery,
+                status: OutboxStatus::Pending,
+                next_attempt_at: Utc::now(),
+                attempts: Vec::new(),
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDelivery>, WebhookDeliveryStoreError> {
+        let mut due: Vec<&mut Entry> = self
+            .deliveries
+            .values_mut()
+            .filter(|entry| entry.status == OutboxStatus::Pending && entry.next_attempt_at <= now)
+            .collect();
+
+        due.sort_by_key(|entry| entry.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|entry| {
+                entry.delivery.attempts += 1;
+                entry.next_attempt_at = lease_until;
+                entry.delivery.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(
+        &mut self,
+        id: Uuid,
+        attempt: WebhookAttempt,
+    ) -> Result<(), WebhookDeliveryStoreError> {
+        let entry = self.get_entry(id)?;
+        entry.status = OutboxStatus::Sent;
+        entry.attempts.push(attempt);
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &mut self,
+        id: Uuid,
+        attempt: WebhookAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookDeliveryStoreError> {
+        let entry = self.get_entry(id)?;
+        entry.attempts.push(attempt);
+
+        match retry_at {
+            Some(retry_at) => entry.next_attempt_at = retry_at,
+            None => entry.status = OutboxStatus::Dead,
+        }
+
+        Ok(())
+    }
+
+    async fn get_status(&self, id: Uuid) -> Result<OutboxStatus, WebhookDeliveryStoreError> {
+        self.deliveries
+            .get(&id)
+            .map(|entry| entry.status)
+            .ok_or(WebhookDeliveryStoreError::DeliveryNotFound)
+    }
+
+    async fn get_deliveries(
+        &self,
+        event_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, WebhookDeliveryStoreError> {
+        Ok(self
+            .deliveries
+            .values()
+            .filter(|entry| entry.delivery.event_id == event_id)
+            .map(|entry| entry.delivery.clone())
+            .collect())
+    }
+
+    async fn get_attempts(
+        &self,
+        id: Uuid,
+    ) -> Result<Vec<WebhookAttempt>, WebhookDeliveryStoreError> {
+        self.deliveries
+            .get(&id)
+            .map(|entry| entry.attempts.clone())
+            .ok_or(WebhookDeliveryStoreError::DeliveryNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::domain::{AuditEvent, AuditEventKind, WebhookEventKind};
+
+    use super::*;
+
+    fn new_example_delivery() -> WebhookDelivery {
+        WebhookDelivery::new(
+            "https://crm.example.com/hooks".to_owned(),
+            WebhookEventKind::UserCreated,
+            &AuditEvent::new(AuditEventKind::Signup, None),
+        )
+    }
+
+    fn attempt(status_code: u16) -> WebhookAttempt {
+        WebhookAttempt {
+            attempted_at: Utc::now(),
+            status_code: Some(status_code),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_leases_deliveries() {
+        let mut store = HashmapWebhookDeliveryStore::default();
+        let delivery = new_example_delivery();
+        store.enqueue(delivery.clone()).await.unwrap();
+
+        let now = Utc::now();
+        let lease_until = now + Duration::seconds(60);
+
+        let claimed = store.claim_due(now, lease_until, 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].attempts, 1);
+
+        // Hidden from other workers until the lease runs out
+        assert!(store
+            .claim_due(now, lease_until, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store
+                .claim_due(lease_until, lease_until, 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attempts_are_logged() {
+        let mut store = HashmapWebhookDeliveryStore::default();
+        let delivery = new_example_delivery();
+        store.enqueue(delivery.clone()).await.unwrap();
+
+        let retry_at = Utc::now() + Duration::seconds(10);
+        store
+            .mark_failed(delivery.id, attempt(500), Some(retry_at))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_status(delivery.id).await.unwrap(),
+            OutboxStatus::Pending
+        );
+
+        store
+            .mark_delivered(delivery.id, attempt(200))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_status(delivery.id).await.unwrap(),
+            OutboxStatus::Sent
+        );
+
+        let status_codes = store
+            .get_attempts(delivery.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|attempt| attempt.status_code)
+            .collect::<Vec<_>>();
+        assert_eq!(status_codes, [Some(500), Some(200)]);
+
+        assert_eq!(
+            store.get_deliveries(delivery.event_id).await.unwrap(),
+            [delivery]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_delivery() {
+        let mut store = HashmapWebhookDeliveryStore::default();
+
+        assert!(matches!(
+            store.mark_delivered(Uuid::new_v4(), attempt(200)).await,
+            Err(WebhookDeliveryStoreError::DeliveryNotFound)
+        ));
+    }
+}
@@ -1,19 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, SecretString};
 
-use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::{BannedTokenStore, BannedTokenStoreError, Email};
 
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
     tokens: HashSet<String>,
+    revoked_before: HashMap<Email, DateTime<Utc>>,
 }
 
 impl<const N: usize> From<[String; N]> for HashsetBannedTokenStore {
     fn from(value: [String; N]) -> Self {
         Self {
             tokens: value.into(),
+            revoked_before: HashMap::new(),
         }
     }
 }
@@ -28,6 +31,22 @@ impl BannedTokenStore for HashsetBannedTokenStore {
     async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
         Ok(self.tokens.contains(token.expose_secret()))
     }
+
+    async fn revoke_tokens_before(
+        &mut self,
+        email: &Email,
+        time: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError> {
+        self.revoked_before.insert(email.clone(), time);
+        Ok(())
+    }
+
+    async fn get_tokens_revoked_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+        Ok(self.revoked_before.get(email).copied())
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +73,7 @@ mod tests {
 
         let store = HashsetBannedTokenStore {
             tokens: HashSet::from([token.expose_secret().into()]),
+            revoked_before: HashMap::new(),
         };
 
         assert!(!store
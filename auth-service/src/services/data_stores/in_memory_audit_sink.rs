@@ -0,0 +1,109 @@
+use std::cmp::Reverse;
+
+use async_trait::async_trait;
+
+use crate::domain::{AuditEvent, AuditEventFilter, AuditSink, AuditSinkError};
+
+#[derive(Default)]
+pub struct InMemoryAuditSink {
+    events: Vec<AuditEvent>,
+}
+
+#[async_trait]
+impl AuditSink for InMemoryAuditSink {
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditSinkError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditSinkError> {
+        let mut events: Vec<_> = self
+            .events
+            .iter()
+            .filter(|event| filter.email.is_none() || event.email == filter.email)
+            .filter(|event| filter.kind.is_none_or(|kind| event.kind == kind))
+            .filter(|event| filter.since.is_none_or(|since| event.occurred_at >= since))
+            .filter(|event| filter.until.is_none_or(|until| event.occurred_at < until))
+            .cloned()
+            .collect();
+
+        events.sort_by_key(|event| Reverse(event.occurred_at));
+        events.truncate(filter.limit as usize);
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::domain::{AuditEventKind, Email};
+
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned().into()).unwrap()
+    }
+
+    fn event(kind: AuditEventKind, address: &str, minutes_ago: i64) -> AuditEvent {
+        AuditEvent {
+            occurred_at: Utc::now() - Duration::minutes(minutes_ago),
+            ..AuditEvent::new(kind, Some(email(address)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_newest_first_up_to_limit() {
+        let mut sink = InMemoryAuditSink::default();
+        let oldest = event(AuditEventKind::Signup, "test@example.com", 3);
+        let older = event(AuditEventKind::LoginSucceeded, "test@example.com", 2);
+        let newest = event(AuditEventKind::Logout, "test@example.com", 1);
+
+        for event in [older.clone(), newest.clone(), oldest] {
+            sink.record(event).await.unwrap();
+        }
+
+        let filter = AuditEventFilter {
+            limit: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(sink.query(&filter).await.unwrap(), [newest, older]);
+    }
+
+    #[tokio::test]
+    async fn test_query_filters() {
+        let mut sink = InMemoryAuditSink::default();
+        let signup = event(AuditEventKind::Signup, "test@example.com", 10);
+        let failed = event(AuditEventKind::LoginFailed, "test@example.com", 5);
+        let other = event(AuditEventKind::LoginFailed, "other@example.com", 5);
+
+        for event in [signup.clone(), failed.clone(), other.clone()] {
+            sink.record(event).await.unwrap();
+        }
+
+        let by_email = AuditEventFilter {
+            email: Some(email("test@example.com")),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            sink.query(&by_email).await.unwrap(),
+            [failed.clone(), signup.clone()]
+        );
+
+        let by_time = AuditEventFilter {
+            since: Some(signup.occurred_at),
+            until: Some(failed.occurred_at),
+            ..by_email.clone()
+        };
+        assert_eq!(sink.query(&by_time).await.unwrap(), [signup]);
+
+        let by_kind = AuditEventFilter {
+            kind: Some(AuditEventKind::LoginFailed),
+            ..by_email
+        };
+        assert_eq!(sink.query(&by_kind).await.unwrap(), [failed]);
+    }
+}
@@ -1,13 +1,37 @@
+pub mod hashmap_email_event_store;
+pub mod hashmap_email_outbox_store;
+pub mod hashmap_session_store;
+pub mod hashmap_trusted_device_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
+pub mod hashmap_webhook_delivery_store;
 pub mod hashset_banned_token_store;
+pub mod in_memory_audit_sink;
+pub mod postgres_audit_sink;
+pub mod postgres_email_event_store;
+pub mod postgres_email_outbox_store;
 pub mod postgres_user_store;
+pub mod postgres_webhook_delivery_store;
 pub mod redis_banned_token_store;
+pub mod redis_session_store;
+pub mod redis_trusted_device_store;
 pub mod redis_two_fa_code_store;
 
+pub use hashmap_email_event_store::HashmapEmailEventStore;
+pub use hashmap_email_outbox_store::HashmapEmailOutboxStore;
+pub use hashmap_session_store::HashmapSessionStore;
+pub use hashmap_trusted_device_store::HashmapTrustedDeviceStore;
 pub use hashmap_two_fa_code_store::HashmapTwoFACodeStore;
 pub use hashmap_user_store::HashmapUserStore;
+pub use hashmap_webhook_delivery_store::HashmapWebhookDeliveryStore;
 pub use hashset_banned_token_store::HashsetBannedTokenStore;
+pub use in_memory_audit_sink::InMemoryAuditSink;
+pub use postgres_audit_sink::PostgresAuditSink;
+pub use postgres_email_event_store::PostgresEmailEventStore;
+pub use postgres_email_outbox_store::PostgresEmailOutboxStore;
 pub use postgres_user_store::PostgresUserStore;
+pub use postgres_webhook_delivery_store::PostgresWebhookDeliveryStore;
 pub use redis_banned_token_store::RedisBannedTokenStore;
+pub use redis_session_store::RedisSessionStore;
+pub use redis_trusted_device_store::RedisTrustedDeviceStore;
 pub use redis_two_fa_code_store::RedisTwoFACodeStore;
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Report;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::{
+    domain::{AuditEvent, AuditEventFilter, AuditEventKind, AuditSink, AuditSinkError, Email},
+    utils::metrics::time_store,
+};
+
+pub struct PostgresAuditSink {
+    pool: PgPool,
+}
+
+impl PostgresAuditSink {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AuditSink for PostgresAuditSink {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditSinkError> {
+        let _timer = time_store("postgres", "audit_sink", "record");
+
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events
+              (id, kind, email, reason, ip_address, user_agent, request_id, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            event.id,
+            event.kind.as_str(),
+            event
+                .email
+                .as_ref()
+                .map(|email| email.as_ref().expose_secret()),
+            event.reason,
+            event.ip_address.map(|ip| ip.to_string()),
+            event.user_agent,
+            event.request_id,
+            event.occurred_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditSinkError> {
+        let _timer = time_store("postgres", "audit_sink", "query");
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, kind, email, reason, ip_address, user_agent, request_id, occurred_at
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR email = $1)
+              AND ($2::TEXT IS NULL OR kind = $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR occurred_at >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR occurred_at < $4)
+            ORDER BY occurred_at DESC
+            LIMIT $5
+            "#,
+            filter
+                .email
+                .as_ref()
+                .map(|email| email.as_ref().expose_secret()),
+            filter.kind.map(|kind| kind.as_str()),
+            filter.since,
+            filter.until,
+            i64::from(filter.limit),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let unexpected = |e: Report| AuditSinkError::UnexpectedError(e);
+
+                Ok(AuditEvent {
+                    id: row.id,
+                    kind: AuditEventKind::parse(&row.kind).map_err(unexpected)?,
+                    email: row
+                        .email
+                        .map(|email| Email::parse(email.into()))
+                        .transpose()
+                        .map_err(unexpected)?,
+                    reason: row.reason,
+                    ip_address: row
+                        .ip_address
+                        .map(|ip| ip.parse())
+                        .transpose()
+                        .map_err(|e| unexpected(Report::new(e)))?,
+                    user_agent: row.user_agent,
+                    request_id: row.request_id,
+                    occurred_at: row.occurred_at,
+                })
+            })
+            .collect()
+    }
+}
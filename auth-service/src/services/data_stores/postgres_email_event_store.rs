@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::{
+    domain::{Email, EmailEvent, EmailEventKind, EmailEventStore, EmailEventStoreError},
+    utils::metrics::time_store,
+};
+
+pub struct PostgresEmailEventStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailEventStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EmailEventStore for PostgresEmailEventStore {
+    #[tracing::instrument(name = "Adding email event to PostgreSQL", skip_all)]
+    async fn add_event(&mut self, event: EmailEvent) -> Result<(), EmailEventStoreError> {
+        let _timer = time_store("postgres", "email_event", "add_event");
+        sqlx::query!(
+            r#"
+            INSERT INTO email_events (id, email, kind, details, occurred_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            event.id,
+            event.email.as_ref().expose_secret(),
+            event.kind.as_str(),
+            event.details,
+            event.occurred_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailEventStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving email events from PostgreSQL", skip_all)]
+    async fn get_events(&self, email: &Email) -> Result<Vec<EmailEvent>, EmailEventStoreError> {
+        let _timer = time_store("postgres", "email_event", "get_events");
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, kind, details, occurred_at
+            FROM email_events
+            WHERE email = $1
+            ORDER BY occurred_at
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailEventStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(EmailEvent {
+                    id: row.id,
+                    email: email.clone(),
+                    kind: EmailEventKind::parse(&row.kind)
+                        .map_err(EmailEventStoreError::UnexpectedError)?,
+                    details: row.details,
+                    occurred_at: row.occurred_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Checking email suppression in PostgreSQL", skip_all)]
+    async fn is_suppressed(&self, email: &Email) -> Result<bool, EmailEventStoreError> {
+        let _timer = time_store("postgres", "email_event", "is_suppressed");
+        // A later delivery shows the address works again, and an operator can lift the
+        // suppression explicitly
+        let latest = sqlx::query_scalar!(
+            r#"
+            SELECT kind
+            FROM email_events
+            WHERE email = $1 AND kind IN ('hard_bounce', 'delivery', 'suppression_cleared')
+            ORDER BY occurred_at DESC, received_at DESC
+            LIMIT 1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EmailEventStoreError::UnexpectedError(e.into()))?;
+
+        Ok(latest.as_deref() == Some(EmailEventKind::HardBounce.as_str()))
+    }
+}
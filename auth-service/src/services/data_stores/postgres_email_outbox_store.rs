@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        Email, EmailContent, EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxStatus,
+    },
+    utils::metrics::time_store,
+};
+
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    #[tracing::instrument(name = "Queuing email in PostgreSQL", skip_all)]
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        let _timer = time_store("postgres", "email_outbox", "enqueue");
+        sqlx::query!(
+            r#"
+            INSERT INTO email_outbox (
+                id, idempotency_key, recipient, subject, html_body, text_body, traceparent
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (idempotency_key) WHERE status = 'pending' DO NOTHING
+            "#,
+            email.id,
+            email.idempotency_key,
+            email.recipient.as_ref().expose_secret(),
+            email.content.subject,
+            email.content.html_body,
+            email.content.text_body,
+            email.traceparent,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming due emails in PostgreSQL", skip_all)]
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let _timer = time_store("postgres", "email_outbox", "claim_due");
+        // SKIP LOCKED lets several workers claim batches concurrently without overlap
+        let rows = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET attempts = attempts + 1, next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING
+                id, idempotency_key, recipient, subject, html_body AS "html_body!",
+                text_body AS "text_body!", attempts, traceparent
+            "#,
+            now,
+            lease_until,
+            i64::from(limit),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(OutboxEmail {
+                    id: row.id,
+                    idempotency_key: row.idempotency_key,
+                    recipient: Email::parse(row.recipient.into())
+                        .map_err(EmailOutboxStoreError::UnexpectedError)?,
+                    content: EmailContent {
+                        subject: row.subject,
+                        html_body: row.html_body,
+                        text_body: row.text_body,
+                    },
+                    attempts: row.attempts as u32,
+                    traceparent: row.traceparent,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Marking email as sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        let _timer = time_store("postgres", "email_outbox", "mark_sent");
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sent', sent_at = now(), last_error = NULL,
+                html_body = NULL, text_body = NULL
+            WHERE id = $1
+            "#,
+            id,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking email as failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &mut self,
+        id: Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError> {
+        let _timer = time_store("postgres", "email_outbox", "mark_failed");
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = CASE WHEN $3::timestamptz IS NULL THEN 'dead' ELSE 'pending' END,
+                next_attempt_at = COALESCE($3, next_attempt_at),
+                last_error = $2,
+                html_body = CASE WHEN $3::timestamptz IS NULL THEN NULL ELSE html_body END,
+                text_body = CASE WHEN $3::timestamptz IS NULL THEN NULL ELSE text_body END
+            WHERE id = $1
+            "#,
+            id,
+            error,
+            retry_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving email status from PostgreSQL", skip_all)]
+    async fn get_status(&self, id: Uuid) -> Result<OutboxStatus, EmailOutboxStoreError> {
+        let _timer = time_store("postgres", "email_outbox", "get_status");
+        let row = sqlx::query!("SELECT status FROM email_outbox WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?
+            .ok_or(EmailOutboxStoreError::EmailNotFound)?;
+
+        OutboxStatus::parse(&row.status).map_err(EmailOutboxStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Purging finished emails from PostgreSQL", skip_all)]
+    async fn purge_finished(
+        &mut self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, EmailOutboxStoreError> {
+        let _timer = time_store("postgres", "email_outbox", "purge_finished");
+        let result = sqlx::query!(
+            "DELETE FROM email_outbox WHERE status <> 'pending' AND created_at < $1",
+            before,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use argon2::{
     password_hash::{rand_core::OsRng, SaltString},
     Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
@@ -9,9 +11,12 @@ use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
 use tokio::task;
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Locale, Password, PhoneNumber, TwoFAChannel, User,
+    },
+    utils::metrics::{record_password_hashing, time_store},
 };
 
 pub struct PostgresUserStore {
@@ -28,15 +33,26 @@ impl PostgresUserStore {
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "add_user");
         let password_hash = compute_password_hash(user.password.as_ref().clone())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
 
         sqlx::query!(
-            "INSERT INTO users VALUES ($1, $2, $3)",
+            r#"
+            INSERT INTO users (
+                email, password_hash, requires_2fa, phone_number, two_fa_channel, locale
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
             user.email.as_ref().expose_secret(),
             password_hash.expose_secret(),
             user.requires_2fa,
+            user.phone_number
+                .as_ref()
+                .map(|phone_number| phone_number.as_ref().expose_secret()),
+            user.two_fa_channel.as_str(),
+            user.locale.as_str(),
         )
         .execute(&self.pool)
         .await
@@ -54,8 +70,9 @@ impl UserStore for PostgresUserStore {
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        let requires_2fa = sqlx::query_scalar!(
-            "SELECT requires_2fa FROM users WHERE email = $1",
+        let _timer = time_store("postgres", "user", "get_user");
+        let row = sqlx::query!(
+            "SELECT requires_2fa, phone_number, two_fa_channel, locale FROM users WHERE email = $1",
             email.as_ref().expose_secret(),
         )
         .fetch_optional(&self.pool)
@@ -63,7 +80,23 @@ impl UserStore for PostgresUserStore {
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
         .ok_or(UserStoreError::UserNotFound)?;
 
-        Ok(User::new(email.clone(), Default::default(), requires_2fa))
+        let phone_number = row
+            .phone_number
+            .map(|phone_number| PhoneNumber::parse(phone_number.into()))
+            .transpose()
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let two_fa_channel =
+            TwoFAChannel::parse(&row.two_fa_channel).map_err(UserStoreError::UnexpectedError)?;
+
+        let locale = Locale::parse(&row.locale).map_err(UserStoreError::UnexpectedError)?;
+
+        Ok(User {
+            phone_number,
+            two_fa_channel,
+            locale,
+            ..User::new(email.clone(), Default::default(), row.requires_2fa)
+        })
     }
 
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
@@ -72,6 +105,7 @@ impl UserStore for PostgresUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "validate_user");
         let password_hash = sqlx::query_scalar!(
             "SELECT password_hash FROM users WHERE email = $1",
             email.as_ref().expose_secret(),
@@ -85,6 +119,102 @@ impl UserStore for PostgresUserStore {
             .await
             .map_err(|_| UserStoreError::InvalidCredentials)
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "update_password");
+        let password_hash = compute_password_hash(password.as_ref().clone())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            password_hash.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user 2FA requirement in PostgreSQL", skip_all)]
+    async fn update_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "update_requires_2fa");
+        let result = sqlx::query!(
+            "UPDATE users SET requires_2fa = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            requires_2fa,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user phone number in PostgreSQL", skip_all)]
+    async fn update_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "update_phone_number");
+        let result = sqlx::query!(
+            "UPDATE users SET phone_number = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            phone_number.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user 2FA channel in PostgreSQL", skip_all)]
+    async fn update_two_fa_channel(
+        &mut self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let _timer = time_store("postgres", "user", "update_two_fa_channel");
+        let result = sqlx::query!(
+            "UPDATE users SET two_fa_channel = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            channel.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
 }
 
 #[tracing::instrument(name = "Verify password hash", skip_all)]
@@ -96,12 +226,15 @@ async fn verify_password_hash(
 
     task::spawn_blocking(move || {
         current_span.in_scope(|| {
+            let started = Instant::now();
             let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())?;
 
-            Argon2::default().verify_password(
+            let verified = Argon2::default().verify_password(
                 password_candidate.expose_secret().as_bytes(),
                 &expected_password_hash,
-            )?;
+            );
+            record_password_hashing("verify", started.elapsed());
+            verified?;
 
             Ok(())
         })
@@ -115,6 +248,7 @@ async fn compute_password_hash(password: SecretString) -> Result<SecretString> {
 
     task::spawn_blocking(move || {
         current_span.in_scope(|| {
+            let started = Instant::now();
             let salt: SaltString = SaltString::generate(&mut OsRng);
 
             let password_hash = Argon2::new(
@@ -124,6 +258,7 @@ async fn compute_password_hash(password: SecretString) -> Result<SecretString> {
             )
             .hash_password(password.expose_secret().as_bytes(), &salt)?
             .to_string();
+            record_password_hashing("hash", started.elapsed());
 
             Ok(password_hash.into())
         })
@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        OutboxStatus, WebhookAttempt, WebhookDelivery, WebhookDeliveryStore,
+        WebhookDeliveryStoreError, WebhookEventKind,
+    },
+    utils::metrics::time_store,
+};
+
+pub struct PostgresWebhookDeliveryStore {
+    pool: PgPool,
+}
+
+impl PostgresWebhookDeliveryStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebhookDeliveryStore for PostgresWebhookDeliveryStore {
+    #[tracing::instrument(name = "Queuing webhook delivery in PostgreSQL", skip_all)]
+    async fn enqueue(
+        &mut self,
+        delivery: WebhookDelivery,
+    ) -> Result<(), WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "enqueue");
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (id, event_id, kind, url, payload, traceparent)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            delivery.id,
+            delivery.event_id,
+            delivery.kind.as_str(),
+            delivery.url,
+            delivery.payload,
+            delivery.traceparent,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming due webhook deliveries in PostgreSQL", skip_all)]
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDelivery>, WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "claim_due");
+        // SKIP LOCKED lets several workers claim batches concurrently without overlap
+        let rows = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET attempts = attempts + 1, next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, event_id, kind, url, payload, attempts, traceparent
+            "#,
+            now,
+            lease_until,
+            i64::from(limit),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WebhookDelivery {
+                    id: row.id,
+                    event_id: row.event_id,
+                    kind: WebhookEventKind::parse(&row.kind)
+                        .map_err(WebhookDeliveryStoreError::UnexpectedError)?,
+                    url: row.url,
+                    payload: row.payload,
+                    attempts: row.attempts as u32,
+                    traceparent: row.traceparent,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Marking webhook as delivered in PostgreSQL", skip_all)]
+    async fn mark_delivered(
+        &mut self,
+        id: Uuid,
+        attempt: WebhookAttempt,
+    ) -> Result<(), WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "mark_delivered");
+        let result = sqlx::query!(
+            r#"
+            WITH delivery AS (
+                UPDATE webhook_deliveries
+                SET status = 'sent', delivered_at = $2
+                WHERE id = $1
+                RETURNING id
+            )
+            INSERT INTO webhook_delivery_attempts (delivery_id, attempted_at, status_code, error)
+            SELECT id, $2, $3, $4 FROM delivery
+            "#,
+            id,
+            attempt.attempted_at,
+            attempt.status_code.map(i32::from),
+            attempt.error,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookDeliveryStoreError::DeliveryNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking webhook delivery as failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &mut self,
+        id: Uuid,
+        attempt: WebhookAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "mark_failed");
+        let result = sqlx::query!(
+            r#"
+            WITH delivery AS (
+                UPDATE webhook_deliveries
+                SET status = CASE WHEN $5::timestamptz IS NULL THEN 'dead' ELSE 'pending' END,
+                    next_attempt_at = COALESCE($5, next_attempt_at)
+                WHERE id = $1
+                RETURNING id
+            )
+            INSERT INTO webhook_delivery_attempts (delivery_id, attempted_at, status_code, error)
+            SELECT id, $2, $3, $4 FROM delivery
+            "#,
+            id,
+            attempt.attempted_at,
+            attempt.status_code.map(i32::from),
+            attempt.error,
+            retry_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookDeliveryStoreError::DeliveryNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook delivery status from PostgreSQL", skip_all)]
+    async fn get_status(&self, id: Uuid) -> Result<OutboxStatus, WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "get_status");
+        let row = sqlx::query!("SELECT status FROM webhook_deliveries WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?
+            .ok_or(WebhookDeliveryStoreError::DeliveryNotFound)?;
+
+        OutboxStatus::parse(&row.status).map_err(WebhookDeliveryStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook deliveries from PostgreSQL", skip_all)]
+    async fn get_deliveries(
+        &self,
+        event_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "get_deliveries");
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, event_id, kind, url, payload, attempts, traceparent
+            FROM webhook_deliveries
+            WHERE event_id = $1
+            ORDER BY created_at
+            "#,
+            event_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WebhookDelivery {
+                    id: row.id,
+                    event_id: row.event_id,
+                    kind: WebhookEventKind::parse(&row.kind)
+                        .map_err(WebhookDeliveryStoreError::UnexpectedError)?,
+                    url: row.url,
+                    payload: row.payload,
+                    attempts: row.attempts as u32,
+                    traceparent: row.traceparent,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook delivery log from PostgreSQL", skip_all)]
+    async fn get_attempts(
+        &self,
+        id: Uuid,
+    ) -> Result<Vec<WebhookAttempt>, WebhookDeliveryStoreError> {
+        let _timer = time_store("postgres", "webhook_deliveries", "get_attempts");
+        let rows = sqlx::query!(
+            r#"
+            SELECT attempted_at, status_code, error
+            FROM webhook_delivery_attempts
+            WHERE delivery_id = $1
+            ORDER BY attempted_at
+            "#,
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookDeliveryStoreError::UnexpectedError(e.into()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WebhookAttempt {
+                attempted_at: row.attempted_at,
+                status_code: row.status_code.map(|code| code as u16),
+                error: row.error,
+            })
+            .collect())
+    }
+}
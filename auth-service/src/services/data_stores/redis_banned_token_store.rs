@@ -1,14 +1,18 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use color_eyre::eyre::Context;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
 use redis::{Commands, Connection};
 use secrecy::{ExposeSecret, SecretString};
 use tokio::sync::RwLock;
 
 use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    domain::{
+        data_stores::{BannedTokenStore, BannedTokenStoreError},
+        Email,
+    },
+    utils::{auth::TOKEN_TTL_SECONDS, metrics::time_store},
 };
 
 pub struct RedisBannedTokenStore {
@@ -25,6 +29,7 @@ impl RedisBannedTokenStore {
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Add the token to the banned token store", skip_all)]
     async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "add_token");
         let key = get_key(&token);
 
         let seconds = TOKEN_TTL_SECONDS
@@ -43,6 +48,7 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
     #[tracing::instrument(name = "Check if the token exists in the banned token store", skip_all)]
     async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "contains_token");
         let key = get_key(token);
         let mut conn = self.conn.write().await;
 
@@ -50,6 +56,58 @@ impl BannedTokenStore for RedisBannedTokenStore {
             .wrap_err("failed to check if token exists in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)
     }
+
+    #[tracing::instrument(name = "Revoke the user's tokens in the banned token store", skip_all)]
+    async fn revoke_tokens_before(
+        &mut self,
+        email: &Email,
+        time: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "revoke_tokens_before");
+
+        // Every token revoked has expired by the time the key does
+        let seconds = TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        conn.set_ex::<_, _, ()>(get_revoked_before_key(email), time.timestamp(), seconds)
+            .wrap_err("failed to set token revocation time in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Get when the user's tokens were revoked from the banned token store",
+        skip_all
+    )]
+    async fn get_tokens_revoked_before(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+        let _timer = time_store("redis", "banned_token", "get_tokens_revoked_before");
+        let mut conn = self.conn.write().await;
+
+        let timestamp = conn
+            .get::<_, Option<i64>>(get_revoked_before_key(email))
+            .wrap_err("failed to get token revocation time from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        drop(conn);
+
+        timestamp
+            .map(|timestamp| {
+                DateTime::from_timestamp(timestamp, 0).ok_or_else(|| {
+                    BannedTokenStoreError::UnexpectedError(eyre!(
+                        "invalid token revocation timestamp"
+                    ))
+                })
+            })
+            .transpose()
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
@@ -58,3 +116,13 @@ const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 fn get_key(token: &SecretString) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token.expose_secret())
 }
+
+const TOKENS_REVOKED_BEFORE_KEY_PREFIX: &str = "tokens_revoked_before:";
+
+fn get_revoked_before_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        TOKENS_REVOKED_BEFORE_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{Email, Session, SessionId, SessionStore, SessionStoreError},
+    utils::metrics::time_store,
+};
+
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Add the session to the redis session store", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let _timer = time_store("redis", "session", "add_session");
+        let key = get_key(&session.id);
+        let user_key = get_user_key(&session.email);
+
+        let seconds: u64 = (session.expires_at - session.created_at)
+            .num_seconds()
+            .try_into()
+            .wrap_err("failed to cast session TTL to u64")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let value = serde_json::to_string(&SessionRecord::from(&session))
+            .wrap_err("failed to serialize session")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        conn.set_ex::<_, _, ()>(key, value, seconds)
+            .wrap_err("failed to set session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        // Index the session by user so that all of a user's sessions can be listed.
+        // The index lives as long as the newest session, and stale members are pruned on read.
+        conn.sadd::<_, _, ()>(&user_key, session.id.as_ref().expose_secret())
+            .wrap_err("failed to add session to user index in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        conn.expire::<_, ()>(&user_key, seconds as i64)
+            .wrap_err("failed to set user session index TTL in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get the session from the redis session store", skip_all)]
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        let _timer = time_store("redis", "session", "get_session");
+        let key = get_key(id);
+        let mut conn = self.conn.write().await;
+
+        let value = conn
+            .get::<_, Option<String>>(key)
+            .wrap_err("failed to get session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?
+            .ok_or(SessionStoreError::SessionNotFound)?;
+
+        drop(conn);
+
+        parse_session(id.clone(), &value)
+    }
+
+    #[tracing::instrument(
+        name = "Get the user's sessions from the redis session store",
+        skip_all
+    )]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let _timer = time_store("redis", "session", "get_sessions");
+        let user_key = get_user_key(email);
+        let mut conn = self.conn.write().await;
+
+        let ids = conn
+            .smembers::<_, Vec<String>>(&user_key)
+            .wrap_err("failed to get user session index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let session_id =
+                SessionId::parse(id.clone().into()).map_err(SessionStoreError::UnexpectedError)?;
+
+            let value = conn
+                .get::<_, Option<String>>(get_key(&session_id))
+                .wrap_err("failed to get session from Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+
+            match value {
+                Some(value) => sessions.push(parse_session(session_id, &value)?),
+                None => conn
+                    .srem::<_, _, ()>(&user_key, id)
+                    .wrap_err("failed to prune user session index in Redis")
+                    .map_err(SessionStoreError::UnexpectedError)?,
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "Remove the session from the redis session store", skip_all)]
+    async fn remove_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let _timer = time_store("redis", "session", "remove_session");
+        let session = match self.get_session(id).await {
+            Ok(session) => session,
+            Err(SessionStoreError::SessionNotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut conn = self.conn.write().await;
+
+        conn.del::<_, ()>(get_key(id))
+            .wrap_err("failed to delete session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        conn.srem::<_, _, ()>(get_user_key(&session.email), id.as_ref().expose_secret())
+            .wrap_err("failed to remove session from user index in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionRecord {
+    email: String,
+    created_at: i64,
+    expires_at: i64,
+}
+
+impl From<&Session> for SessionRecord {
+    fn from(session: &Session) -> Self {
+        Self {
+            email: session.email.as_ref().expose_secret().to_owned(),
+            created_at: session.created_at.timestamp(),
+            expires_at: session.expires_at.timestamp(),
+        }
+    }
+}
+
+fn parse_session(id: SessionId, value: &str) -> Result<Session, SessionStoreError> {
+    let record: SessionRecord = serde_json::from_str(value)
+        .wrap_err("failed to deserialize session")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+    let email = Email::parse(record.email.into()).map_err(SessionStoreError::UnexpectedError)?;
+
+    let created_at = DateTime::from_timestamp(record.created_at, 0)
+        .ok_or_else(|| SessionStoreError::UnexpectedError(eyre!("invalid session timestamp")))?;
+
+    let expires_at = DateTime::from_timestamp(record.expires_at, 0)
+        .ok_or_else(|| SessionStoreError::UnexpectedError(eyre!("invalid session timestamp")))?;
+
+    Ok(Session {
+        id,
+        email,
+        created_at,
+        expires_at,
+    })
+}
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions:";
+
+fn get_key(id: &SessionId) -> String {
+    format!("{}{}", SESSION_KEY_PREFIX, id.as_ref().expose_secret())
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_SESSIONS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{DeviceId, Email, TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError},
+    utils::metrics::time_store,
+};
+
+pub struct RedisTrustedDeviceStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisTrustedDeviceStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait]
+impl TrustedDeviceStore for RedisTrustedDeviceStore {
+    #[tracing::instrument(name = "Add the device to the redis trusted device store", skip_all)]
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        let _timer = time_store("redis", "trusted_device", "add_device");
+        let key = get_key(&device.id);
+        let user_key = get_user_key(&device.email);
+
+        let seconds: u64 = (device.expires_at - device.created_at)
+            .num_seconds()
+            .try_into()
+            .wrap_err("failed to cast device TTL to u64")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        let value = serde_json::to_string(&DeviceRecord::from(&device))
+            .wrap_err("failed to serialize device")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        conn.set_ex::<_, _, ()>(key, value, seconds)
+            .wrap_err("failed to set device in Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        // Index the device by user so that all of a user's devices can be listed.
+        // The index lives as long as the newest device, and stale members are pruned on read.
+        conn.sadd::<_, _, ()>(&user_key, device.id.as_ref().expose_secret())
+            .wrap_err("failed to add device to user index in Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        conn.expire::<_, ()>(&user_key, seconds as i64)
+            .wrap_err("failed to set user device index TTL in Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get the device from the redis trusted device store", skip_all)]
+    async fn get_device(&self, id: &DeviceId) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        let _timer = time_store("redis", "trusted_device", "get_device");
+        let key = get_key(id);
+        let mut conn = self.conn.write().await;
+
+        let value = conn
+            .get::<_, Option<String>>(key)
+            .wrap_err("failed to get device from Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?
+            .ok_or(TrustedDeviceStoreError::DeviceNotFound)?;
+
+        drop(conn);
+
+        parse_device(id.clone(), &value)
+    }
+
+    #[tracing::instrument(
+        name = "Get the user's devices from the redis trusted device store",
+        skip_all
+    )]
+    async fn get_devices(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let _timer = time_store("redis", "trusted_device", "get_devices");
+        let user_key = get_user_key(email);
+        let mut conn = self.conn.write().await;
+
+        let ids = conn
+            .smembers::<_, Vec<String>>(&user_key)
+            .wrap_err("failed to get user device index from Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        let mut devices = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let device_id = DeviceId::parse(id.clone().into())
+                .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+            let value = conn
+                .get::<_, Option<String>>(get_key(&device_id))
+                .wrap_err("failed to get device from Redis")
+                .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+            match value {
+                Some(value) => devices.push(parse_device(device_id, &value)?),
+                None => conn
+                    .srem::<_, _, ()>(&user_key, id)
+                    .wrap_err("failed to prune user device index in Redis")
+                    .map_err(TrustedDeviceStoreError::UnexpectedError)?,
+            }
+        }
+
+        Ok(devices)
+    }
+
+    #[tracing::instrument(
+        name = "Remove the device from the redis trusted device store",
+        skip_all
+    )]
+    async fn remove_device(&mut self, id: &DeviceId) -> Result<(), TrustedDeviceStoreError> {
+        let _timer = time_store("redis", "trusted_device", "remove_device");
+        let device = match self.get_device(id).await {
+            Ok(device) => device,
+            Err(TrustedDeviceStoreError::DeviceNotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut conn = self.conn.write().await;
+
+        conn.del::<_, ()>(get_key(id))
+            .wrap_err("failed to delete device from Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        conn.srem::<_, _, ()>(get_user_key(&device.email), id.as_ref().expose_secret())
+            .wrap_err("failed to remove device from user index in Redis")
+            .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeviceRecord {
+    email: String,
+    created_at: i64,
+    expires_at: i64,
+}
+
+impl From<&TrustedDevice> for DeviceRecord {
+    fn from(device: &TrustedDevice) -> Self {
+        Self {
+            email: device.email.as_ref().expose_secret().to_owned(),
+            created_at: device.created_at.timestamp(),
+            expires_at: device.expires_at.timestamp(),
+        }
+    }
+}
+
+fn parse_device(id: DeviceId, value: &str) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+    let record: DeviceRecord = serde_json::from_str(value)
+        .wrap_err("failed to deserialize device")
+        .map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+    let email =
+        Email::parse(record.email.into()).map_err(TrustedDeviceStoreError::UnexpectedError)?;
+
+    let created_at = DateTime::from_timestamp(record.created_at, 0).ok_or_else(|| {
+        TrustedDeviceStoreError::UnexpectedError(eyre!("invalid device timestamp"))
+    })?;
+
+    let expires_at = DateTime::from_timestamp(record.expires_at, 0).ok_or_else(|| {
+        TrustedDeviceStoreError::UnexpectedError(eyre!("invalid device timestamp"))
+    })?;
+
+    Ok(TrustedDevice {
+        id,
+        email,
+        created_at,
+        expires_at,
+    })
+}
+
+const DEVICE_KEY_PREFIX: &str = "trusted_device:";
+const USER_DEVICES_KEY_PREFIX: &str = "user_trusted_devices:";
+
+fn get_key(id: &DeviceId) -> String {
+    format!("{}{}", DEVICE_KEY_PREFIX, id.as_ref().expose_secret())
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_DEVICES_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
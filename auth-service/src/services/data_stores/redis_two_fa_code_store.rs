@@ -1,15 +1,22 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use color_eyre::eyre::Context;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
 use redis::{Commands, Connection};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{
+            LoginAttemptId, ResendStatus, TwoFACode, TwoFACodePurpose, TwoFACodeStore,
+            TwoFACodeStoreError,
+        },
+        Email, PhoneNumber,
+    },
+    utils::{constants::MAX_PENDING_LOGIN_ATTEMPTS, metrics::time_store},
 };
 
 pub struct RedisTwoFACodeStore {
@@ -20,6 +27,41 @@ impl RedisTwoFACodeStore {
     pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
         Self { conn }
     }
+
+    async fn get_entry(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<TwoFAEntry, TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        let value = conn
+            .get::<_, String>(key)
+            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        drop(conn);
+
+        serde_json::from_str(&value)
+            .wrap_err("failed to deserialize 2FA entry")
+            .map_err(TwoFACodeStoreError::UnexpectedError)
+    }
+
+    async fn get_phone_verification_entry(
+        &self,
+        email: &Email,
+    ) -> Result<PhoneVerificationEntry, TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let value = conn
+            .get::<_, String>(get_phone_verification_key(email))
+            .map_err(|_| TwoFACodeStoreError::PhoneVerificationNotFound)?;
+
+        drop(conn);
+
+        serde_json::from_str(&value)
+            .wrap_err("failed to deserialize phone verification")
+            .map_err(TwoFACodeStoreError::UnexpectedError)
+    }
 }
 
 #[async_trait]
@@ -30,16 +72,26 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        purpose: TwoFACodePurpose,
     ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let _timer = time_store("redis", "two_fa_code", "add_code");
+        let key = get_key(&login_attempt_id);
+        let user_key = get_user_key(&email);
+        let id = login_attempt_id.as_ref().expose_secret();
 
-        let two_fa_tuple = TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().into(),
-            code.as_ref().expose_secret().into(),
-        );
+        let now = Utc::now().timestamp_millis();
+
+        let entry = TwoFAEntry {
+            email: email.as_ref().expose_secret().to_owned(),
+            code: code.as_ref().expose_secret().to_owned(),
+            purpose: purpose.as_str().to_owned(),
+            sent_at: now,
+            resend_count: 0,
+            failed_attempts: 0,
+        };
 
-        let value = serde_json::to_string(&two_fa_tuple)
-            .wrap_err("failed to serialize 2FA tuple")
+        let value = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize 2FA entry")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
         let mut conn = self.conn.write().await;
@@ -48,53 +100,421 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
+        // Index the user's pending attempts by creation time, dropping the expired ones,
+        // so that the oldest attempts can be evicted once there are too many
+        conn.zadd::<_, _, _, ()>(&user_key, id, now)
+            .wrap_err("failed to add login attempt to user index in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        conn.zrembyscore::<_, _, _, ()>(
+            &user_key,
+            "-inf",
+            now - TEN_MINUTES_IN_SECONDS as i64 * 1000,
+        )
+        .wrap_err("failed to prune user login attempt index in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        conn.expire::<_, ()>(&user_key, TEN_MINUTES_IN_SECONDS as i64)
+            .wrap_err("failed to set user login attempt index TTL in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let ids = conn
+            .zrange::<_, Vec<String>>(&user_key, 0, -1)
+            .wrap_err("failed to get user login attempt index from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let excess = ids.len().saturating_sub(MAX_PENDING_LOGIN_ATTEMPTS);
+
+        for stale_id in &ids[..excess] {
+            conn.del::<_, ()>(format!("{}{}", TWO_FA_CODE_PREFIX, stale_id))
+                .wrap_err("failed to delete 2FA code from Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+            conn.zrem::<_, _, ()>(&user_key, stale_id)
+                .wrap_err("failed to remove login attempt from user index in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        }
+
         Ok(())
     }
 
     #[tracing::instrument(name = "Remove the 2FA code from the redis 2FA code store", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "remove_code");
+        let (email, _, _) = match self.get_code(login_attempt_id).await {
+            Ok(entry) => entry,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
         let mut conn = self.conn.write().await;
 
-        conn.del::<_, ()>(key)
+        conn.del::<_, ()>(get_key(login_attempt_id))
             .wrap_err("failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
+        conn.zrem::<_, _, ()>(
+            get_user_key(&email),
+            login_attempt_id.as_ref().expose_secret(),
+        )
+        .wrap_err("failed to remove login attempt from user index in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
         Ok(())
     }
 
     #[tracing::instrument(name = "Get the 2FA code from the redis 2FA code store", skip_all)]
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode, TwoFACodePurpose), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "get_code");
+        let TwoFAEntry {
+            email,
+            code,
+            purpose,
+            ..
+        } = self.get_entry(login_attempt_id).await?;
+
+        let email = Email::parse(email.into()).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let two_fa_code =
+            TwoFACode::parse(code.into()).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let purpose =
+            TwoFACodePurpose::parse(&purpose).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((email, two_fa_code, purpose))
+    }
+
+    #[tracing::instrument(name = "Update the 2FA code in the redis 2FA code store", skip_all)]
+    async fn update_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "update_code");
+        let mut entry = self.get_entry(login_attempt_id).await?;
+        entry.code = code.as_ref().expose_secret().to_owned();
+        entry.sent_at = Utc::now().timestamp_millis();
+        entry.resend_count += 1;
+
+        let value = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize 2FA entry")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let key = get_key(login_attempt_id);
         let mut conn = self.conn.write().await;
 
-        let value = conn
-            .get::<_, String>(key)
-            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        // Resending doesn't extend the login attempt
+        let ttl = conn
+            .ttl::<_, i64>(&key)
+            .wrap_err("failed to get 2FA code TTL from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if ttl <= 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
 
-        let TwoFATuple(login_attempt_id, two_fa_code) = serde_json::from_str(&value)
-            .wrap_err("failed to deserialize 2FA tuple")
+        conn.set_ex::<_, _, ()>(key, value, ttl as u64)
+            .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let login_attempt_id = LoginAttemptId::parse(login_attempt_id.into())
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Get the 2FA resend status from the redis 2FA code store",
+        skip_all
+    )]
+    async fn get_resend_status(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<ResendStatus, TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "get_resend_status");
+        let entry = self.get_entry(login_attempt_id).await?;
+
+        let last_sent_at = DateTime::from_timestamp_millis(entry.sent_at).ok_or_else(|| {
+            TwoFACodeStoreError::UnexpectedError(eyre!("invalid 2FA code timestamp"))
+        })?;
+
+        Ok(ResendStatus {
+            last_sent_at,
+            resend_count: entry.resend_count,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "Record a failed 2FA code in the redis 2FA code store",
+        skip_all
+    )]
+    async fn record_failed_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "record_failed_code");
+        let mut entry = self.get_entry(login_attempt_id).await?;
+        entry.failed_attempts += 1;
+
+        if entry.failed_attempts >= max_attempts {
+            return self.remove_code(login_attempt_id).await;
+        }
+
+        let value = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize 2FA entry")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let two_fa_code =
-            TwoFACode::parse(two_fa_code.into()).map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let key = get_key(login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        // Failed attempts don't extend the login attempt
+        let ttl = conn
+            .ttl::<_, i64>(&key)
+            .wrap_err("failed to get 2FA code TTL from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if ttl <= 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        conn.set_ex::<_, _, ()>(key, value, ttl as u64)
+            .wrap_err("failed to set 2FA code in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Add the phone verification to the redis 2FA code store",
+        skip_all
+    )]
+    async fn add_phone_verification(
+        &mut self,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "add_phone_verification");
+        let resend_count = match self.get_phone_verification_entry(&email).await {
+            Ok(pending) => pending.resend_count + 1,
+            Err(TwoFACodeStoreError::PhoneVerificationNotFound) => 0,
+            Err(e) => return Err(e),
+        };
+
+        let entry = PhoneVerificationEntry {
+            phone_number: phone_number.as_ref().expose_secret().to_owned(),
+            code: Some(code.as_ref().expose_secret().to_owned()),
+            sent_at: Utc::now().timestamp_millis(),
+            resend_count,
+            failed_attempts: 0,
+        };
+
+        let value = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize phone verification")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        conn.set_ex::<_, _, ()>(
+            get_phone_verification_key(&email),
+            value,
+            TEN_MINUTES_IN_SECONDS,
+        )
+        .wrap_err("failed to set phone verification in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Get the phone verification from the redis 2FA code store",
+        skip_all
+    )]
+    async fn get_phone_verification(
+        &self,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "get_phone_verification");
+        let PhoneVerificationEntry {
+            phone_number, code, ..
+        } = self.get_phone_verification_entry(email).await?;
+
+        // Invalidated after too many incorrect codes
+        let code = code.ok_or(TwoFACodeStoreError::PhoneVerificationNotFound)?;
+
+        let phone_number = PhoneNumber::parse(phone_number.into())
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let code = TwoFACode::parse(code.into()).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((phone_number, code))
+    }
+
+    #[tracing::instrument(
+        name = "Get the phone verification resend status from the redis 2FA code store",
+        skip_all
+    )]
+    async fn get_phone_verification_resend_status(
+        &self,
+        email: &Email,
+    ) -> Result<ResendStatus, TwoFACodeStoreError> {
+        let _timer = time_store(
+            "redis",
+            "two_fa_code",
+            "get_phone_verification_resend_status",
+        );
+        let entry = self.get_phone_verification_entry(email).await?;
+
+        let last_sent_at = DateTime::from_timestamp_millis(entry.sent_at).ok_or_else(|| {
+            TwoFACodeStoreError::UnexpectedError(eyre!("invalid phone verification timestamp"))
+        })?;
+
+        Ok(ResendStatus {
+            last_sent_at,
+            resend_count: entry.resend_count,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "Record a failed phone verification in the redis 2FA code store",
+        skip_all
+    )]
+    async fn record_failed_phone_verification(
+        &mut self,
+        email: &Email,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "record_failed_phone_verification");
+        let mut entry = self.get_phone_verification_entry(email).await?;
+        entry.failed_attempts += 1;
+
+        if entry.failed_attempts >= max_attempts {
+            entry.code = None;
+        }
+
+        let value = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize phone verification")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let key = get_phone_verification_key(email);
+        let mut conn = self.conn.write().await;
+
+        // Failed attempts don't extend the verification
+        let ttl = conn
+            .ttl::<_, i64>(&key)
+            .wrap_err("failed to get phone verification TTL from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        Ok((login_attempt_id, two_fa_code))
+        if ttl <= 0 {
+            return Err(TwoFACodeStoreError::PhoneVerificationNotFound);
+        }
+
+        conn.set_ex::<_, _, ()>(key, value, ttl as u64)
+            .wrap_err("failed to set phone verification in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Remove the phone verification from the redis 2FA code store",
+        skip_all
+    )]
+    async fn remove_phone_verification(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let _timer = time_store("redis", "two_fa_code", "remove_phone_verification");
+        let mut conn = self.conn.write().await;
+
+        conn.del::<_, ()>(get_phone_verification_key(email))
+            .wrap_err("failed to delete phone verification from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
     }
 }
 
 #[derive(Serialize, Deserialize)]
-struct TwoFATuple(pub String, pub String);
+struct TwoFAEntry {
+    email: String,
+    code: String,
+    // Entries written before codes had a purpose were all sent for logins
+    #[serde(default = "default_purpose")]
+    purpose: String,
+    // Milliseconds since the epoch. Defaulted for entries written before resends were
+    // tracked, which then may be resent straight away.
+    #[serde(default)]
+    sent_at: i64,
+    #[serde(default)]
+    resend_count: u32,
+    #[serde(default)]
+    failed_attempts: u32,
+}
+
+fn default_purpose() -> String {
+    TwoFACodePurpose::Login.as_str().to_owned()
+}
+
+#[derive(Serialize, Deserialize)]
+struct PhoneVerificationEntry {
+    phone_number: String,
+    // Cleared after too many incorrect codes
+    code: Option<String>,
+    // Milliseconds since the epoch. Defaulted, like the counts, for entries written before
+    // they were tracked.
+    #[serde(default)]
+    sent_at: i64,
+    #[serde(default)]
+    resend_count: u32,
+    #[serde(default)]
+    failed_attempts: u32,
+}
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const USER_LOGIN_ATTEMPTS_PREFIX: &str = "user_login_attempts:";
+const PHONE_VERIFICATION_PREFIX: &str = "phone_verification:";
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn get_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}",
+        TWO_FA_CODE_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_LOGIN_ATTEMPTS_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_phone_verification_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        PHONE_VERIFICATION_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_entries_written_before_resends_were_tracked() {
+        let entry: TwoFAEntry =
+            serde_json::from_str(r#"{"email":"test@example.com","code":"123456"}"#).unwrap();
+
+        assert_eq!(entry.purpose, "login");
+        assert_eq!(entry.sent_at, 0);
+        assert_eq!(entry.resend_count, 0);
+    }
 }
@@ -0,0 +1,364 @@
+use std::future::Future;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    app_state::EmailOutboxStoreType,
+    domain::{EmailClient, EmailOutboxStore, EmailSuppressed, OutboxEmail},
+    services::outbox_worker::{self, retry_delay, OutboxWorker},
+    utils::{
+        constants::{
+            email_outbox::RETENTION_DAYS,
+            outbox::{BATCH_SIZE, MAX_ATTEMPTS},
+        },
+        metrics::record_email_delivery,
+        tracing::trace_context_from,
+    },
+};
+
+// Delivers queued emails through the real email client, see `OutboxWorker`
+pub struct EmailOutboxWorker<EmailOutboxStoreImpl, EmailClientImpl> {
+    outbox: EmailOutboxStoreType<EmailOutboxStoreImpl>,
+    email_client: EmailClientImpl,
+}
+
+impl<EmailOutboxStoreImpl, EmailClientImpl> EmailOutboxWorker<EmailOutboxStoreImpl, EmailClientImpl>
+where
+    EmailOutboxStoreImpl: EmailOutboxStore + Send + Sync,
+    EmailClientImpl: EmailClient + Send + Sync,
+{
+    pub fn new(
+        outbox: EmailOutboxStoreType<EmailOutboxStoreImpl>,
+        email_client: EmailClientImpl,
+    ) -> Self {
+        Self {
+            outbox,
+            email_client,
+        }
+    }
+
+    // Poll the outbox until `shutdown` resolves, then deliver what's due by then, e.g. the
+    // 2FA codes of logins that were still in flight
+    pub async fn run<F>(self, shutdown: F)
+    where
+        F: Future<Output = ()>,
+    {
+        outbox_worker::run(&self, shutdown).await
+    }
+
+    // Attempt delivery of one batch of due emails, returning how many were attempted
+    #[tracing::instrument(name = "Processing email outbox", skip_all)]
+    pub async fn process_due(&self, now: DateTime<Utc>) -> Result<usize> {
+        outbox_worker::process_due(self, now).await
+    }
+
+    // Delete sent and dead-lettered emails older than the retention period
+    #[tracing::instrument(name = "Purging email outbox", skip_all)]
+    pub async fn purge_finished(&self, now: DateTime<Utc>) -> Result<u64> {
+        let before = now - Duration::days(RETENTION_DAYS);
+        let purged = self.outbox.write().await.purge_finished(before).await?;
+
+        Ok(purged)
+    }
+}
+
+#[async_trait]
+impl<EmailOutboxStoreImpl, EmailClientImpl> OutboxWorker
+    for EmailOutboxWorker<EmailOutboxStoreImpl, EmailClientImpl>
+where
+    EmailOutboxStoreImpl: EmailOutboxStore + Send + Sync,
+    EmailClientImpl: EmailClient + Send + Sync,
+{
+    type Item = OutboxEmail;
+
+    const NAME: &'static str = "email outbox";
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEmail>> {
+        let emails = self
+            .outbox
+            .write()
+            .await
+            .claim_due(now, lease_until, BATCH_SIZE)
+            .await?;
+
+        Ok(emails)
+    }
+
+    async fn deliver(&self, email: &OutboxEmail, now: DateTime<Utc>) -> Result<()> {
+        let sent = self
+            .email_client
+            .send_email(&email.recipient, &email.content)
+            .await;
+
+        let mut outbox = self.outbox.write().await;
+
+        match sent {
+            Ok(()) => {
+                record_email_delivery("sent");
+                outbox.mark_sent(email.id).await?
+            }
+            // The address hard-bounced after the email was queued, so retrying won't help
+            Err(e) if e.is::<EmailSuppressed>() => {
+                tracing::warn!("Dropping email to suppressed address");
+                record_email_delivery("suppressed");
+
+                outbox
+                    .mark_failed(email.id, &format!("{e:?}"), None)
+                    .await?;
+            }
+            Err(e) if email.attempts >= MAX_ATTEMPTS => {
+                tracing::error!(
+                    "Giving up on email after {} attempts: {:?}",
+                    email.attempts,
+                    e
+                );
+                record_email_delivery("dead_lettered");
+
+                outbox
+                    .mark_failed(email.id, &format!("{e:?}"), None)
+                    .await?;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to deliver email, will retry: {:?}", e);
+                record_email_delivery("retrying");
+
+                let retry_at = now + retry_delay(email.attempts);
+
+                outbox
+                    .mark_failed(email.id, &format!("{e:?}"), Some(retry_at))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Delivery is part of the trace of the request that queued the email
+    fn delivery_span(&self, email: &OutboxEmail) -> Span {
+        let span = tracing::info_span!("Delivering queued email", id = %email.id);
+        let _ = span.set_parent(trace_context_from(email.traceparent.as_deref()));
+
+        span
+    }
+
+    async fn purge(&self, now: DateTime<Utc>) -> Result<()> {
+        self.purge_finished(now).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+    use color_eyre::eyre::eyre;
+    use tokio::sync::RwLock;
+
+    use crate::{
+        domain::{Email, EmailContent, OutboxStatus},
+        services::data_stores::HashmapEmailOutboxStore,
+        utils::constants::outbox::POLL_INTERVAL,
+    };
+
+    use super::*;
+
+    // Fails the first `failures` sends, then succeeds
+    struct FlakyEmailClient {
+        failures: u32,
+        attempts: AtomicU32,
+    }
+
+    impl FlakyEmailClient {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures,
+                attempts: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _: &Email, _: &EmailContent) -> Result<()> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(eyre!("Provider unavailable"));
+            }
+
+            Ok(())
+        }
+    }
+
+    async fn setup(
+        failures: u32,
+    ) -> (
+        EmailOutboxWorker<HashmapEmailOutboxStore, FlakyEmailClient>,
+        OutboxEmail,
+    ) {
+        let outbox = Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+
+        let email = OutboxEmail::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            EmailContent {
+                subject: "Subject".to_owned(),
+                html_body: "<p>Body</p>".to_owned(),
+                text_body: "Body".to_owned(),
+            },
+        );
+
+        outbox.write().await.enqueue(email.clone()).await.unwrap();
+
+        let worker = EmailOutboxWorker::new(outbox, FlakyEmailClient::new(failures));
+
+        (worker, email)
+    }
+
+    async fn status(
+        worker: &EmailOutboxWorker<HashmapEmailOutboxStore, FlakyEmailClient>,
+        email: &OutboxEmail,
+    ) -> OutboxStatus {
+        worker
+            .outbox
+            .read()
+            .await
+            .get_status(email.id)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_flush_due_emails_on_shutdown() {
+        let (worker, email) = setup(0).await;
+        let outbox = worker.outbox.clone();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let running = tokio::spawn(worker.run(async {
+            let _ = shutdown_rx.await;
+        }));
+
+        // Let the worker deliver the first email and start waiting for the next poll
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let queued_late = OutboxEmail::new(
+            email.recipient.clone(),
+            EmailContent {
+                subject: "Another subject".to_owned(),
+                ..email.content.clone()
+            },
+        );
+        outbox
+            .write()
+            .await
+            .enqueue(queued_late.clone())
+            .await
+            .unwrap();
+
+        shutdown_tx.send(()).unwrap();
+
+        // Returns without waiting for the next poll
+        tokio::time::timeout(POLL_INTERVAL / 2, running)
+            .await
+            .expect("worker didn't stop")
+            .unwrap();
+
+        for email in [email, queued_late] {
+            let status = outbox.read().await.get_status(email.id).await.unwrap();
+            assert_eq!(status, OutboxStatus::Sent);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_deliver_due_emails() {
+        let (worker, email) = setup(0).await;
+        let now = Utc::now();
+
+        assert_eq!(worker.process_due(now).await.unwrap(), 1);
+        assert_eq!(status(&worker, &email).await, OutboxStatus::Sent);
+
+        // Sent emails aren't delivered again
+        let later = now + Duration::hours(1);
+        assert_eq!(worker.process_due(later).await.unwrap(), 0);
+        assert_eq!(worker.email_client.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_retry_failed_delivery_after_backoff() {
+        let (worker, email) = setup(1).await;
+        let now = Utc::now();
+
+        assert_eq!(worker.process_due(now).await.unwrap(), 1);
+        assert_eq!(status(&worker, &email).await, OutboxStatus::Pending);
+
+        // Not due again until the retry delay has passed
+        let retry_at = now + retry_delay(1);
+        let too_soon = retry_at - Duration::seconds(1);
+        assert_eq!(worker.process_due(too_soon).await.unwrap(), 0);
+
+        assert_eq!(worker.process_due(retry_at).await.unwrap(), 1);
+        assert_eq!(status(&worker, &email).await, OutboxStatus::Sent);
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_after_max_attempts() {
+        let (worker, email) = setup(u32::MAX).await;
+        let mut now = Utc::now();
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            assert_eq!(worker.process_due(now).await.unwrap(), 1);
+            now += retry_delay(attempt);
+        }
+
+        assert_eq!(status(&worker, &email).await, OutboxStatus::Dead);
+
+        let later = now + Duration::days(1);
+        assert_eq!(worker.process_due(later).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_deliver_a_batch_concurrently() {
+        struct SlowEmailClient;
+
+        #[async_trait]
+        impl EmailClient for SlowEmailClient {
+            async fn send_email(&self, _: &Email, _: &EmailContent) -> Result<()> {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                Ok(())
+            }
+        }
+
+        let (worker, email) = setup(0).await;
+
+        for i in 1..BATCH_SIZE {
+            let email = OutboxEmail::new(
+                email.recipient.clone(),
+                EmailContent {
+                    subject: format!("Subject {i}"),
+                    ..email.content.clone()
+                },
+            );
+            worker.outbox.write().await.enqueue(email).await.unwrap();
+        }
+
+        let worker = EmailOutboxWorker::new(worker.outbox, SlowEmailClient);
+
+        // One at a time, the batch would take 4 seconds
+        let started = std::time::Instant::now();
+        assert_eq!(
+            worker.process_due(Utc::now()).await.unwrap(),
+            BATCH_SIZE as usize
+        );
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,413 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Result};
+use tracing::Instrument;
+
+use crate::{
+    domain::{Email, EmailClient, EmailContent, EmailSuppressed},
+    utils::metrics::record_email_send,
+};
+
+// Tries each provider in order until one delivers the email. Providers that keep failing
+// are skipped for a while, so an outage doesn't slow down every send.
+pub struct FailoverEmailClient {
+    providers: Vec<Provider>,
+}
+
+struct Provider {
+    name: &'static str,
+    client: Box<dyn EmailClient + Send + Sync>,
+    breaker: Mutex<CircuitBreaker>,
+}
+
+impl FailoverEmailClient {
+    pub fn new(
+        providers: Vec<(&'static str, Box<dyn EmailClient + Send + Sync>)>,
+        settings: CircuitBreakerSettings,
+    ) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|(name, client)| Provider {
+                name,
+                client,
+                breaker: Mutex::new(CircuitBreaker::new(settings)),
+            })
+            .collect();
+
+        Self { providers }
+    }
+}
+
+#[async_trait]
+impl EmailClient for FailoverEmailClient {
+    #[tracing::instrument(
+        name = "Sending email with failover",
+        skip_all,
+        fields(provider = tracing::field::Empty)
+    )]
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()> {
+        let mut last_error = None;
+
+        for provider in &self.providers {
+            if !provider.breaker.lock().unwrap().allows(Instant::now()) {
+                tracing::debug!("Skipping email provider {}, circuit is open", provider.name);
+                continue;
+            }
+
+            let sent = provider
+                .client
+                .send_email(recipient, content)
+                .instrument(tracing::info_span!(
+                    "Email provider",
+                    provider = provider.name
+                ))
+                .await;
+
+            let mut breaker = provider.breaker.lock().unwrap();
+
+            match sent {
+                Ok(()) => {
+                    breaker.record_success();
+                    record_email_send(provider.name, "sent");
+                    tracing::Span::current().record("provider", provider.name);
+
+                    return Ok(());
+                }
+                // Another provider mustn't send to an address that hard-bounced either
+                Err(e) if e.is::<EmailSuppressed>() => {
+                    breaker.release_trial(Instant::now());
+                    record_email_send(provider.name, "suppressed");
+
+                    return Err(e);
+                }
+                Err(e) => {
+                    breaker.record_failure(Instant::now());
+                    record_email_send(provider.name, "failed");
+                    tracing::warn!("Email provider {} failed: {:?}", provider.name, e);
+
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(match last_error {
+            Some(e) => e.wrap_err("All email providers failed"),
+            None => eyre!("No email provider available"),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CircuitBreakerSettings {
+    // Consecutive failures after which the provider is skipped
+    pub failure_threshold: u32,
+    // How long to skip the provider before trying it again
+    pub open_duration: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    // The open period is over and one trial send is in flight. A trial that hasn't finished
+    // by `until`, e.g. because the request sending it was dropped, is given up on so the
+    // provider isn't skipped forever.
+    HalfOpen { until: Instant },
+}
+
+#[derive(Debug)]
+struct CircuitBreaker {
+    settings: CircuitBreakerSettings,
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    fn new(settings: CircuitBreakerSettings) -> Self {
+        Self {
+            settings,
+            state: CircuitState::Closed { failures: 0 },
+        }
+    }
+
+    fn allows(&mut self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed { .. } => true,
+            CircuitState::Open { until } | CircuitState::HalfOpen { until } if now >= until => {
+                self.state = CircuitState::HalfOpen {
+                    until: now + self.settings.open_duration,
+                };
+                true
+            }
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => false,
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.state = CircuitState::Closed { failures: 0 };
+    }
+
+    // The trial send ended without telling whether the provider works, so let the next one try
+    fn release_trial(&mut self, now: Instant) {
+        if let CircuitState::HalfOpen { .. } = self.state {
+            self.state = CircuitState::Open { until: now };
+        }
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        let failures = match self.state {
+            CircuitState::Closed { failures } => failures + 1,
+            // A failed trial opens the circuit again right away
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => {
+                self.settings.failure_threshold
+            }
+        };
+
+        self.state = if failures >= self.settings.failure_threshold {
+            CircuitState::Open {
+                until: now + self.settings.open_duration,
+            }
+        } else {
+            CircuitState::Closed { failures }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    const SETTINGS: CircuitBreakerSettings = CircuitBreakerSettings {
+        failure_threshold: 2,
+        open_duration: Duration::from_millis(100),
+    };
+
+    // Succeeds or fails on demand, counting the sends it's asked for
+    #[derive(Clone, Default)]
+    struct TestEmailClient {
+        failing: Arc<AtomicBool>,
+        sends: Arc<AtomicU32>,
+    }
+
+    impl TestEmailClient {
+        fn failing() -> Self {
+            let client = Self::default();
+            client.set_failing(true);
+            client
+        }
+
+        fn set_failing(&self, failing: bool) {
+            self.failing.store(failing, Ordering::SeqCst);
+        }
+
+        fn sends(&self) -> u32 {
+            self.sends.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl EmailClient for TestEmailClient {
+        async fn send_email(&self, _: &Email, _: &EmailContent) -> Result<()> {
+            self.sends.fetch_add(1, Ordering::SeqCst);
+
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(eyre!("Provider unavailable"));
+            }
+
+            Ok(())
+        }
+    }
+
+    fn failover_client(
+        primary: &TestEmailClient,
+        secondary: &TestEmailClient,
+    ) -> FailoverEmailClient {
+        FailoverEmailClient::new(
+            vec![
+                ("primary", Box::new(primary.clone())),
+                ("secondary", Box::new(secondary.clone())),
+            ],
+            SETTINGS,
+        )
+    }
+
+    async fn send(client: &FailoverEmailClient) -> Result<()> {
+        let recipient = Email::parse("test@example.com".to_owned().into()).unwrap();
+
+        let content = EmailContent {
+            subject: "Subject".to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        };
+
+        client.send_email(&recipient, &content).await
+    }
+
+    #[tokio::test]
+    async fn should_send_through_first_healthy_provider() {
+        let primary = TestEmailClient::default();
+        let secondary = TestEmailClient::default();
+        let client = failover_client(&primary, &secondary);
+
+        send(&client).await.expect("should send email");
+
+        assert_eq!(primary.sends(), 1);
+        assert_eq!(secondary.sends(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_to_next_provider() {
+        let primary = TestEmailClient::failing();
+        let secondary = TestEmailClient::default();
+        let client = failover_client(&primary, &secondary);
+
+        send(&client).await.expect("should send email");
+
+        assert_eq!(primary.sends(), 1);
+        assert_eq!(secondary.sends(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_skip_provider_while_circuit_is_open() {
+        let primary = TestEmailClient::failing();
+        let secondary = TestEmailClient::default();
+        let client = failover_client(&primary, &secondary);
+
+        for _ in 0..SETTINGS.failure_threshold + 2 {
+            send(&client).await.expect("should send email");
+        }
+
+        assert_eq!(primary.sends(), SETTINGS.failure_threshold);
+        assert_eq!(secondary.sends(), SETTINGS.failure_threshold + 2);
+    }
+
+    #[tokio::test]
+    async fn should_try_provider_again_once_circuit_half_opens() {
+        let primary = TestEmailClient::failing();
+        let secondary = TestEmailClient::default();
+        let client = failover_client(&primary, &secondary);
+
+        for _ in 0..SETTINGS.failure_threshold {
+            send(&client).await.expect("should send email");
+        }
+
+        primary.set_failing(false);
+        tokio::time::sleep(SETTINGS.open_duration).await;
+
+        send(&client).await.expect("should send email");
+        send(&client).await.expect("should send email");
+
+        assert_eq!(primary.sends(), SETTINGS.failure_threshold + 2);
+        assert_eq!(secondary.sends(), SETTINGS.failure_threshold);
+    }
+
+    #[tokio::test]
+    async fn should_fail_if_every_provider_fails() {
+        let primary = TestEmailClient::failing();
+        let secondary = TestEmailClient::failing();
+        let client = failover_client(&primary, &secondary);
+
+        for _ in 0..SETTINGS.failure_threshold {
+            assert!(send(&client).await.is_err());
+        }
+
+        // With every circuit open, nothing is tried
+        assert!(send(&client).await.is_err());
+
+        assert_eq!(primary.sends(), SETTINGS.failure_threshold);
+        assert_eq!(secondary.sends(), SETTINGS.failure_threshold);
+    }
+
+    #[tokio::test]
+    async fn should_not_fail_over_for_suppressed_recipient() {
+        struct SuppressingEmailClient;
+
+        #[async_trait]
+        impl EmailClient for SuppressingEmailClient {
+            async fn send_email(&self, _: &Email, _: &EmailContent) -> Result<()> {
+                Err(EmailSuppressed.into())
+            }
+        }
+
+        let secondary = TestEmailClient::default();
+        let client = FailoverEmailClient::new(
+            vec![
+                ("primary", Box::new(SuppressingEmailClient)),
+                ("secondary", Box::new(secondary.clone())),
+            ],
+            SETTINGS,
+        );
+
+        for _ in 0..SETTINGS.failure_threshold {
+            let e = send(&client).await.unwrap_err();
+            assert!(e.is::<EmailSuppressed>());
+        }
+
+        // Refusing a recipient isn't a provider failure
+        assert_eq!(
+            client.providers[0].breaker.lock().unwrap().state,
+            CircuitState::Closed { failures: 0 }
+        );
+        assert_eq!(secondary.sends(), 0);
+    }
+
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let mut breaker = CircuitBreaker::new(SETTINGS);
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        assert_eq!(breaker.state, CircuitState::Closed { failures: 1 });
+
+        // A success resets the count
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert!(breaker.allows(now));
+
+        breaker.record_failure(now);
+        let until = now + SETTINGS.open_duration;
+        assert_eq!(breaker.state, CircuitState::Open { until });
+        assert!(!breaker.allows(now));
+
+        // Only one trial is let through once the open period is over
+        assert!(breaker.allows(until));
+        assert!(!breaker.allows(until));
+
+        // A failed trial opens the circuit again
+        breaker.record_failure(until);
+        assert!(!breaker.allows(until));
+
+        let later = until + SETTINGS.open_duration;
+        assert!(breaker.allows(later));
+        breaker.record_success();
+        assert_eq!(breaker.state, CircuitState::Closed { failures: 0 });
+    }
+
+    #[test]
+    fn test_circuit_breaker_gives_up_on_lost_trial() {
+        let mut breaker = CircuitBreaker::new(SETTINGS);
+        let now = Instant::now();
+
+        for _ in 0..SETTINGS.failure_threshold {
+            breaker.record_failure(now);
+        }
+
+        // The trial never reports back, as if its request was dropped mid-send
+        let until = now + SETTINGS.open_duration;
+        assert!(breaker.allows(until));
+        assert!(!breaker.allows(until));
+
+        // Another trial is let through once it's presumed lost
+        let later = until + SETTINGS.open_duration;
+        assert!(breaker.allows(later));
+        assert!(!breaker.allows(later));
+    }
+}
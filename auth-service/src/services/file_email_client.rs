@@ -0,0 +1,165 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::{Email, EmailClient, EmailContent};
+
+// Development email client that saves each email as a JSON file instead of sending it,
+// so codes can be read from `/dev/mailbox`
+pub struct FileEmailClient {
+    dir: PathBuf,
+}
+
+impl FileEmailClient {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    // Captured emails, newest first
+    pub async fn list_emails(&self) -> Result<Vec<CapturedEmail>> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            // Nothing has been sent yet
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut emails = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|ext| ext == "json") {
+                let json = tokio::fs::read(entry.path()).await?;
+                emails.push(serde_json::from_slice::<CapturedEmail>(&json)?);
+            }
+        }
+
+        emails.sort_by_key(|email| std::cmp::Reverse(email.sent_at));
+
+        Ok(emails)
+    }
+
+    pub async fn get_email(&self, id: Uuid) -> Result<Option<CapturedEmail>> {
+        match tokio::fs::read(self.path(id)).await {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+#[async_trait]
+impl EmailClient for FileEmailClient {
+    #[tracing::instrument(name = "Saving email to file", skip_all)]
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()> {
+        let email = CapturedEmail {
+            id: Uuid::new_v4(),
+            sent_at: Utc::now(),
+            recipient: recipient.as_ref().expose_secret().to_owned(),
+            subject: content.subject.clone(),
+            html_body: content.html_body.clone(),
+            text_body: content.text_body.clone(),
+        };
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.path(email.id), serde_json::to_vec_pretty(&email)?).await?;
+
+        tracing::info!("Saved email to {}", self.path(email.id).display());
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CapturedEmail {
+    pub id: Uuid,
+    pub sent_at: DateTime<Utc>,
+    pub recipient: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> FileEmailClient {
+        FileEmailClient::new(std::env::temp_dir().join(format!("mailbox-{}", Uuid::new_v4())))
+    }
+
+    fn content(subject: &str) -> EmailContent {
+        EmailContent {
+            subject: subject.to_owned(),
+            html_body: "<p>Your code is 123456</p>".to_owned(),
+            text_body: "Your code is 123456".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_email_saves_email() {
+        let client = client();
+        let recipient = Email::parse("test@example.com".to_owned().into()).unwrap();
+
+        client
+            .send_email(&recipient, &content("Your verification code"))
+            .await
+            .expect("should save email");
+
+        let emails = client.list_emails().await.unwrap();
+        assert_eq!(emails.len(), 1);
+
+        let email = &emails[0];
+        assert_eq!(email.recipient, "test@example.com");
+        assert_eq!(email.subject, "Your verification code");
+        assert_eq!(email.text_body, "Your code is 123456");
+
+        assert_eq!(
+            client.get_email(email.id).await.unwrap().as_ref(),
+            Some(email)
+        );
+
+        tokio::fs::remove_dir_all(&client.dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_emails_newest_first() {
+        let client = client();
+        let recipient = Email::parse("test@example.com".to_owned().into()).unwrap();
+
+        for subject in ["First", "Second"] {
+            client
+                .send_email(&recipient, &content(subject))
+                .await
+                .unwrap();
+        }
+
+        let subjects: Vec<_> = client
+            .list_emails()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|email| email.subject)
+            .collect();
+
+        assert_eq!(subjects, ["Second", "First"]);
+
+        tokio::fs::remove_dir_all(&client.dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_empty_mailbox() {
+        let client = client();
+
+        assert!(client.list_emails().await.unwrap().is_empty());
+        assert_eq!(client.get_email(Uuid::new_v4()).await.unwrap(), None);
+    }
+}
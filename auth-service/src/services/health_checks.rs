@@ -0,0 +1,307 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Result};
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::{net::TcpStream, sync::RwLock, task::JoinSet};
+
+use crate::{
+    domain::{HealthCheck, HealthCheckReport, HealthStatus},
+    utils::settings::{EmailProvider, Settings},
+};
+
+// The dependencies checked by `/health/ready`. Checks run concurrently, each within the timeout.
+#[derive(Clone)]
+pub struct HealthChecks {
+    checks: Vec<Arc<dyn HealthCheck + Send + Sync>>,
+    timeout: Duration,
+}
+
+impl HealthChecks {
+    pub fn new(checks: Vec<Arc<dyn HealthCheck + Send + Sync>>, timeout: Duration) -> Self {
+        Self { checks, timeout }
+    }
+
+    // Postgres and Redis, plus the configured email providers when enabled
+    pub fn from_settings(
+        settings: &Settings,
+        pg_pool: PgPool,
+        redis_conn: Arc<RwLock<redis::Connection>>,
+    ) -> Self {
+        let mut checks: Vec<Arc<dyn HealthCheck + Send + Sync>> = vec![
+            Arc::new(PostgresHealthCheck::new(pg_pool)),
+            Arc::new(RedisHealthCheck::new(redis_conn)),
+        ];
+
+        if settings.health.check_email_providers {
+            for provider in &settings.email.providers {
+                match provider {
+                    EmailProvider::Postmark => {
+                        checks.push(Arc::new(EmailProviderHealthCheck::http(
+                            provider.as_str(),
+                            settings.email.postmark.base_url.clone(),
+                            Client::new(),
+                        )))
+                    }
+                    EmailProvider::Smtp => {
+                        let smtp = settings.email.smtp();
+                        checks.push(Arc::new(EmailProviderHealthCheck::tcp(
+                            provider.as_str(),
+                            &smtp.host,
+                            smtp.port,
+                        )))
+                    }
+                    // Nothing to reach
+                    EmailProvider::File => {}
+                }
+            }
+        }
+
+        Self::new(checks, settings.health.timeout())
+    }
+
+    #[tracing::instrument(name = "Running health checks", skip_all)]
+    pub async fn run(&self) -> Vec<HealthCheckReport> {
+        let mut running = JoinSet::new();
+
+        for (index, check) in self.checks.iter().cloned().enumerate() {
+            let timeout = self.timeout;
+
+            running.spawn(async move {
+                let started = Instant::now();
+
+                let result = match tokio::time::timeout(timeout, check.check()).await {
+                    Ok(result) => result,
+                    Err(_) => Err(eyre!("timed out after {timeout:?}")),
+                };
+
+                let report = HealthCheckReport {
+                    name: check.name().to_owned(),
+                    status: match result {
+                        Ok(()) => HealthStatus::Up,
+                        Err(_) => HealthStatus::Down,
+                    },
+                    critical: check.is_critical(),
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    error: result.err().map(|e| e.to_string()),
+                };
+
+                (index, report)
+            });
+        }
+
+        let mut reports: Vec<_> = running.join_all().await;
+
+        // Report in the order the checks were given, not the order they finished
+        reports.sort_by_key(|(index, _)| *index);
+        reports.into_iter().map(|(_, report)| report).collect()
+    }
+}
+
+pub struct PostgresHealthCheck {
+    pool: PgPool,
+}
+
+impl PostgresHealthCheck {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for PostgresHealthCheck {
+    fn name(&self) -> &str {
+        "postgres"
+    }
+
+    async fn check(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+pub struct RedisHealthCheck {
+    conn: Arc<RwLock<redis::Connection>>,
+}
+
+impl RedisHealthCheck {
+    pub fn new(conn: Arc<RwLock<redis::Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for RedisHealthCheck {
+    fn name(&self) -> &str {
+        "redis"
+    }
+
+    async fn check(&self) -> Result<()> {
+        let mut conn = self.conn.write().await;
+        redis::cmd("PING").query::<String>(&mut *conn)?;
+        Ok(())
+    }
+}
+
+// Whether an email provider can be reached. Emails are delivered through the outbox, which
+// retries later, so an unreachable provider doesn't make the service unready.
+pub struct EmailProviderHealthCheck {
+    name: String,
+    target: EmailProviderTarget,
+}
+
+enum EmailProviderTarget {
+    // Any HTTP response means the API is reachable
+    Http { url: String, http_client: Client },
+    Tcp { address: String },
+}
+
+impl EmailProviderHealthCheck {
+    pub fn http(provider: &str, url: String, http_client: Client) -> Self {
+        Self {
+            name: format!("email:{provider}"),
+            target: EmailProviderTarget::Http { url, http_client },
+        }
+    }
+
+    pub fn tcp(provider: &str, host: &str, port: u16) -> Self {
+        Self {
+            name: format!("email:{provider}"),
+            target: EmailProviderTarget::Tcp {
+                address: format!("{host}:{port}"),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for EmailProviderHealthCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_critical(&self) -> bool {
+        false
+    }
+
+    async fn check(&self) -> Result<()> {
+        match &self.target {
+            EmailProviderTarget::Http { url, http_client } => {
+                http_client.head(url).send().await?;
+            }
+            EmailProviderTarget::Tcp { address } => {
+                TcpStream::connect(address).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_redis_connection, utils::settings::RedisSettings};
+
+    struct StubHealthCheck {
+        name: &'static str,
+        critical: bool,
+        delay: Duration,
+        result: fn() -> Result<()>,
+    }
+
+    #[async_trait]
+    impl HealthCheck for StubHealthCheck {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn is_critical(&self) -> bool {
+            self.critical
+        }
+
+        async fn check(&self) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            (self.result)()
+        }
+    }
+
+    fn stub(
+        name: &'static str,
+        delay_ms: u64,
+        result: fn() -> Result<()>,
+    ) -> Arc<dyn HealthCheck + Send + Sync> {
+        Arc::new(StubHealthCheck {
+            name,
+            critical: name != "optional",
+            delay: Duration::from_millis(delay_ms),
+            result,
+        })
+    }
+
+    #[tokio::test]
+    async fn should_report_every_check_in_order() {
+        let checks = HealthChecks::new(
+            vec![
+                stub("slow", 50, || Ok(())),
+                stub("failing", 0, || Err(eyre!("connection refused"))),
+                stub("optional", 0, || Ok(())),
+            ],
+            Duration::from_secs(1),
+        );
+
+        let reports = checks.run().await;
+
+        let names: Vec<_> = reports.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["slow", "failing", "optional"]);
+
+        assert_eq!(reports[0].status, HealthStatus::Up);
+        assert!(reports[0].latency_ms >= 50);
+        assert_eq!(reports[1].status, HealthStatus::Down);
+        assert_eq!(reports[1].error.as_deref(), Some("connection refused"));
+        assert!(reports[1].critical);
+        assert!(!reports[2].critical);
+    }
+
+    #[tokio::test]
+    async fn should_fail_checks_that_time_out() {
+        let checks = HealthChecks::new(
+            vec![stub("hanging", 1_000, || Ok(()))],
+            Duration::from_millis(20),
+        );
+
+        let started = Instant::now();
+        let reports = checks.run().await;
+
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(reports[0].status, HealthStatus::Down);
+        assert!(reports[0].error.as_deref().unwrap().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn redis_check_should_fail_when_redis_hangs() {
+        // Accepts connections but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let settings = RedisSettings {
+            host_name: listener.local_addr().unwrap().to_string(),
+            timeout_milliseconds: 100,
+        };
+
+        let started = Instant::now();
+        let result = tokio::task::spawn_blocking(move || get_redis_connection(&settings))
+            .await
+            .unwrap();
+
+        // Either connecting or the PING gives up
+        if let Ok(conn) = result {
+            let check = RedisHealthCheck::new(Arc::new(RwLock::new(conn)));
+            assert!(check.check().await.is_err());
+        }
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
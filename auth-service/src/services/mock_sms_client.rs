@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+pub struct MockSmsClient;
+
+#[async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<()> {
+        tracing::debug!(
+            "Sending SMS to {} with content: {}",
+            recipient.as_ref().expose_secret(),
+            content
+        );
+
+        Ok(())
+    }
+}
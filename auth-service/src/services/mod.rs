@@ -1,3 +1,14 @@
 pub mod data_stores;
+pub mod email_outbox_worker;
+pub mod failover_email_client;
+pub mod file_email_client;
+pub mod health_checks;
 pub mod mock_email_client;
+pub mod mock_sms_client;
+pub mod outbox_email_client;
+pub mod outbox_worker;
 pub mod postmark_email_client;
+pub mod smtp_email_client;
+pub mod twilio_sms_client;
+pub mod webhook_audit_sink;
+pub mod webhook_worker;
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::{EmailEventStoreType, EmailOutboxStoreType},
+    domain::{
+        Email, EmailClient, EmailContent, EmailEventStore, EmailOutboxStore, EmailSuppressed,
+        OutboxEmail,
+    },
+    utils::tracing::current_traceparent,
+};
+
+// Queues emails in the outbox instead of sending them, so requests don't wait on the
+// email provider. `EmailOutboxWorker` delivers them in the background.
+pub struct OutboxEmailClient<EmailOutboxStoreImpl, EmailEventStoreImpl> {
+    outbox: EmailOutboxStoreType<EmailOutboxStoreImpl>,
+    email_event_store: EmailEventStoreType<EmailEventStoreImpl>,
+}
+
+impl<EmailOutboxStoreImpl, EmailEventStoreImpl>
+    OutboxEmailClient<EmailOutboxStoreImpl, EmailEventStoreImpl>
+{
+    pub fn new(
+        outbox: EmailOutboxStoreType<EmailOutboxStoreImpl>,
+        email_event_store: EmailEventStoreType<EmailEventStoreImpl>,
+    ) -> Self {
+        Self {
+            outbox,
+            email_event_store,
+        }
+    }
+}
+
+#[async_trait]
+impl<EmailOutboxStoreImpl, EmailEventStoreImpl> EmailClient
+    for OutboxEmailClient<EmailOutboxStoreImpl, EmailEventStoreImpl>
+where
+    EmailOutboxStoreImpl: EmailOutboxStore + Send + Sync,
+    EmailEventStoreImpl: EmailEventStore + Send + Sync,
+{
+    #[tracing::instrument(name = "Queuing email", skip_all)]
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()> {
+        // Refuse up front, so the caller learns the email will never arrive
+        if self
+            .email_event_store
+            .read()
+            .await
+            .is_suppressed(recipient)
+            .await?
+        {
+            return Err(EmailSuppressed.into());
+        }
+
+        let email = OutboxEmail {
+            traceparent: current_traceparent(),
+            ..OutboxEmail::new(recipient.clone(), content.clone())
+        };
+        self.outbox.write().await.enqueue(email).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use std::future::Future;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use futures::future::join_all;
+use tokio::time::Instant;
+use tracing::{Instrument, Span};
+
+use crate::utils::constants::outbox::{
+    BASE_RETRY_DELAY_SECONDS, BATCH_SIZE, LEASE_SECONDS, MAX_RETRY_DELAY_SECONDS, POLL_INTERVAL,
+    PURGE_INTERVAL,
+};
+
+// A worker delivering what's queued in a table, such as emails or webhooks. `run` polls for due
+// items, claims a batch of them under a lease so other workers skip them, and delivers the batch
+// concurrently. Failures are retried after `retry_delay` until they're dead-lettered after
+// `MAX_ATTEMPTS`.
+#[async_trait]
+pub trait OutboxWorker: Send + Sync {
+    type Item: Send + Sync;
+
+    // What's being delivered, for the logs, e.g. "email outbox"
+    const NAME: &'static str;
+
+    // Claim up to `BATCH_SIZE` due items, hidden from other workers until `lease_until`
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<Self::Item>>;
+
+    // Attempt delivery of one item, recording the outcome and when to retry
+    async fn deliver(&self, item: &Self::Item, now: DateTime<Utc>) -> Result<()>;
+
+    // The span delivering `item` is traced in
+    fn delivery_span(&self, item: &Self::Item) -> Span;
+
+    // Clean up finished items, run every `PURGE_INTERVAL`
+    async fn purge(&self, _now: DateTime<Utc>) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Poll for due items until `shutdown` resolves. A batch being delivered is never cut short, and
+// what's due by then is delivered before returning. Whatever fails stays queued for the next start.
+pub async fn run<W, F>(worker: &W, shutdown: F)
+where
+    W: OutboxWorker,
+    F: Future<Output = ()>,
+{
+    tokio::pin!(shutdown);
+    let mut next_purge = Instant::now();
+
+    loop {
+        if Instant::now() >= next_purge {
+            if let Err(e) = worker.purge(Utc::now()).await {
+                tracing::error!("Failed to purge {}: {:?}", W::NAME, e);
+            }
+
+            next_purge = Instant::now() + PURGE_INTERVAL;
+        }
+
+        if process_batch(worker).await {
+            continue;
+        }
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+
+    tracing::info!("Flushing {}", W::NAME);
+    while process_batch(worker).await {}
+}
+
+// Process one batch, returning whether more may be waiting
+async fn process_batch<W: OutboxWorker>(worker: &W) -> bool {
+    match process_due(worker, Utc::now()).await {
+        // A full batch means more may be waiting
+        Ok(processed) => processed == BATCH_SIZE as usize,
+        Err(e) => {
+            tracing::error!("Failed to process {}: {:?}", W::NAME, e);
+            false
+        }
+    }
+}
+
+// Attempt delivery of one batch of due items, returning how many were attempted
+pub async fn process_due<W: OutboxWorker>(worker: &W, now: DateTime<Utc>) -> Result<usize> {
+    let lease_until = now + Duration::seconds(LEASE_SECONDS);
+    let items = worker.claim_due(now, lease_until).await?;
+
+    // Delivered concurrently, so the batch takes as long as its slowest item rather than all
+    // of them in turn, and is done well before the lease runs out
+    let deliveries = items.iter().map(|item| {
+        let span = worker.delivery_span(item);
+        worker.deliver(item, now).instrument(span)
+    });
+
+    join_all(deliveries)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(items.len())
+}
+
+// Double the delay after each failed attempt, up to a cap
+pub fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(31);
+
+    Duration::seconds(
+        BASE_RETRY_DELAY_SECONDS
+            .saturating_mul(1 << exponent)
+            .min(MAX_RETRY_DELAY_SECONDS),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_grows_exponentially_up_to_a_cap() {
+        assert_eq!(retry_delay(1), Duration::seconds(BASE_RETRY_DELAY_SECONDS));
+        assert_eq!(
+            retry_delay(2),
+            Duration::seconds(BASE_RETRY_DELAY_SECONDS * 2)
+        );
+        assert_eq!(
+            retry_delay(3),
+            Duration::seconds(BASE_RETRY_DELAY_SECONDS * 4)
+        );
+        assert_eq!(retry_delay(100), Duration::seconds(MAX_RETRY_DELAY_SECONDS));
+    }
+}
@@ -3,50 +3,73 @@ use color_eyre::eyre::Result;
 use reqwest::{Client, Url};
 use secrecy::{ExposeSecret, SecretString};
 
-use crate::domain::{Email, EmailClient};
+use crate::{
+    app_state::EmailEventStoreType,
+    domain::{Email, EmailClient, EmailContent, EmailEventStore, EmailSuppressed},
+    utils::tracing::trace_context_headers,
+};
 
-pub struct PostmarkEmailClient {
+pub struct PostmarkEmailClient<EmailEventStoreImpl> {
     http_client: Client,
     base_url: String,
     sender: Email,
     authorization_token: SecretString,
+    // Bounces reported through the Postmark webhook
+    email_event_store: EmailEventStoreType<EmailEventStoreImpl>,
 }
 
-impl PostmarkEmailClient {
+impl<EmailEventStoreImpl> PostmarkEmailClient<EmailEventStoreImpl> {
     pub fn new(
         base_url: String,
         sender: Email,
         authorization_token: SecretString,
         http_client: Client,
+        email_event_store: EmailEventStoreType<EmailEventStoreImpl>,
     ) -> Self {
         Self {
             http_client,
             base_url,
             sender,
             authorization_token,
+            email_event_store,
         }
     }
 }
 
 #[async_trait]
-impl EmailClient for PostmarkEmailClient {
+impl<EmailEventStoreImpl> EmailClient for PostmarkEmailClient<EmailEventStoreImpl>
+where
+    EmailEventStoreImpl: EmailEventStore + Send + Sync,
+{
     #[tracing::instrument(name = "Sending email", skip_all)]
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()> {
+        // Sending to hard-bounced addresses hurts the sender reputation
+        if self
+            .email_event_store
+            .read()
+            .await
+            .is_suppressed(recipient)
+            .await?
+        {
+            return Err(EmailSuppressed.into());
+        }
+
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
 
         let request_body = SendEmailRequest {
             from: self.sender.as_ref().expose_secret(),
             to: recipient.as_ref().expose_secret(),
-            subject,
-            html_body: content,
-            text_body: content,
+            subject: &content.subject,
+            html_body: &content.html_body,
+            text_body: &content.text_body,
             message_stream: MESSAGE_STREAM,
         };
 
         let request = self
             .http_client
             .post(url)
+            .headers(trace_context_headers())
             .header(
                 POSTMARK_AUTH_HEADER,
                 self.authorization_token.expose_secret(),
@@ -76,25 +99,31 @@ struct SendEmailRequest<'a> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+    use tokio::sync::RwLock;
+
+    use crate::domain::{EmailEvent, EmailEventKind};
+    use crate::services::data_stores::HashmapEmailEventStore;
     use crate::utils::constants::test;
 
     use super::*;
     use fake::faker::internet::en::SafeEmail;
     use fake::faker::lorem::en::{Paragraph, Sentence};
     use fake::{Fake, Faker};
-    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::matchers::{any, body_partial_json, header, header_exists, method, path};
     use wiremock::{Mock, MockServer, Request, ResponseTemplate};
 
     use super::PostmarkEmailClient;
 
-    // Helper function to generate a test subject
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
-
     // Helper function to generate test content
-    fn content() -> String {
-        Paragraph(1..10).fake()
+    fn content() -> EmailContent {
+        EmailContent {
+            subject: Sentence(1..2).fake(),
+            html_body: Paragraph(1..10).fake(),
+            text_body: Paragraph(1..10).fake(),
+        }
     }
 
     // Helper function to generate a test email
@@ -103,7 +132,7 @@ mod tests {
     }
 
     // Helper function to create a test email client
-    fn email_client(base_url: String) -> PostmarkEmailClient {
+    fn email_client(base_url: String) -> PostmarkEmailClient<HashmapEmailEventStore> {
         let http_client = Client::builder()
             .timeout(test::email_client::TIMEOUT)
             .build()
@@ -113,6 +142,7 @@ mod tests {
             email(),
             Faker.fake::<String>().into(),
             http_client,
+            Arc::new(RwLock::new(HashmapEmailEventStore::default())),
         )
     }
 
@@ -153,10 +183,31 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+        let outcome = email_client.send_email(&email(), &content()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    // Test to ensure the HTML and plain-text versions are sent as separate bodies
+    #[tokio::test]
+    async fn send_email_sends_html_and_text_bodies() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let content = content();
+
+        Mock::given(path("/email"))
+            .and(body_partial_json(serde_json::json!({
+                "Subject": content.subject,
+                "HtmlBody": content.html_body,
+                "TextBody": content.text_body,
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
             .await;
 
+        let outcome = email_client.send_email(&email(), &content).await;
+
         assert!(outcome.is_ok());
     }
 
@@ -174,9 +225,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &content()).await;
 
         assert!(outcome.is_err());
     }
@@ -196,10 +245,39 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &content()).await;
 
         assert!(outcome.is_err());
     }
+
+    // Test to ensure hard-bounced addresses aren't sent to
+    #[tokio::test]
+    async fn send_email_refuses_suppressed_recipient() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipient = email();
+
+        email_client
+            .email_event_store
+            .write()
+            .await
+            .add_event(EmailEvent::new(
+                recipient.clone(),
+                EmailEventKind::HardBounce,
+                None,
+                Utc::now(),
+            ))
+            .await
+            .unwrap();
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&recipient, &content()).await;
+
+        assert!(outcome.unwrap_err().is::<EmailSuppressed>());
+    }
 }
@@ -0,0 +1,422 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Report, Result};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::{
+        authentication::Credentials,
+        client::{Tls, TlsParameters},
+        PoolConfig,
+    },
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::domain::{Email, EmailClient, EmailContent};
+
+// Sends email through any SMTP relay, as an alternative to a vendor's HTTP API
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Email,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    pub fn new(settings: &SmtpSettings, sender: Email) -> Result<Self> {
+        let tls = match settings.tls {
+            SmtpTls::StartTls => Tls::Required(TlsParameters::new(settings.host.clone())?),
+            SmtpTls::Implicit => Tls::Wrapper(TlsParameters::new(settings.host.clone())?),
+            SmtpTls::None => Tls::None,
+        };
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            .port(settings.port)
+            .tls(tls)
+            .timeout(Some(settings.timeout))
+            .pool_config(PoolConfig::new().max_size(settings.max_connections));
+
+        if let Some(credentials) = &settings.credentials {
+            builder = builder.credentials(Credentials::new(
+                credentials.username.clone(),
+                credentials.password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+            timeout: settings.timeout,
+        })
+    }
+}
+
+#[async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, content: &EmailContent) -> Result<()> {
+        let from: Mailbox = self.sender.as_ref().expose_secret().parse()?;
+        let to: Mailbox = recipient.as_ref().expose_secret().parse()?;
+
+        let message = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(&content.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                content.text_body.clone(),
+                content.html_body.clone(),
+            ))?;
+
+        // The transport only applies its timeout to connecting, so bound the whole exchange too
+        tokio::time::timeout(self.timeout, self.transport.send(message))
+            .await
+            .map_err(|_| eyre!("Timed out sending email over SMTP"))??;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub credentials: Option<SmtpCredentials>,
+    // Applies to sending each email, including connecting
+    pub timeout: Duration,
+    // Connections are kept open and reused between emails, up to this many at once
+    pub max_connections: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpCredentials {
+    pub username: String,
+    pub password: SecretString,
+}
+
+// How the connection to the SMTP server is secured
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub enum SmtpTls {
+    // Connect in plain text and upgrade with STARTTLS, failing if the server doesn't offer it
+    #[default]
+    StartTls,
+    // Connect over TLS from the start (SMTPS)
+    Implicit,
+    // No encryption, only for trusted local relays
+    None,
+}
+
+impl TryFrom<String> for SmtpTls {
+    type Error = Report;
+
+    fn try_from(tls: String) -> Result<Self> {
+        Self::parse(&tls)
+    }
+}
+
+impl SmtpTls {
+    pub fn parse(tls: &str) -> Result<Self> {
+        match tls {
+            "starttls" => Ok(Self::StartTls),
+            "implicit" => Ok(Self::Implicit),
+            "none" => Ok(Self::None),
+            _ => Err(eyre!("{tls} is not a valid SMTP TLS mode")),
+        }
+    }
+
+    // The port conventionally used with each mode
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Implicit => 465,
+            Self::None => 25,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::Fake;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use crate::utils::constants::test;
+
+    use super::*;
+
+    // Base64 of "\0user\0password", as sent with AUTH PLAIN
+    const PLAIN_CREDENTIALS: &str = "AHVzZXIAcGFzc3dvcmQ=";
+
+    #[derive(Clone, Debug, Default)]
+    struct ReceivedEmail {
+        auth: Option<String>,
+        from: String,
+        to: Vec<String>,
+        data: String,
+    }
+
+    #[derive(Clone, Copy, Default)]
+    enum Behavior {
+        #[default]
+        Accept,
+        RejectRecipients,
+        // Accept connections but never greet the client
+        Hang,
+    }
+
+    // A minimal in-process SMTP server, speaking just enough of the protocol to receive email
+    struct SmtpStandIn {
+        port: u16,
+        connections: Arc<Mutex<usize>>,
+        received: Arc<Mutex<Vec<ReceivedEmail>>>,
+    }
+
+    impl SmtpStandIn {
+        async fn start(behavior: Behavior) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let connections = Arc::new(Mutex::new(0));
+            let received = Arc::new(Mutex::new(Vec::new()));
+
+            let stand_in = Self {
+                port,
+                connections: connections.clone(),
+                received: received.clone(),
+            };
+
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    *connections.lock().unwrap() += 1;
+                    tokio::spawn(handle_connection(stream, behavior, received.clone()));
+                }
+            });
+
+            stand_in
+        }
+
+        fn settings(&self, tls: SmtpTls, credentials: Option<SmtpCredentials>) -> SmtpSettings {
+            SmtpSettings {
+                host: "127.0.0.1".to_owned(),
+                port: self.port,
+                tls,
+                credentials,
+                timeout: test::email_client::TIMEOUT,
+                max_connections: 1,
+            }
+        }
+
+        fn received(&self) -> Vec<ReceivedEmail> {
+            self.received.lock().unwrap().clone()
+        }
+
+        fn connections(&self) -> usize {
+            *self.connections.lock().unwrap()
+        }
+    }
+
+    async fn handle_connection(
+        stream: tokio::net::TcpStream,
+        behavior: Behavior,
+        received: Arc<Mutex<Vec<ReceivedEmail>>>,
+    ) {
+        if let Behavior::Hang = behavior {
+            tokio::time::sleep(Duration::from_secs(180)).await;
+            return;
+        }
+
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut email = ReceivedEmail::default();
+
+        writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let command = line.to_ascii_uppercase();
+
+            let reply: &[u8] = if command.starts_with("EHLO") {
+                b"250-localhost\r\n250-AUTH PLAIN\r\n250 8BITMIME\r\n"
+            } else if let Some(auth) = line.strip_prefix("AUTH PLAIN ") {
+                email.auth = Some(auth.to_owned());
+                b"235 Authentication succeeded\r\n"
+            } else if command.starts_with("MAIL FROM:") {
+                email.from = line["MAIL FROM:".len()..].to_owned();
+                b"250 OK\r\n"
+            } else if command.starts_with("RCPT TO:") {
+                if let Behavior::RejectRecipients = behavior {
+                    b"550 No such user\r\n"
+                } else {
+                    email.to.push(line["RCPT TO:".len()..].to_owned());
+                    b"250 OK\r\n"
+                }
+            } else if command == "DATA" {
+                writer
+                    .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                    .await
+                    .unwrap();
+
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line == "." {
+                        break;
+                    }
+
+                    email.data.push_str(&line);
+                    email.data.push('\n');
+                }
+
+                received.lock().unwrap().push(email.clone());
+                email = ReceivedEmail {
+                    auth: email.auth,
+                    ..Default::default()
+                };
+
+                b"250 OK\r\n"
+            } else if command == "QUIT" {
+                writer.write_all(b"221 Bye\r\n").await.unwrap();
+                return;
+            } else {
+                // RSET, NOOP
+                b"250 OK\r\n"
+            };
+
+            if writer.write_all(reply).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn content() -> EmailContent {
+        EmailContent {
+            subject: Sentence(1..2).fake(),
+            html_body: format!("<p>{}</p>", Paragraph(1..3).fake::<String>()),
+            text_body: Paragraph(1..3).fake(),
+        }
+    }
+
+    fn email() -> Email {
+        Email::parse(SafeEmail().fake::<String>().into()).unwrap()
+    }
+
+    fn credentials() -> SmtpCredentials {
+        SmtpCredentials {
+            username: "user".to_owned(),
+            password: "password".to_owned().into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_html_and_text_alternatives() {
+        let stand_in = SmtpStandIn::start(Behavior::Accept).await;
+        let sender = email();
+        let client =
+            SmtpEmailClient::new(&stand_in.settings(SmtpTls::None, None), sender.clone()).unwrap();
+
+        let recipient = email();
+        let content = content();
+
+        client
+            .send_email(&recipient, &content)
+            .await
+            .expect("should send email");
+
+        let received = stand_in.received();
+        assert_eq!(received.len(), 1);
+
+        let email = &received[0];
+        assert!(email.from.contains(sender.as_ref().expose_secret()));
+        assert!(email.to[0].contains(recipient.as_ref().expose_secret()));
+        assert!(email.data.contains(&content.subject));
+        assert!(email.data.contains("multipart/alternative"));
+        assert!(email.data.contains("text/plain"));
+        assert!(email.data.contains("text/html"));
+        assert_eq!(email.auth, None);
+    }
+
+    #[tokio::test]
+    async fn send_email_authenticates_with_credentials() {
+        let stand_in = SmtpStandIn::start(Behavior::Accept).await;
+        let settings = stand_in.settings(SmtpTls::None, Some(credentials()));
+        let client = SmtpEmailClient::new(&settings, email()).unwrap();
+
+        client
+            .send_email(&email(), &content())
+            .await
+            .expect("should send email");
+
+        assert_eq!(
+            stand_in.received()[0].auth.as_deref(),
+            Some(PLAIN_CREDENTIALS)
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_reuses_pooled_connection() {
+        let stand_in = SmtpStandIn::start(Behavior::Accept).await;
+        let client =
+            SmtpEmailClient::new(&stand_in.settings(SmtpTls::None, None), email()).unwrap();
+
+        for _ in 0..3 {
+            client
+                .send_email(&email(), &content())
+                .await
+                .expect("should send email");
+
+            // Connections go back to the pool in a background task
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(stand_in.received().len(), 3);
+        assert_eq!(stand_in.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_rejects_the_recipient() {
+        let stand_in = SmtpStandIn::start(Behavior::RejectRecipients).await;
+        let client =
+            SmtpEmailClient::new(&stand_in.settings(SmtpTls::None, None), email()).unwrap();
+
+        let outcome = client.send_email(&email(), &content()).await;
+
+        assert!(outcome.is_err());
+        assert!(stand_in.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_starttls_is_not_offered() {
+        let stand_in = SmtpStandIn::start(Behavior::Accept).await;
+        let settings = stand_in.settings(SmtpTls::StartTls, Some(credentials()));
+        let client = SmtpEmailClient::new(&settings, email()).unwrap();
+
+        let outcome = client.send_email(&email(), &content()).await;
+
+        // Neither credentials nor the email go out over an unencrypted connection
+        assert!(outcome.is_err());
+        assert!(stand_in.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let stand_in = SmtpStandIn::start(Behavior::Hang).await;
+        let client =
+            SmtpEmailClient::new(&stand_in.settings(SmtpTls::None, None), email()).unwrap();
+
+        let outcome = client.send_email(&email(), &content()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[test]
+    fn test_parse_smtp_tls() {
+        assert_eq!(SmtpTls::parse("starttls").unwrap(), SmtpTls::StartTls);
+        assert_eq!(SmtpTls::parse("implicit").unwrap(), SmtpTls::Implicit);
+        assert_eq!(SmtpTls::parse("none").unwrap(), SmtpTls::None);
+        assert!(SmtpTls::parse("ssl").is_err());
+    }
+}
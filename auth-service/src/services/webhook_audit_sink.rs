@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+
+use crate::{
+    app_state::WebhookDeliveryStoreType,
+    domain::{
+        AuditEvent, AuditEventFilter, AuditSink, AuditSinkError, WebhookDelivery,
+        WebhookDeliveryStore, WebhookEventKind, WebhookSubscription,
+    },
+    utils::tracing::current_traceparent,
+};
+
+// Records audit events in the wrapped sink, and publishes the ones downstream systems
+// subscribe to by queuing a delivery per subscriber. `WebhookWorker` sends them in the
+// background, so requests don't wait on subscribers.
+pub struct WebhookAuditSink<AuditSinkImpl, WebhookDeliveryStoreImpl> {
+    audit_sink: AuditSinkImpl,
+    deliveries: WebhookDeliveryStoreType<WebhookDeliveryStoreImpl>,
+    subscriptions: Vec<WebhookSubscription>,
+}
+
+impl<AuditSinkImpl, WebhookDeliveryStoreImpl>
+    WebhookAuditSink<AuditSinkImpl, WebhookDeliveryStoreImpl>
+{
+    pub fn new(
+        audit_sink: AuditSinkImpl,
+        deliveries: WebhookDeliveryStoreType<WebhookDeliveryStoreImpl>,
+        subscriptions: Vec<WebhookSubscription>,
+    ) -> Self {
+        Self {
+            audit_sink,
+            deliveries,
+            subscriptions,
+        }
+    }
+}
+
+#[async_trait]
+impl<AuditSinkImpl, WebhookDeliveryStoreImpl> AuditSink
+    for WebhookAuditSink<AuditSinkImpl, WebhookDeliveryStoreImpl>
+where
+    AuditSinkImpl: AuditSink + Send + Sync,
+    WebhookDeliveryStoreImpl: WebhookDeliveryStore + Send + Sync,
+{
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditSinkError> {
+        self.audit_sink.record(event.clone()).await?;
+
+        let Some(kind) = WebhookEventKind::from_audit_event(event.kind) else {
+            return Ok(());
+        };
+
+        let subscribers = self
+            .subscriptions
+            .iter()
+            .filter(|subscription| subscription.is_subscribed(kind));
+
+        for subscription in subscribers {
+            let delivery = WebhookDelivery {
+                traceparent: current_traceparent(),
+                ..WebhookDelivery::new(subscription.url.clone(), kind, &event)
+            };
+
+            self.deliveries
+                .write()
+                .await
+                .enqueue(delivery)
+                .await
+                .map_err(|e| AuditSinkError::UnexpectedError(e.into()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditSinkError> {
+        self.audit_sink.query(filter).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use crate::{
+        domain::{AuditEventKind, Email},
+        services::data_stores::{HashmapWebhookDeliveryStore, InMemoryAuditSink},
+    };
+
+    use super::*;
+
+    fn subscription(url: &str, events: &[WebhookEventKind]) -> WebhookSubscription {
+        WebhookSubscription {
+            url: url.to_owned(),
+            secret: "secret".to_owned().into(),
+            events: events.to_vec(),
+        }
+    }
+
+    async fn subscriber_urls(
+        deliveries: &HashmapWebhookDeliveryStore,
+        event: &AuditEvent,
+    ) -> Vec<String> {
+        let mut urls = deliveries
+            .get_deliveries(event.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|delivery| delivery.url)
+            .collect::<Vec<_>>();
+
+        urls.sort();
+        urls
+    }
+
+    #[tokio::test]
+    async fn should_queue_a_delivery_per_subscriber() {
+        let deliveries = Arc::new(RwLock::new(HashmapWebhookDeliveryStore::default()));
+        let mut sink = WebhookAuditSink::new(
+            InMemoryAuditSink::default(),
+            deliveries.clone(),
+            vec![
+                subscription("https://crm.example.com", &[WebhookEventKind::UserCreated]),
+                subscription(
+                    "https://fraud.example.com",
+                    &[WebhookEventKind::UserCreated, WebhookEventKind::UserLogin],
+                ),
+            ],
+        );
+
+        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
+        let signup = AuditEvent::new(AuditEventKind::Signup, Some(email.clone()));
+        let login = AuditEvent::new(AuditEventKind::LoginSucceeded, Some(email.clone()));
+        let logout = AuditEvent::new(AuditEventKind::Logout, Some(email));
+
+        for event in [signup.clone(), login.clone(), logout.clone()] {
+            sink.record(event).await.unwrap();
+        }
+
+        let deliveries = deliveries.read().await;
+
+        assert_eq!(
+            subscriber_urls(&deliveries, &signup).await,
+            ["https://crm.example.com", "https://fraud.example.com"]
+        );
+        assert_eq!(
+            subscriber_urls(&deliveries, &login).await,
+            ["https://fraud.example.com"]
+        );
+        assert!(subscriber_urls(&deliveries, &logout).await.is_empty());
+
+        // Every event is still recorded
+        let filter = AuditEventFilter {
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(sink.query(&filter).await.unwrap().len(), 3);
+    }
+}
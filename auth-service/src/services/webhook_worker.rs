@@ -0,0 +1,447 @@
+use std::future::Future;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    app_state::WebhookDeliveryStoreType,
+    domain::{WebhookAttempt, WebhookDelivery, WebhookDeliveryStore, WebhookSubscription},
+    services::outbox_worker::{self, retry_delay, OutboxWorker},
+    utils::{
+        constants::{
+            outbox::{BATCH_SIZE, MAX_ATTEMPTS},
+            webhooks::{EVENT_ID_HEADER_NAME, EVENT_TYPE_HEADER_NAME, SIGNATURE_HEADER_NAME},
+        },
+        metrics::record_webhook_delivery,
+        tracing::{trace_context_from, trace_context_headers},
+    },
+};
+
+// POSTs queued webhook deliveries to their subscribers, see `OutboxWorker`. Any 2xx response
+// counts as delivered.
+pub struct WebhookWorker<WebhookDeliveryStoreImpl> {
+    deliveries: WebhookDeliveryStoreType<WebhookDeliveryStoreImpl>,
+    subscriptions: Vec<WebhookSubscription>,
+    http_client: Client,
+}
+
+impl<WebhookDeliveryStoreImpl> WebhookWorker<WebhookDeliveryStoreImpl>
+where
+    WebhookDeliveryStoreImpl: WebhookDeliveryStore + Send + Sync,
+{
+    pub fn new(
+        deliveries: WebhookDeliveryStoreType<WebhookDeliveryStoreImpl>,
+        subscriptions: Vec<WebhookSubscription>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            deliveries,
+            subscriptions,
+            http_client,
+        }
+    }
+
+    // Poll for due deliveries until `shutdown` resolves, then deliver what's due by then
+    pub async fn run<F>(self, shutdown: F)
+    where
+        F: Future<Output = ()>,
+    {
+        outbox_worker::run(&self, shutdown).await
+    }
+
+    // Attempt one batch of due deliveries, returning how many were attempted
+    #[tracing::instrument(name = "Processing webhook deliveries", skip_all)]
+    pub async fn process_due(&self, now: DateTime<Utc>) -> Result<usize> {
+        outbox_worker::process_due(self, now).await
+    }
+
+    // Send the delivery, returning the attempt for the log and whether it was accepted
+    async fn post(
+        &self,
+        subscription: &WebhookSubscription,
+        delivery: &WebhookDelivery,
+    ) -> (WebhookAttempt, bool) {
+        let attempted_at = Utc::now();
+        let signature = sign_webhook(
+            &subscription.secret,
+            attempted_at.timestamp(),
+            &delivery.payload,
+        );
+
+        let response = self
+            .http_client
+            .post(&delivery.url)
+            .headers(trace_context_headers())
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_ID_HEADER_NAME, delivery.event_id.to_string())
+            .header(EVENT_TYPE_HEADER_NAME, delivery.kind.as_str())
+            .header(
+                SIGNATURE_HEADER_NAME,
+                format!("t={},v1={signature}", attempted_at.timestamp()),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (status_code, error) = match response {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16()), None)
+            }
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Some(format!("Subscriber responded with {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        let delivered = error.is_none();
+
+        let attempt = WebhookAttempt {
+            attempted_at,
+            status_code,
+            error,
+        };
+
+        (attempt, delivered)
+    }
+}
+
+#[async_trait]
+impl<WebhookDeliveryStoreImpl> OutboxWorker for WebhookWorker<WebhookDeliveryStoreImpl>
+where
+    WebhookDeliveryStoreImpl: WebhookDeliveryStore + Send + Sync,
+{
+    type Item = WebhookDelivery;
+
+    const NAME: &'static str = "webhook deliveries";
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let deliveries = self
+            .deliveries
+            .write()
+            .await
+            .claim_due(now, lease_until, BATCH_SIZE)
+            .await?;
+
+        Ok(deliveries)
+    }
+
+    async fn deliver(&self, delivery: &WebhookDelivery, now: DateTime<Utc>) -> Result<()> {
+        let subscription = self
+            .subscriptions
+            .iter()
+            .find(|subscription| subscription.url == delivery.url);
+
+        let (attempt, delivered) = match subscription {
+            Some(subscription) => self.post(subscription, delivery).await,
+            // Unsubscribed since the event was queued
+            None => (
+                WebhookAttempt {
+                    attempted_at: Utc::now(),
+                    status_code: None,
+                    error: Some("No longer subscribed".to_owned()),
+                },
+                false,
+            ),
+        };
+
+        let mut deliveries = self.deliveries.write().await;
+
+        if delivered {
+            record_webhook_delivery("sent");
+            deliveries.mark_delivered(delivery.id, attempt).await?;
+        } else if subscription.is_none() || delivery.attempts >= MAX_ATTEMPTS {
+            tracing::error!(
+                "Giving up on webhook after {} attempts: {:?}",
+                delivery.attempts,
+                attempt.error
+            );
+            record_webhook_delivery("dead_lettered");
+
+            deliveries.mark_failed(delivery.id, attempt, None).await?;
+        } else {
+            tracing::warn!("Failed to deliver webhook, will retry: {:?}", attempt.error);
+            record_webhook_delivery("retrying");
+
+            let retry_at = now + retry_delay(delivery.attempts);
+            deliveries
+                .mark_failed(delivery.id, attempt, Some(retry_at))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Delivery is part of the trace of the request that caused the event
+    fn delivery_span(&self, delivery: &WebhookDelivery) -> Span {
+        let span = tracing::info_span!(
+            "Delivering webhook",
+            id = %delivery.id,
+            kind = delivery.kind.as_str()
+        );
+        let _ = span.set_parent(trace_context_from(delivery.traceparent.as_deref()));
+
+        span
+    }
+}
+
+// The `v1` signature of a webhook sent at `timestamp`, which subscribers recompute with the
+// shared secret to check the request came from us. Signing the timestamp too lets them
+// reject replays of old requests.
+pub fn sign_webhook(secret: &SecretString, timestamp: i64, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(payload.as_bytes());
+
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Duration;
+    use tokio::sync::RwLock;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        domain::{AuditEvent, AuditEventKind, OutboxStatus, WebhookEventKind},
+        services::data_stores::HashmapWebhookDeliveryStore,
+    };
+
+    use super::*;
+
+    const SECRET: &str = "webhook-secret";
+
+    async fn setup(
+        mock_server: &MockServer,
+    ) -> (WebhookWorker<HashmapWebhookDeliveryStore>, WebhookDelivery) {
+        let url = format!("{}/hooks", mock_server.uri());
+        let deliveries = Arc::new(RwLock::new(HashmapWebhookDeliveryStore::default()));
+
+        let delivery = WebhookDelivery::new(
+            url.clone(),
+            WebhookEventKind::UserCreated,
+            &AuditEvent::new(AuditEventKind::Signup, None),
+        );
+        deliveries
+            .write()
+            .await
+            .enqueue(delivery.clone())
+            .await
+            .unwrap();
+
+        let subscription = WebhookSubscription {
+            url,
+            secret: SECRET.to_owned().into(),
+            events: vec![WebhookEventKind::UserCreated],
+        };
+
+        let http_client = Client::builder()
+            .timeout(std::time::Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let worker = WebhookWorker::new(deliveries, vec![subscription], http_client);
+
+        (worker, delivery)
+    }
+
+    async fn status(
+        worker: &WebhookWorker<HashmapWebhookDeliveryStore>,
+        delivery: &WebhookDelivery,
+    ) -> OutboxStatus {
+        worker
+            .deliveries
+            .read()
+            .await
+            .get_status(delivery.id)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_post_signed_event() {
+        let mock_server = MockServer::start().await;
+        let (worker, delivery) = setup(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/hooks"))
+            .and(header("Content-Type", "application/json"))
+            .and(header("X-Webhook-Event", "user.created"))
+            .and(header(
+                "X-Webhook-Id",
+                delivery.event_id.to_string().as_str(),
+            ))
+            .and(header_exists("X-Webhook-Signature"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_eq!(worker.process_due(Utc::now()).await.unwrap(), 1);
+        assert_eq!(status(&worker, &delivery).await, OutboxStatus::Sent);
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let signature = request.headers["X-Webhook-Signature"].to_str().unwrap();
+        let (timestamp, signature) = signature
+            .strip_prefix("t=")
+            .and_then(|rest| rest.split_once(",v1="))
+            .unwrap();
+
+        assert_eq!(
+            signature,
+            sign_webhook(
+                &SECRET.to_owned().into(),
+                timestamp.parse().unwrap(),
+                std::str::from_utf8(&request.body).unwrap()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn should_retry_failed_delivery_after_backoff() {
+        let mock_server = MockServer::start().await;
+        let (worker, delivery) = setup(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let now = Utc::now();
+        assert_eq!(worker.process_due(now).await.unwrap(), 1);
+        assert_eq!(status(&worker, &delivery).await, OutboxStatus::Pending);
+
+        // Not due again until the retry delay has passed
+        let retry_at = now + retry_delay(1);
+        let too_soon = retry_at - Duration::seconds(1);
+        assert_eq!(worker.process_due(too_soon).await.unwrap(), 0);
+
+        assert_eq!(worker.process_due(retry_at).await.unwrap(), 1);
+        assert_eq!(status(&worker, &delivery).await, OutboxStatus::Sent);
+
+        let attempts = worker
+            .deliveries
+            .read()
+            .await
+            .get_attempts(delivery.id)
+            .await
+            .unwrap();
+        let status_codes = attempts
+            .iter()
+            .map(|attempt| attempt.status_code)
+            .collect::<Vec<_>>();
+
+        assert_eq!(status_codes, [Some(503), Some(200)]);
+        assert!(attempts[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_after_max_attempts() {
+        let mock_server = MockServer::start().await;
+        let (worker, delivery) = setup(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        let mut now = Utc::now();
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            assert_eq!(worker.process_due(now).await.unwrap(), 1);
+            now += retry_delay(attempt);
+        }
+
+        assert_eq!(status(&worker, &delivery).await, OutboxStatus::Dead);
+
+        let later = now + Duration::days(1);
+        assert_eq!(worker.process_due(later).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_drop_deliveries_to_removed_subscriptions() {
+        let mock_server = MockServer::start().await;
+        let (mut worker, delivery) = setup(&mock_server).await;
+        worker.subscriptions.clear();
+
+        assert_eq!(worker.process_due(Utc::now()).await.unwrap(), 1);
+        assert_eq!(status(&worker, &delivery).await, OutboxStatus::Dead);
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_deliver_a_batch_concurrently() {
+        let mock_server = MockServer::start().await;
+        let (worker, delivery) = setup(&mock_server).await;
+
+        for _ in 1..BATCH_SIZE {
+            let delivery = WebhookDelivery::new(
+                delivery.url.clone(),
+                WebhookEventKind::UserCreated,
+                &AuditEvent::new(AuditEventKind::Signup, None),
+            );
+            worker
+                .deliveries
+                .write()
+                .await
+                .enqueue(delivery)
+                .await
+                .unwrap();
+        }
+
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200).set_delay(std::time::Duration::from_millis(100)),
+            )
+            .expect(BATCH_SIZE as u64)
+            .mount(&mock_server)
+            .await;
+
+        // One at a time, the batch would take 2 seconds
+        let started = std::time::Instant::now();
+        assert_eq!(
+            worker.process_due(Utc::now()).await.unwrap(),
+            BATCH_SIZE as usize
+        );
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn signature_depends_on_secret_timestamp_and_payload() {
+        let secret: SecretString = SECRET.to_owned().into();
+        let signature = sign_webhook(&secret, 1_700_000_000, "{}");
+
+        assert_eq!(signature.len(), 64);
+        assert_eq!(sign_webhook(&secret, 1_700_000_000, "{}"), signature);
+        assert_ne!(
+            sign_webhook(&"other".to_owned().into(), 1_700_000_000, "{}"),
+            signature
+        );
+        assert_ne!(sign_webhook(&secret, 1_700_000_001, "{}"), signature);
+        assert_ne!(sign_webhook(&secret, 1_700_000_000, "[]"), signature);
+    }
+}
@@ -0,0 +1,102 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+
+use crate::{
+    app_state::AuditSinkType,
+    domain::{AuditEvent, AuditEventKind, AuditSink, Email},
+};
+
+use super::constants::REQUEST_ID_HEADER_NAME;
+
+// Who made a request, recorded with the audit events it causes
+#[derive(Clone, Debug, Default)]
+pub struct AuditContext {
+    ip_address: Option<IpAddr>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        Ok(Self {
+            // The peer, which is the reverse proxy when there is one
+            ip_address: parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip()),
+            user_agent: header(USER_AGENT.as_str())
+                .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+            request_id: header(REQUEST_ID_HEADER_NAME),
+        })
+    }
+}
+
+impl AuditContext {
+    pub fn event(&self, kind: AuditEventKind, email: Option<&Email>) -> AuditEvent {
+        AuditEvent {
+            ip_address: self.ip_address,
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+            ..AuditEvent::new(kind, email.cloned())
+        }
+    }
+
+    pub async fn record<AuditSinkImpl>(
+        &self,
+        audit_sink: &AuditSinkType<AuditSinkImpl>,
+        kind: AuditEventKind,
+        email: Option<&Email>,
+    ) where
+        AuditSinkImpl: AuditSink,
+    {
+        record_audit_event(audit_sink, self.event(kind, email)).await;
+    }
+
+    pub async fn record_failure<AuditSinkImpl>(
+        &self,
+        audit_sink: &AuditSinkType<AuditSinkImpl>,
+        kind: AuditEventKind,
+        email: Option<&Email>,
+        reason: &str,
+    ) where
+        AuditSinkImpl: AuditSink,
+    {
+        let event = AuditEvent {
+            reason: Some(reason.to_owned()),
+            ..self.event(kind, email)
+        };
+
+        record_audit_event(audit_sink, event).await;
+    }
+}
+
+// The request already happened, so an audit log that can't be written is logged rather than
+// failing it
+#[tracing::instrument(name = "Record audit event", skip_all, fields(kind = event.kind.as_str()))]
+pub async fn record_audit_event<AuditSinkImpl>(
+    audit_sink: &AuditSinkType<AuditSinkImpl>,
+    event: AuditEvent,
+) where
+    AuditSinkImpl: AuditSink,
+{
+    if let Err(e) = audit_sink.write().await.record(event).await {
+        tracing::error!("Failed to record audit event: {:?}", e);
+    }
+}
+
+// User agents are free-form, don't let one fill the audit log
+const MAX_USER_AGENT_LENGTH: usize = 512;
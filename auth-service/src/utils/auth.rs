@@ -1,4 +1,10 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use std::sync::Arc;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
@@ -6,29 +12,57 @@ use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    app_state::BannedTokenStoreType,
-    domain::{email::Email, BannedTokenStore},
+    app_state::{BannedTokenStoreType, SessionStoreType},
+    domain::{email::Email, AuthAPIError, BannedTokenStore, Session, SessionId, SessionStore},
 };
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::{
+    constants::JWT_COOKIE_NAME,
+    csrf::constant_time_eq,
+    settings::{AdminSettings, AuthSettings, Settings},
+};
 
 // Create cookie with a new JWT auth token
 #[tracing::instrument(name = "Generate auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
-    Ok(create_auth_cookie(token))
+pub fn generate_auth_cookie(
+    email: &Email,
+    amr: &[AuthMethod],
+    settings: &AuthSettings,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, amr, settings)?;
+    Ok(create_auth_cookie(token, settings))
 }
 
-// Create cookie and set the value to the passed-in token string
+// Create cookie and set the value to the passed-in token string.
+// This is a browser-session cookie, dropped when the browser is closed.
 #[tracing::instrument(name = "Create auth cookie", skip_all)]
-pub fn create_auth_cookie(token: SecretString) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token.expose_secret().to_owned()))
-        .path("/") // apple cookie to all URLs on the server
-        .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
-        .build();
+pub fn create_auth_cookie(token: SecretString, settings: &AuthSettings) -> Cookie<'static> {
+    settings.cookies.build(
+        JWT_COOKIE_NAME,
+        token.expose_secret().to_owned(),
+        None,
+        true, // prevent JavaScript from accessing the cookie
+    )
+}
 
-    cookie
+// Create cookie that outlives the browser session, for "remember me" logins
+#[tracing::instrument(name = "Create persistent auth cookie", skip_all)]
+pub fn create_persistent_auth_cookie(
+    token: SecretString,
+    max_age: time::Duration,
+    settings: &AuthSettings,
+) -> Cookie<'static> {
+    settings.cookies.build(
+        JWT_COOKIE_NAME,
+        token.expose_secret().to_owned(),
+        Some(max_age),
+        true, // prevent JavaScript from accessing the cookie
+    )
+}
+
+// Create a cookie that clears the auth cookie, matching the attributes it was set with
+pub fn remove_auth_cookie(settings: &AuthSettings) -> Cookie<'static> {
+    settings.cookies.removal(JWT_COOKIE_NAME)
 }
 
 #[derive(Debug)]
@@ -42,7 +76,11 @@ pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
 // Create JWT auth token
 #[tracing::instrument(name = "Create JWT auth token", skip_all)]
-pub fn generate_auth_token(email: &Email) -> Result<SecretString> {
+pub fn generate_auth_token(
+    email: &Email,
+    amr: &[AuthMethod],
+    settings: &AuthSettings,
+) -> Result<SecretString> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
@@ -59,19 +97,54 @@ pub fn generate_auth_token(email: &Email) -> Result<SecretString> {
 
     let sub = email.as_ref().expose_secret().to_owned();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub,
+        exp,
+        sid: None,
+        auth_time: Utc::now().timestamp(),
+        amr: amr.to_vec(),
+    };
+
+    create_token(&claims, settings)
+}
+
+// Create long-lived JWT auth token tied to a server-side session, which expires with the session
+#[tracing::instrument(name = "Create JWT session token", skip_all)]
+pub fn generate_session_token(
+    session: &Session,
+    amr: &[AuthMethod],
+    settings: &AuthSettings,
+) -> Result<SecretString> {
+    let exp = session.expires_at.timestamp();
 
-    create_token(&claims)
+    let exp: usize = exp
+        .try_into()
+        .wrap_err_with(|| eyre!("failed to cast exp time to usize. exp time: {}", exp))?;
+
+    let claims = Claims {
+        sub: session.email.as_ref().expose_secret().to_owned(),
+        exp,
+        sid: Some(session.id.as_ref().expose_secret().to_owned()),
+        auth_time: Utc::now().timestamp(),
+        amr: amr.to_vec(),
+    };
+
+    create_token(&claims, settings)
 }
 
-// Check if JWT auth token is valid by decoding it using the JWT secret
+// Check if JWT auth token is valid by decoding it using the JWT secret.
+// Tokens tied to a session are only valid as long as the session hasn't been revoked, and
+// others as long as the user's tokens haven't been revoked since they authenticated.
 #[tracing::instrument(name = "Validate token", skip_all)]
-pub async fn validate_token<BannedTokenStoreImpl>(
+pub async fn validate_token<BannedTokenStoreImpl, SessionStoreImpl>(
     token: &SecretString,
     banned_token_store: &BannedTokenStoreType<BannedTokenStoreImpl>,
+    session_store: &SessionStoreType<SessionStoreImpl>,
+    settings: &AuthSettings,
 ) -> Result<Claims>
 where
     BannedTokenStoreImpl: BannedTokenStore,
+    SessionStoreImpl: SessionStore,
 {
     let lock = banned_token_store.read().await;
 
@@ -83,22 +156,50 @@ where
 
     drop(lock);
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .wrap_err("failed to decode token")?;
+
+    if let Some(sid) = &claims.sid {
+        let session_id = SessionId::parse(sid.to_owned().into())?;
+        let lock = session_store.read().await;
+
+        let session = lock
+            .get_session(&session_id)
+            .await
+            .wrap_err("session has been revoked")?;
+
+        drop(lock);
+
+        if *session.email.as_ref().expose_secret() != claims.sub {
+            return Err(eyre!("session belongs to another user"));
+        }
+    } else {
+        let email = Email::parse(claims.sub.clone().into())?;
+        let lock = banned_token_store.read().await;
+        let revoked_before = lock.get_tokens_revoked_before(&email).await?;
+
+        drop(lock);
+
+        if revoked_before.is_some_and(|time| claims.auth_time < time.timestamp()) {
+            return Err(eyre!("token has been revoked"));
+        }
+    }
+
+    Ok(claims)
 }
 
 // Create JWT auth token by encoding claims using the JWT secret
 #[tracing::instrument(name = "Create JWT auth token", skip_all)]
-fn create_token(claims: &Claims) -> Result<SecretString> {
+fn create_token(claims: &Claims, settings: &AuthSettings) -> Result<SecretString> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
     )
     .map(|t| t.into())
     .wrap_err("failed to create token")
@@ -108,31 +209,162 @@ fn create_token(claims: &Claims) -> Result<SecretString> {
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // Session id, only present on "remember me" tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    // When the user last proved who they are, in seconds since the epoch.
+    // Unlike `exp`, this is not extended by a long-lived session.
+    #[serde(default)]
+    pub auth_time: i64,
+    // How the user proved who they are
+    #[serde(default)]
+    pub amr: Vec<AuthMethod>,
 }
 
+impl Claims {
+    // Whether the user authenticated recently enough, and strongly enough, for a sensitive
+    // operation. Users with 2FA must have entered a code, rather than skipped it on a trusted device.
+    pub fn is_recent_auth(&self, now: i64, max_age_seconds: i64, requires_2fa: bool) -> bool {
+        now - self.auth_time <= max_age_seconds
+            && (!requires_2fa || self.amr.contains(&AuthMethod::Otp))
+    }
+}
+
+// Authentication method reference values (RFC 8176) recorded in the `amr` claim
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMethod {
+    // Password
+    Pwd,
+    // One-time code sent by email or SMS
+    Otp,
+}
+
+// Step-up guard for sensitive operations, such as changing the password or the 2FA settings:
+// an old token, or one that skipped 2FA, must be exchanged for a fresh one first
+pub fn require_recent_auth(
+    claims: &Claims,
+    requires_2fa: bool,
+    settings: &AuthSettings,
+) -> Result<(), AuthAPIError> {
+    if !claims.is_recent_auth(
+        Utc::now().timestamp(),
+        settings.step_up_max_age_seconds,
+        requires_2fa,
+    ) {
+        return Err(AuthAPIError::ReauthenticationRequired);
+    }
+
+    Ok(())
+}
+
+// Guard for the `/admin/*` routes and `/metrics`: the request must carry the admin API token
+// as a bearer token
+pub fn require_admin_token(
+    headers: &HeaderMap,
+    settings: &AdminSettings,
+) -> Result<(), AuthAPIError> {
+    let token = bearer_token(headers).ok_or(AuthAPIError::MissingToken)??;
+
+    if !constant_time_eq(token, settings.api_token.expose_secret()) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    Ok(())
+}
+
+// JWT auth token extracted from the request, along with where it was found
+#[derive(Debug)]
+pub enum AuthToken {
+    Cookie(SecretString),
+    Bearer(SecretString),
+}
+
+impl AsRef<SecretString> for AuthToken {
+    fn as_ref(&self) -> &SecretString {
+        match self {
+            Self::Cookie(token) | Self::Bearer(token) => token,
+        }
+    }
+}
+
+// Read the token from the `Authorization: Bearer` header, falling back to the JWT cookie.
+// A present but malformed `Authorization` header is rejected rather than ignored.
+// The cookie name depends on the settings `Application::build` adds to each request.
+impl<S> FromRequestParts<S> for AuthToken
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(token) = bearer_token(&parts.headers) {
+            return Ok(Self::Bearer(token?.to_owned().into()));
+        }
+
+        let settings = parts
+            .extensions
+            .get::<Arc<Settings>>()
+            .ok_or_else(|| AuthAPIError::UnexpectedError(eyre!("settings are missing")))?;
+
+        let jar = CookieJar::from_headers(&parts.headers);
+        let cookie = jar
+            .get(&settings.auth.cookies.name(JWT_COOKIE_NAME))
+            .ok_or(AuthAPIError::MissingToken)?;
+
+        Ok(Self::Cookie(cookie.value().to_owned().into()))
+    }
+}
+
+// The token in the `Authorization` header, if there is one. The scheme is matched
+// case-insensitively, and a header without a bearer token is rejected.
+fn bearer_token(headers: &HeaderMap) -> Option<Result<&str, AuthAPIError>> {
+    let header = headers.get(AUTHORIZATION)?;
+
+    let token = header
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(BEARER_SCHEME))
+        .map(|(_, token)| token.trim())
+        .filter(|token| !token.is_empty())
+        .ok_or(AuthAPIError::InvalidToken);
+
+    Some(token)
+}
+
+const BEARER_SCHEME: &str = "Bearer";
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
-    use crate::services::data_stores::HashsetBannedTokenStore;
+    use crate::services::data_stores::{HashmapSessionStore, HashsetBannedTokenStore};
+
+    use axum_extra::extract::cookie::SameSite;
 
     use super::*;
 
+    fn auth_settings() -> AuthSettings {
+        Settings::test().auth
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse("test@example.com".into()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&email, &[AuthMethod::Pwd], &auth_settings()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), None);
     }
 
     #[tokio::test]
     async fn test_create_auth_cookie() {
         let token: SecretString = "test_token".into();
-        let cookie = create_auth_cookie(token.clone());
+        let cookie = create_auth_cookie(token.clone(), &auth_settings());
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value(), token.expose_secret());
         assert_eq!(cookie.path(), Some("/"));
@@ -140,21 +372,203 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_create_persistent_auth_cookie() {
+        let token: SecretString = "test_token".into();
+        let cookie =
+            create_persistent_auth_cookie(token, time::Duration::days(30), &auth_settings());
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.max_age(), Some(time::Duration::days(30)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_session() {
+        let email = Email::parse("test@example.com".into()).unwrap();
+        let session = Session::new(email, chrono::Duration::days(30));
+        let token = generate_session_token(&session, &[AuthMethod::Pwd], &auth_settings()).unwrap();
+        let session_store: SessionStoreType<HashmapSessionStore> = Default::default();
+
+        let result = validate_token::<HashsetBannedTokenStore, _>(
+            &token,
+            &Default::default(),
+            &session_store,
+            &auth_settings(),
+        )
+        .await;
+
+        assert!(result.is_err(), "unknown session should be rejected");
+
+        session_store
+            .write()
+            .await
+            .add_session(session.clone())
+            .await
+            .unwrap();
+
+        let claims = validate_token::<HashsetBannedTokenStore, _>(
+            &token,
+            &Default::default(),
+            &session_store,
+            &auth_settings(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(
+            claims.sid.as_deref(),
+            Some(session.id.as_ref().expose_secret())
+        );
+        assert_eq!(claims.exp, session.expires_at.timestamp() as usize);
+
+        session_store
+            .write()
+            .await
+            .remove_session(&session.id)
+            .await
+            .unwrap();
+
+        let result = validate_token::<HashsetBannedTokenStore, _>(
+            &token,
+            &Default::default(),
+            &session_store,
+            &auth_settings(),
+        )
+        .await;
+
+        assert!(result.is_err(), "revoked session should be rejected");
+    }
+
+    #[tokio::test]
+    async fn test_remove_auth_cookie() {
+        let cookie = remove_auth_cookie(&auth_settings());
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.max_age(), Some(time::Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_from_bearer_header() {
+        let request = axum::http::Request::builder()
+            .extension(Arc::new(Settings::test()))
+            .header(AUTHORIZATION, "Bearer test_token")
+            .header("Cookie", format!("{JWT_COOKIE_NAME}=cookie_token"))
+            .body(())
+            .unwrap();
+
+        let (mut parts, _) = request.into_parts();
+        let token = AuthToken::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert!(matches!(token, AuthToken::Bearer(_)));
+        assert_eq!(token.as_ref().expose_secret(), "test_token");
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_from_cookie() {
+        let request = axum::http::Request::builder()
+            .extension(Arc::new(Settings::test()))
+            .header("Cookie", format!("{JWT_COOKIE_NAME}=cookie_token"))
+            .body(())
+            .unwrap();
+
+        let (mut parts, _) = request.into_parts();
+        let token = AuthToken::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert!(matches!(token, AuthToken::Cookie(_)));
+        assert_eq!(token.as_ref().expose_secret(), "cookie_token");
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_rejects_malformed_header() {
+        for value in ["Basic dXNlcjpwYXNz", "Bearer", "Bearer  ", "test_token"] {
+            let request = axum::http::Request::builder()
+                .extension(Arc::new(Settings::test()))
+                .header(AUTHORIZATION, value)
+                .body(())
+                .unwrap();
+
+            let (mut parts, _) = request.into_parts();
+            let result = AuthToken::from_request_parts(&mut parts, &()).await;
+            assert!(
+                matches!(result, Err(AuthAPIError::InvalidToken)),
+                "Failed for header: {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_require_admin_token() {
+        let settings = AdminSettings {
+            api_token: "admin_token".to_owned().into(),
+        };
+
+        for value in [
+            "Bearer admin_token",
+            "bearer admin_token",
+            "BEARER  admin_token",
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+
+            assert!(
+                require_admin_token(&headers, &settings).is_ok(),
+                "Failed for header: {value}"
+            );
+        }
+
+        for value in ["Bearer wrong_token", "Basic admin_token", "admin_token"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+
+            assert!(
+                matches!(
+                    require_admin_token(&headers, &settings),
+                    Err(AuthAPIError::InvalidToken)
+                ),
+                "Failed for header: {value}"
+            );
+        }
+
+        assert!(matches!(
+            require_admin_token(&HeaderMap::new(), &settings),
+            Err(AuthAPIError::MissingToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_missing() {
+        let request = axum::http::Request::builder()
+            .extension(Arc::new(Settings::test()))
+            .body(())
+            .unwrap();
+        let (mut parts, _) = request.into_parts();
+        let result = AuthToken::from_request_parts(&mut parts, &()).await;
+        assert!(matches!(result, Err(AuthAPIError::MissingToken)));
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse("test@example.com".into()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, &[AuthMethod::Pwd], &auth_settings()).unwrap();
         assert_eq!(result.expose_secret().split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse("test@example.com".into()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &[AuthMethod::Pwd], &auth_settings()).unwrap();
 
-        let result = validate_token::<HashsetBannedTokenStore>(&token, &Default::default())
-            .await
-            .unwrap();
+        let result = validate_token::<HashsetBannedTokenStore, HashmapSessionStore>(
+            &token,
+            &Default::default(),
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(result.sub, "test@example.com");
 
@@ -166,19 +580,133 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_records_authentication() {
+        let email = Email::parse("test@example.com".into()).unwrap();
+        let token = generate_auth_token(
+            &email,
+            &[AuthMethod::Pwd, AuthMethod::Otp],
+            &auth_settings(),
+        )
+        .unwrap();
+
+        let claims = validate_token::<HashsetBannedTokenStore, HashmapSessionStore>(
+            &token,
+            &Default::default(),
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(claims.amr, vec![AuthMethod::Pwd, AuthMethod::Otp]);
+        assert!((Utc::now().timestamp() - claims.auth_time).abs() <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_revoked_after_authentication() {
+        let email = Email::parse("test@example.com".into()).unwrap();
+        let token = generate_auth_token(&email, &[AuthMethod::Pwd], &auth_settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType<HashsetBannedTokenStore> = Default::default();
+
+        banned_token_store
+            .write()
+            .await
+            .revoke_tokens_before(&email, Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+
+        let result = validate_token::<_, HashmapSessionStore>(
+            &token,
+            &banned_token_store,
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await;
+
+        assert!(result.is_err(), "revoked token should be rejected");
+
+        banned_token_store
+            .write()
+            .await
+            .revoke_tokens_before(&email, Utc::now() - chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+
+        let result = validate_token::<_, HashmapSessionStore>(
+            &token,
+            &banned_token_store,
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await;
+
+        assert!(
+            result.is_ok(),
+            "token issued after revocation should be accepted"
+        );
+    }
+
+    #[test]
+    fn test_is_recent_auth() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            exp: 0,
+            sid: None,
+            auth_time: 1_000,
+            amr: vec![AuthMethod::Pwd],
+        };
+
+        assert!(claims.is_recent_auth(1_300, 300, false));
+        assert!(!claims.is_recent_auth(1_301, 300, false));
+
+        // A password alone isn't enough for a user with 2FA
+        assert!(!claims.is_recent_auth(1_000, 300, true));
+
+        let claims = Claims {
+            amr: vec![AuthMethod::Pwd, AuthMethod::Otp],
+            ..claims
+        };
+
+        assert!(claims.is_recent_auth(1_000, 300, true));
+    }
+
+    #[test]
+    fn test_claims_without_auth_time_are_not_recent() {
+        let claims: Claims = serde_json::from_str(r#"{"sub":"test@example.com","exp":0}"#).unwrap();
+
+        assert!(!claims.is_recent_auth(Utc::now().timestamp(), 300, false));
+        assert!(matches!(
+            require_recent_auth(&claims, false, &auth_settings()),
+            Err(AuthAPIError::ReauthenticationRequired)
+        ));
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".into();
-        let result = validate_token::<HashsetBannedTokenStore>(&token, &Default::default()).await;
+        let result = validate_token::<HashsetBannedTokenStore, HashmapSessionStore>(
+            &token,
+            &Default::default(),
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
         let email = Email::parse("test@example.com".into()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &[AuthMethod::Pwd], &auth_settings()).unwrap();
         let banned_token_store = HashsetBannedTokenStore::from([token.expose_secret().to_owned()]);
-        let result = validate_token(&token, &Arc::new(banned_token_store.into())).await;
+        let result = validate_token::<_, HashmapSessionStore>(
+            &token,
+            &Arc::new(banned_token_store.into()),
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await;
         assert!(result.is_err());
     }
 }
@@ -1,92 +1,55 @@
-use dotenvy::dotenv;
-use lazy_static::lazy_static;
-use secrecy::SecretString;
-use std::env as std_env;
-
-lazy_static! {
-    pub static ref AUTH_SERVICE_IP: String = set_auth_service_ip();
-    pub static ref JWT_SECRET: SecretString = set_token();
-    pub static ref DATABASE_URL: SecretString = set_database_url();
-    pub static ref REDIS_HOST_NAME: String = set_redis_host();
-    pub static ref POSTMARK_AUTH_TOKEN: SecretString = set_postmark_auth_token();
-}
-
-fn set_auth_service_ip() -> String {
-    dotenv().ok();
-    let secret = std_env::var(env::AUTH_SERVICE_IP_ENV_VAR).expect("AUTH_SERVICE_IP must be set.");
-
-    if secret.is_empty() {
-        panic!("AUTH_SERVICE_IP must not be empty.");
-    }
-
-    secret
-}
-
-fn set_token() -> SecretString {
-    dotenv().ok();
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
-    }
-
-    secret.into()
-}
-
-fn set_database_url() -> SecretString {
-    dotenv().ok();
-    let secret = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.");
-
-    if secret.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
-    }
-
-    secret.into()
-}
-
-fn set_redis_host() -> String {
-    dotenv().ok();
-    std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
-}
-
-fn set_postmark_auth_token() -> SecretString {
-    dotenv().ok();
-
-    std_env::var(env::POSTMARK_AUTH_TOKEN_ENV_VAR)
-        .expect("POSTMARK_AUTH_TOKEN must be set.")
-        .into()
-}
-
-pub mod env {
-    pub const AUTH_SERVICE_IP_ENV_VAR: &str = "AUTH_SERVICE_IP";
-    pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
-    pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
-    pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
-    pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
-}
-
 pub const JWT_COOKIE_NAME: &str = "jwt";
-pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
-
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
-
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+pub const REQUEST_ID_HEADER_NAME: &str = "x-request-id";
+pub const TRUSTED_DEVICE_COOKIE_NAME: &str = "trusted_device";
+pub const MAX_PENDING_LOGIN_ATTEMPTS: usize = 5;
+pub const TWO_FA_RESEND_COOLDOWN_SECONDS: i64 = 30;
+pub const MAX_TWO_FA_RESENDS: u32 = 3;
+pub const MAX_TWO_FA_CODE_ATTEMPTS: u32 = 5;
+pub const MAX_PHONE_VERIFICATION_ATTEMPTS: u32 = 5;
+pub const AUDIT_EVENTS_DEFAULT_LIMIT: u32 = 100;
+pub const AUDIT_EVENTS_MAX_LIMIT: u32 = 1000;
+
+// Shared by the email outbox and webhook workers
+pub mod outbox {
+    use std::time::Duration;
+
+    pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+    pub const BATCH_SIZE: u32 = 20;
+    // How long a claimed item stays hidden from other workers
+    pub const LEASE_SECONDS: i64 = 60;
+    pub const BASE_RETRY_DELAY_SECONDS: i64 = 10;
+    pub const MAX_RETRY_DELAY_SECONDS: i64 = 60 * 60; // 1 hour
+    pub const MAX_ATTEMPTS: u32 = 10;
+    pub const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+}
+
+pub mod email_outbox {
+    // Sent and dead-lettered emails are kept this long, without their bodies
+    pub const RETENTION_DAYS: i64 = 30;
+}
+
+pub mod webhooks {
+    // `t=<unix timestamp>,v1=<hex HMAC-SHA256 of "<timestamp>.<body>">`
+    pub const SIGNATURE_HEADER_NAME: &str = "x-webhook-signature";
+    // The event's id, the same on every retry
+    pub const EVENT_ID_HEADER_NAME: &str = "x-webhook-id";
+    pub const EVENT_TYPE_HEADER_NAME: &str = "x-webhook-event";
+}
+
+// Values for unit tests, the API tests use `configuration/test.yaml`
+pub mod test {
     pub mod email_client {
         use std::time::Duration;
 
-        pub const BASE_URL: &str = "https://api.postmarkapp.com/email";
-        pub const SENDER: &str = "bogdan@codeiron.io";
-        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
-}
-
-pub mod test {
-    pub const APP_ADDRESS: &str = "127.0.0.1:0";
 
-    pub mod email_client {
+    pub mod sms_client {
         use std::time::Duration;
 
-        pub const SENDER: &str = "test@email.com";
+        pub const ACCOUNT_SID: &str = "AC00000000000000000000000000000000";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
 }
@@ -0,0 +1,210 @@
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use color_eyre::eyre::{eyre, Report, Result};
+use serde::Deserialize;
+use time::Duration;
+
+// Attributes shared by every cookie the service sets, so that cookies can be configured
+// per environment and removed with the same attributes they were set with.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "CookieSettingsConfig")]
+pub struct CookieSettings {
+    secure: bool,
+    domain: Option<String>,
+    same_site: SameSite,
+    host_prefix: bool,
+}
+
+impl CookieSettings {
+    pub fn new(
+        secure: bool,
+        domain: Option<String>,
+        same_site: SameSite,
+        host_prefix: bool,
+    ) -> Result<Self> {
+        let domain = domain.filter(|d| !d.is_empty());
+
+        // See https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie#cookie_prefixes
+        if host_prefix && (!secure || domain.is_some()) {
+            return Err(eyre!(
+                "cookies with the {HOST_PREFIX} prefix must be Secure and must not set a Domain"
+            ));
+        }
+
+        if same_site == SameSite::None && !secure {
+            return Err(eyre!("cookies with SameSite=None must be Secure"));
+        }
+
+        Ok(Self {
+            secure,
+            domain,
+            same_site,
+            host_prefix,
+        })
+    }
+
+    // The name the cookie is actually sent under, including the `__Host-` prefix if enabled
+    pub fn name(&self, name: &str) -> String {
+        if self.host_prefix {
+            format!("{HOST_PREFIX}{name}")
+        } else {
+            name.to_owned()
+        }
+    }
+
+    // Build a cookie with the configured attributes applied
+    pub fn build(
+        &self,
+        name: &str,
+        value: String,
+        max_age: Option<Duration>,
+        http_only: bool,
+    ) -> Cookie<'static> {
+        let mut builder = Cookie::build((self.name(name), value))
+            .path("/") // apply cookie to all URLs on the server
+            .http_only(http_only) // whether to hide the cookie from JavaScript
+            .secure(self.secure) // only send the cookie over HTTPS
+            .same_site(self.same_site);
+
+        // Without a Max-Age, the cookie only lasts for the browser session
+        if let Some(max_age) = max_age {
+            builder = builder.max_age(max_age);
+        }
+
+        if let Some(domain) = &self.domain {
+            builder = builder.domain(domain.clone());
+        }
+
+        builder.build()
+    }
+
+    // Browsers only delete a cookie when the removal matches its name, path and domain
+    pub fn removal(&self, name: &str) -> Cookie<'static> {
+        self.build(name, String::new(), Some(Duration::ZERO), true)
+    }
+}
+
+impl Default for CookieSettings {
+    fn default() -> Self {
+        Self {
+            secure: false,
+            domain: None,
+            same_site: SameSite::Lax,
+            host_prefix: false,
+        }
+    }
+}
+
+// The cookie settings as written in the configuration, checked by `CookieSettings::new`
+#[derive(Deserialize)]
+struct CookieSettingsConfig {
+    secure: bool,
+    domain: Option<String>,
+    same_site: String,
+    host_prefix: bool,
+}
+
+impl TryFrom<CookieSettingsConfig> for CookieSettings {
+    type Error = Report;
+
+    fn try_from(config: CookieSettingsConfig) -> Result<Self> {
+        Self::new(
+            config.secure,
+            config.domain,
+            parse_same_site(&config.same_site)?,
+            config.host_prefix,
+        )
+    }
+}
+
+pub fn parse_same_site(value: &str) -> Result<SameSite> {
+    match value.to_ascii_lowercase().as_str() {
+        "strict" => Ok(SameSite::Strict),
+        "lax" => Ok(SameSite::Lax),
+        "none" => Ok(SameSite::None),
+        _ => Err(eyre!("{value} is not a valid SameSite value")),
+    }
+}
+
+const HOST_PREFIX: &str = "__Host-";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_settings() {
+        let cookie = CookieSettings::default().build("jwt", "token".to_owned(), None, true);
+
+        assert_eq!(cookie.name(), "jwt");
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(false));
+        assert_eq!(cookie.domain(), None);
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), None);
+    }
+
+    #[test]
+    fn test_shared_domain() {
+        let settings = CookieSettings::new(
+            true,
+            Some("example.com".to_owned()),
+            SameSite::Strict,
+            false,
+        )
+        .unwrap();
+
+        let cookie = settings.build("jwt", "token".to_owned(), Some(Duration::days(30)), true);
+
+        assert_eq!(cookie.name(), "jwt");
+        assert_eq!(cookie.max_age(), Some(Duration::days(30)));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+    }
+
+    #[test]
+    fn test_host_prefix() {
+        let settings = CookieSettings::new(true, None, SameSite::Lax, true).unwrap();
+        let cookie = settings.build("jwt", "token".to_owned(), None, true);
+
+        assert_eq!(cookie.name(), "__Host-jwt");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.domain(), None);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(CookieSettings::new(false, None, SameSite::Lax, true).is_err());
+        assert!(
+            CookieSettings::new(true, Some("example.com".into()), SameSite::Lax, true).is_err()
+        );
+        assert!(CookieSettings::new(false, None, SameSite::None, false).is_err());
+    }
+
+    #[test]
+    fn test_removal_matches_attributes() {
+        let settings =
+            CookieSettings::new(true, Some("example.com".to_owned()), SameSite::Lax, false)
+                .unwrap();
+
+        let mut cookie = settings.removal("jwt");
+        cookie.make_removal();
+
+        assert_eq!(cookie.name(), "jwt");
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.max_age(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_parse_same_site() {
+        assert_eq!(parse_same_site("Strict").unwrap(), SameSite::Strict);
+        assert_eq!(parse_same_site("lax").unwrap(), SameSite::Lax);
+        assert_eq!(parse_same_site("NONE").unwrap(), SameSite::None);
+        assert!(parse_same_site("sometimes").is_err());
+    }
+}
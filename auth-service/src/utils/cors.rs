@@ -0,0 +1,273 @@
+use std::{str::FromStr, time::Duration};
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use color_eyre::eyre::{eyre, Report, Result};
+use serde::{Deserialize, Deserializer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::constants::REQUEST_ID_HEADER_NAME;
+
+// Which cross-origin requests browsers may make, e.g. from app-service.
+// Credentials are always allowed, since the auth cookie is what cross-origin callers rely on.
+// The request ID is always readable, so a browser client can quote it when reporting errors.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "CorsSettingsConfig")]
+pub struct CorsSettings {
+    allowed_origins: Vec<OriginPattern>,
+    allowed_methods: Vec<Method>,
+    allowed_headers: Vec<HeaderName>,
+    max_age: Duration,
+}
+
+impl CorsSettings {
+    pub fn new(
+        allowed_origins: Vec<OriginPattern>,
+        allowed_methods: Vec<Method>,
+        allowed_headers: Vec<HeaderName>,
+        max_age: Duration,
+    ) -> Self {
+        Self {
+            allowed_origins,
+            allowed_methods,
+            allowed_headers,
+            max_age,
+        }
+    }
+
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|pattern| pattern.matches(origin))
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let settings = self.clone();
+
+        CorsLayer::new()
+            .allow_methods(self.allowed_methods.clone())
+            .allow_headers(self.allowed_headers.clone())
+            .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER_NAME)])
+            .allow_credentials(true)
+            .max_age(self.max_age)
+            .allow_origin(AllowOrigin::predicate(
+                move |origin: &HeaderValue, _| match origin.to_str() {
+                    Ok(origin) => settings.is_allowed(origin),
+                    Err(_) => false,
+                },
+            ))
+    }
+}
+
+// An allowed origin, either exact (`https://example.com`) or covering every subdomain of a
+// domain (`https://*.example.com`). The domain itself has to be listed separately.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OriginPattern {
+    scheme: String,
+    host: HostPattern,
+    port: Option<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum HostPattern {
+    Exact(String),
+    Subdomains(String),
+}
+
+impl OriginPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let invalid = || eyre!("{pattern} is not a valid origin, e.g. https://*.example.com");
+
+        let (scheme, rest) = pattern.split_once("://").ok_or_else(invalid)?;
+        let (host, port) = split_port(rest).ok_or_else(invalid)?;
+
+        if !matches!(scheme, "http" | "https") {
+            return Err(invalid());
+        }
+
+        let host = match host.strip_prefix("*.") {
+            Some(domain) if is_host(domain) => HostPattern::Subdomains(domain.to_ascii_lowercase()),
+            None if is_host(host) => HostPattern::Exact(host.to_ascii_lowercase()),
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self {
+            scheme: scheme.to_owned(),
+            host,
+            port,
+        })
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        let Some((scheme, rest)) = origin.split_once("://") else {
+            return false;
+        };
+
+        let Some((host, port)) = split_port(rest) else {
+            return false;
+        };
+
+        if scheme != self.scheme || port != self.port || !is_host(host) {
+            return false;
+        }
+
+        let host = host.to_ascii_lowercase();
+
+        match &self.host {
+            HostPattern::Exact(expected) => host == *expected,
+            HostPattern::Subdomains(domain) => host
+                .strip_suffix(domain.as_str())
+                .and_then(|subdomain| subdomain.strip_suffix('.'))
+                .is_some_and(|subdomain| !subdomain.is_empty()),
+        }
+    }
+}
+
+impl FromStr for OriginPattern {
+    type Err = Report;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        Self::parse(pattern)
+    }
+}
+
+// Split `host[:port]`, rejecting anything with a path, query or credentials
+fn split_port(authority: &str) -> Option<(&str, Option<u16>)> {
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host, Some(port.parse().ok()?))),
+        None => Some((authority, None)),
+    }
+}
+
+fn is_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+// The CORS settings as written in the configuration. Lists may also be given as
+// comma-separated strings, which is how environment variables set them.
+#[derive(Deserialize)]
+struct CorsSettingsConfig {
+    #[serde(deserialize_with = "deserialize_list")]
+    allowed_origins: Vec<String>,
+    #[serde(deserialize_with = "deserialize_list")]
+    allowed_methods: Vec<String>,
+    #[serde(deserialize_with = "deserialize_list")]
+    allowed_headers: Vec<String>,
+    max_age_seconds: u64,
+}
+
+impl TryFrom<CorsSettingsConfig> for CorsSettings {
+    type Error = Report;
+
+    fn try_from(config: CorsSettingsConfig) -> Result<Self> {
+        let allowed_origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| OriginPattern::parse(origin))
+            .collect::<Result<_>>()?;
+
+        let allowed_methods = config
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| eyre!("{method} is not a valid HTTP method"))
+            })
+            .collect::<Result<_>>()?;
+
+        let allowed_headers = config
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::from_bytes(header.as_bytes())
+                    .map_err(|_| eyre!("{header} is not a valid header name"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self::new(
+            allowed_origins,
+            allowed_methods,
+            allowed_headers,
+            Duration::from_secs(config.max_age_seconds),
+        ))
+    }
+}
+
+fn deserialize_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Items(Vec<String>),
+        CommaSeparated(String),
+    }
+
+    let items = match List::deserialize(deserializer)? {
+        List::Items(items) => items,
+        List::CommaSeparated(items) => items.split(',').map(ToOwned::to_owned).collect(),
+    };
+
+    Ok(items
+        .iter()
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_origin() {
+        let pattern = OriginPattern::parse("http://localhost:8000").unwrap();
+
+        assert!(pattern.matches("http://localhost:8000"));
+        assert!(pattern.matches("http://LOCALHOST:8000"));
+        assert!(!pattern.matches("http://localhost"));
+        assert!(!pattern.matches("http://localhost:8001"));
+        assert!(!pattern.matches("https://localhost:8000"));
+        assert!(!pattern.matches("http://localhost.evil.com:8000"));
+    }
+
+    #[test]
+    fn test_subdomain_origin() {
+        let pattern = OriginPattern::parse("https://*.example.com").unwrap();
+
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(pattern.matches("https://a.b.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("https://.example.com"));
+        assert!(!pattern.matches("https://evilexample.com"));
+        assert!(!pattern.matches("https://app.example.com.evil.com"));
+        assert!(!pattern.matches("http://app.example.com"));
+        assert!(!pattern.matches("https://app.example.com:8443"));
+    }
+
+    #[test]
+    fn test_invalid_origin_patterns() {
+        for pattern in [
+            "*",
+            "example.com",
+            "ftp://example.com",
+            "https://",
+            "https://*",
+            "https://app.*.example.com",
+            "https://example.com/path",
+            "https://example.com:port",
+        ] {
+            assert!(
+                OriginPattern::parse(pattern).is_err(),
+                "Failed for pattern: {pattern}"
+            );
+        }
+    }
+}
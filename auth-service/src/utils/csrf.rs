@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Request},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+
+use crate::domain::AuthAPIError;
+
+use super::{
+    constants::{CSRF_COOKIE_NAME, CSRF_HEADER_NAME, JWT_COOKIE_NAME},
+    settings::{AuthSettings, Settings},
+};
+
+// Create cookie with a new random CSRF token, to be issued alongside the auth cookie
+// and to last as long as it does
+#[tracing::instrument(name = "Generate CSRF cookie", skip_all)]
+pub fn generate_csrf_cookie(
+    max_age: Option<time::Duration>,
+    settings: &AuthSettings,
+) -> Cookie<'static> {
+    let token: String = rand::random::<[u8; CSRF_TOKEN_BYTES]>()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+
+    settings.cookies.build(
+        CSRF_COOKIE_NAME,
+        token,
+        max_age,
+        false, // JavaScript must read the cookie to echo it back in the CSRF header
+    )
+}
+
+// Create a cookie that clears the CSRF cookie, matching the attributes it was set with
+pub fn remove_csrf_cookie(settings: &AuthSettings) -> Cookie<'static> {
+    settings.cookies.removal(CSRF_COOKIE_NAME)
+}
+
+// Double-submit CSRF check for state-changing requests.
+// Only requests carrying the JWT cookie are checked: browsers attach cookies automatically,
+// whereas a bearer token in the `Authorization` header can't be forged by another site.
+#[tracing::instrument(name = "CSRF protection", skip_all)]
+pub async fn csrf_protection(
+    Extension(settings): Extension<Arc<Settings>>,
+    jar: CookieJar,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cookies = &settings.auth.cookies;
+
+    if request.method().is_safe()
+        || request.headers().contains_key(AUTHORIZATION)
+        || jar.get(&cookies.name(JWT_COOKIE_NAME)).is_none()
+    {
+        return next.run(request).await;
+    }
+
+    let cookie_token = jar
+        .get(&cookies.name(CSRF_COOKIE_NAME))
+        .map(|cookie| cookie.value());
+
+    let header_token = request
+        .headers()
+        .get(CSRF_HEADER_NAME)
+        .and_then(|value| value.to_str().ok());
+
+    match (cookie_token, header_token) {
+        (Some(cookie_token), Some(header_token))
+            if !cookie_token.is_empty() && constant_time_eq(cookie_token, header_token) =>
+        {
+            next.run(request).await
+        }
+        _ => AuthAPIError::InvalidCsrfToken.into_response(),
+    }
+}
+
+// Compare without short-circuiting so the response time doesn't leak the matching prefix
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+const CSRF_TOKEN_BYTES: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_csrf_cookie() {
+        let settings = Settings::test().auth;
+        let cookie = generate_csrf_cookie(None, &settings);
+        assert_eq!(cookie.name(), CSRF_COOKIE_NAME);
+        assert_eq!(cookie.value().len(), CSRF_TOKEN_BYTES * 2);
+        assert!(cookie.value().chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(false));
+        assert_eq!(cookie.max_age(), None);
+        assert_ne!(
+            cookie.value(),
+            generate_csrf_cookie(None, &settings).value()
+        );
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("abcdef", "abcdef"));
+        assert!(!constant_time_eq("abcdef", "abcdeg"));
+        assert!(!constant_time_eq("abcdef", "abcde"));
+        assert!(!constant_time_eq("", "a"));
+    }
+}
@@ -0,0 +1,280 @@
+use askama::Template;
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+
+use crate::domain::{EmailContent, Locale, TwoFACode};
+
+// The transactional emails we send. Each one has an HTML and a plain-text template under
+// `templates/emails`, rendered with the strings for the user's locale and the branding settings.
+#[derive(Clone, Copy, Debug)]
+pub enum EmailTemplate<'a> {
+    TwoFACode { code: &'a TwoFACode },
+    TwoFAEnabled,
+    TwoFADisabled,
+}
+
+impl EmailTemplate<'_> {
+    pub fn render(&self, locale: Locale, brand: &Branding) -> Result<EmailContent> {
+        let t = Translations::for_locale(locale);
+
+        match self {
+            Self::TwoFACode { code } => {
+                let subject = t.two_fa_code_subject;
+                let code = code.as_ref().expose_secret();
+
+                render(
+                    subject,
+                    TwoFACodeHtml {
+                        t,
+                        brand,
+                        subject,
+                        code,
+                    },
+                    TwoFACodeText { t, brand, code },
+                )
+            }
+            Self::TwoFAEnabled | Self::TwoFADisabled => {
+                let enabled = matches!(self, Self::TwoFAEnabled);
+
+                let subject = if enabled {
+                    t.two_fa_enabled_subject
+                } else {
+                    t.two_fa_disabled_subject
+                };
+
+                render(
+                    subject,
+                    TwoFAChangedHtml {
+                        t,
+                        brand,
+                        subject,
+                        enabled,
+                    },
+                    TwoFAChangedText { t, brand, enabled },
+                )
+            }
+        }
+    }
+}
+
+// Render a template by name with sample values, for the development preview route.
+// Returns `None` for unknown names.
+pub fn render_preview(
+    name: &str,
+    locale: Locale,
+    brand: &Branding,
+) -> Result<Option<EmailContent>> {
+    let code = TwoFACode::parse(PREVIEW_TWO_FA_CODE.to_owned().into())?;
+
+    let template = match name {
+        "two-fa-code" => EmailTemplate::TwoFACode { code: &code },
+        "two-fa-enabled" => EmailTemplate::TwoFAEnabled,
+        "two-fa-disabled" => EmailTemplate::TwoFADisabled,
+        _ => return Ok(None),
+    };
+
+    template.render(locale, brand).map(Some)
+}
+
+const PREVIEW_TWO_FA_CODE: &str = "123456";
+
+fn render(subject: &str, html: impl Template, text: impl Template) -> Result<EmailContent> {
+    Ok(EmailContent {
+        subject: subject.to_owned(),
+        html_body: html.render()?,
+        text_body: text.render()?,
+    })
+}
+
+// Values shared by all emails, so they carry the product's look
+#[derive(Clone, Debug, Deserialize)]
+pub struct Branding {
+    pub product_name: String,
+    // A CSS color, used for the email header
+    pub primary_color: String,
+    pub logo_url: Option<String>,
+    pub support_email: Option<String>,
+}
+
+// User-facing strings, one set per supported locale
+pub struct Translations {
+    lang: &'static str,
+    two_fa_code_subject: &'static str,
+    two_fa_code_intro: &'static str,
+    two_fa_code_ignore: &'static str,
+    two_fa_enabled_subject: &'static str,
+    two_fa_enabled_body: &'static str,
+    two_fa_disabled_subject: &'static str,
+    two_fa_disabled_body: &'static str,
+    not_you: &'static str,
+    footer: &'static str,
+    contact: &'static str,
+}
+
+impl Translations {
+    fn for_locale(locale: Locale) -> &'static Self {
+        match locale {
+            Locale::En => &EN,
+            Locale::Es => &ES,
+        }
+    }
+}
+
+const EN: Translations = Translations {
+    lang: "en",
+    two_fa_code_subject: "Your verification code",
+    two_fa_code_intro: "Enter this code to confirm it's you:",
+    two_fa_code_ignore: "If you didn't ask for a code, someone may know your password. \
+        Change it as soon as you can.",
+    two_fa_enabled_subject: "Two-factor authentication enabled",
+    two_fa_enabled_body: "Two-factor authentication was turned on for your account. \
+        From now on, you'll be asked for a code when you log in.",
+    two_fa_disabled_subject: "Two-factor authentication disabled",
+    two_fa_disabled_body: "Two-factor authentication was turned off for your account. \
+        You'll no longer be asked for a code when you log in.",
+    not_you: "If this wasn't you, change your password and contact us right away.",
+    footer: "You're receiving this email because you have an account with",
+    contact: "Questions? Write to us at",
+};
+
+const ES: Translations = Translations {
+    lang: "es",
+    two_fa_code_subject: "Tu código de verificación",
+    two_fa_code_intro: "Introduce este código para confirmar que eres tú:",
+    two_fa_code_ignore: "Si no has pedido un código, es posible que alguien conozca tu \
+        contraseña. Cámbiala lo antes posible.",
+    two_fa_enabled_subject: "Autenticación en dos pasos activada",
+    two_fa_enabled_body: "Se ha activado la autenticación en dos pasos en tu cuenta. \
+        A partir de ahora, te pediremos un código al iniciar sesión.",
+    two_fa_disabled_subject: "Autenticación en dos pasos desactivada",
+    two_fa_disabled_body: "Se ha desactivado la autenticación en dos pasos en tu cuenta. \
+        Ya no te pediremos un código al iniciar sesión.",
+    not_you: "Si no has sido tú, cambia tu contraseña y contacta con nosotros cuanto antes.",
+    footer: "Recibes este correo porque tienes una cuenta en",
+    contact: "¿Tienes preguntas? Escríbenos a",
+};
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+struct TwoFACodeHtml<'a> {
+    t: &'a Translations,
+    brand: &'a Branding,
+    subject: &'a str,
+    code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+struct TwoFACodeText<'a> {
+    t: &'a Translations,
+    brand: &'a Branding,
+    code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_changed.html")]
+struct TwoFAChangedHtml<'a> {
+    t: &'a Translations,
+    brand: &'a Branding,
+    subject: &'a str,
+    enabled: bool,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_changed.txt")]
+struct TwoFAChangedText<'a> {
+    t: &'a Translations,
+    brand: &'a Branding,
+    enabled: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branding() -> Branding {
+        Branding {
+            product_name: "Acme <Auth>".to_owned(),
+            primary_color: "#ff0000".to_owned(),
+            logo_url: Some("https://example.com/logo.png".to_owned()),
+            support_email: Some("help@example.com".to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_two_fa_code_email_contains_code_in_both_bodies() {
+        let code = TwoFACode::parse("123456".to_owned().into()).unwrap();
+
+        let content = EmailTemplate::TwoFACode { code: &code }
+            .render(Locale::En, &branding())
+            .unwrap();
+
+        assert_eq!(content.subject, "Your verification code");
+        assert!(content.html_body.contains("123456"));
+        assert!(content.text_body.contains("123456"));
+        assert!(!content.text_body.contains("<p>"));
+    }
+
+    #[test]
+    fn test_emails_are_branded() {
+        let content = EmailTemplate::TwoFAEnabled
+            .render(Locale::En, &branding())
+            .unwrap();
+
+        // HTML is escaped, plain text is not
+        assert!(content.html_body.contains("Acme &lt;Auth&gt;"));
+        assert!(content.html_body.contains("#ff0000"));
+        assert!(content.html_body.contains("https://example.com/logo.png"));
+        assert!(content.html_body.contains("mailto:help@example.com"));
+        assert!(content.text_body.contains("Acme <Auth>"));
+        assert!(content.text_body.contains("help@example.com"));
+    }
+
+    #[test]
+    fn test_optional_branding_is_omitted() {
+        let brand = Branding {
+            logo_url: None,
+            support_email: None,
+            ..branding()
+        };
+
+        let content = EmailTemplate::TwoFADisabled
+            .render(Locale::En, &brand)
+            .unwrap();
+
+        assert!(!content.html_body.contains("<img"));
+        assert!(!content.html_body.contains("mailto:"));
+        assert!(!content.text_body.contains(EN.contact));
+    }
+
+    #[test]
+    fn test_emails_are_localized() {
+        let enabled = EmailTemplate::TwoFAEnabled
+            .render(Locale::Es, &branding())
+            .unwrap();
+
+        assert_eq!(enabled.subject, ES.two_fa_enabled_subject);
+        assert!(enabled.html_body.contains("lang=\"es\""));
+        assert!(enabled.text_body.contains(ES.two_fa_enabled_body));
+
+        let disabled = EmailTemplate::TwoFADisabled
+            .render(Locale::Es, &branding())
+            .unwrap();
+
+        assert_eq!(disabled.subject, ES.two_fa_disabled_subject);
+        assert!(disabled.text_body.contains(ES.two_fa_disabled_body));
+    }
+
+    #[test]
+    fn test_render_preview() {
+        let content = render_preview("two-fa-code", Locale::En, &branding())
+            .unwrap()
+            .expect("should render preview");
+
+        assert!(content.text_body.contains(PREVIEW_TWO_FA_CODE));
+        assert!(render_preview("password-reset", Locale::En, &branding())
+            .unwrap()
+            .is_none());
+    }
+}
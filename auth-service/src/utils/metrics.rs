@@ -0,0 +1,235 @@
+use std::{
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+// The recorder is global, so every app in the process (e.g. in the API tests) shares it
+static PROMETHEUS: OnceLock<PrometheusHandle> = OnceLock::new();
+
+// Install the Prometheus recorder on first use. Until then, recording a metric does nothing.
+pub fn prometheus_handle() -> &'static PrometheusHandle {
+    PROMETHEUS.get_or_init(|| {
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("_seconds".to_owned()), LATENCY_BUCKETS)
+            .expect("Latency buckets must not be empty")
+            .install_recorder()
+            .expect("Failed to install Prometheus recorder");
+
+        describe_metrics();
+
+        // Drains histogram samples even when nothing scrapes `/metrics`
+        let upkeep_handle = handle.clone();
+        thread::spawn(move || loop {
+            thread::sleep(UPKEEP_INTERVAL);
+            upkeep_handle.run_upkeep();
+        });
+
+        handle
+    })
+}
+
+// What `record_http_request` labels a request with, attached to the response by
+// `label_response` since the `TraceLayer` hooks only see the response
+#[derive(Clone)]
+pub struct RequestLabels {
+    method: Method,
+    // The route template, e.g. `/sessions/{id}`, so ids don't each get their own series
+    route: Option<MatchedPath>,
+}
+
+pub async fn label_response(request: Request, next: Next) -> Response {
+    let labels = RequestLabels {
+        method: request.method().clone(),
+        route: request.extensions().get::<MatchedPath>().cloned(),
+    };
+
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(labels);
+    response
+}
+
+pub fn record_http_request(response: &Response, latency: Duration) {
+    let Some(labels) = response.extensions().get::<RequestLabels>() else {
+        return;
+    };
+
+    let method = labels.method.to_string();
+    let route = labels
+        .route
+        .as_ref()
+        .map_or("fallback", MatchedPath::as_str)
+        .to_owned();
+
+    counter!(
+        "http_requests_total",
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => response.status().as_u16().to_string()
+    )
+    .increment(1);
+
+    histogram!("http_request_duration_seconds", "method" => method, "route" => route)
+        .record(latency);
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LoginOutcome {
+    Success,
+    // The password was right, a 2FA code was sent
+    TwoFARequired,
+    Failure(LoginFailure),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LoginFailure {
+    InvalidInput,
+    IncorrectCredentials,
+    EmailUndeliverable,
+    Error,
+}
+
+impl LoginFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidInput => "invalid_input",
+            Self::IncorrectCredentials => "incorrect_credentials",
+            Self::EmailUndeliverable => "email_undeliverable",
+            Self::Error => "error",
+        }
+    }
+}
+
+pub fn record_login(outcome: LoginOutcome) {
+    match outcome {
+        LoginOutcome::Success => counter!("auth_logins_total", "outcome" => "success"),
+        LoginOutcome::TwoFARequired => counter!("auth_logins_total", "outcome" => "2fa_required"),
+        LoginOutcome::Failure(reason) => {
+            counter!("auth_logins_total", "outcome" => "failure", "reason" => reason.as_str())
+        }
+    }
+    .increment(1);
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum TwoFACodeEvent {
+    Issued,
+    Verified,
+    // Missing, expired or wrong
+    Failed,
+}
+
+pub fn record_2fa_code(event: TwoFACodeEvent) {
+    let event = match event {
+        TwoFACodeEvent::Issued => "issued",
+        TwoFACodeEvent::Verified => "verified",
+        TwoFACodeEvent::Failed => "failed",
+    };
+
+    counter!("auth_2fa_codes_total", "event" => event).increment(1);
+}
+
+pub fn record_password_hashing(operation: &'static str, duration: Duration) {
+    histogram!("auth_password_hash_duration_seconds", "operation" => operation).record(duration);
+}
+
+// Records how long a store operation took when dropped, e.g. at the end of the method
+pub struct StoreTimer {
+    backend: &'static str,
+    store: &'static str,
+    operation: &'static str,
+    started: Instant,
+}
+
+pub fn time_store(
+    backend: &'static str,
+    store: &'static str,
+    operation: &'static str,
+) -> StoreTimer {
+    StoreTimer {
+        backend,
+        store,
+        operation,
+        started: Instant::now(),
+    }
+}
+
+impl Drop for StoreTimer {
+    fn drop(&mut self) {
+        histogram!(
+            "auth_store_operation_duration_seconds",
+            "backend" => self.backend,
+            "store" => self.store,
+            "operation" => self.operation
+        )
+        .record(self.started.elapsed());
+    }
+}
+
+// How an attempt to send an email through a provider ended
+pub fn record_email_send(provider: &'static str, outcome: &'static str) {
+    counter!("auth_email_sends_total", "provider" => provider, "outcome" => outcome).increment(1);
+}
+
+// What the outbox did with a queued email after trying to send it
+pub fn record_email_delivery(outcome: &'static str) {
+    counter!("auth_email_deliveries_total", "outcome" => outcome).increment(1);
+}
+
+// What the worker did with a webhook delivery after trying to send it
+pub fn record_webhook_delivery(outcome: &'static str) {
+    counter!("auth_webhook_deliveries_total", "outcome" => outcome).increment(1);
+}
+
+fn describe_metrics() {
+    describe_counter!("http_requests_total", "HTTP requests by route and status");
+    describe_histogram!(
+        "http_request_duration_seconds",
+        Unit::Seconds,
+        "HTTP request latency by route"
+    );
+    describe_counter!(
+        "auth_logins_total",
+        "Login attempts by outcome, and reason for failures"
+    );
+    describe_counter!(
+        "auth_2fa_codes_total",
+        "2FA codes issued, verified and failed"
+    );
+    describe_histogram!(
+        "auth_password_hash_duration_seconds",
+        Unit::Seconds,
+        "Time spent hashing and verifying passwords with Argon2"
+    );
+    describe_histogram!(
+        "auth_store_operation_duration_seconds",
+        Unit::Seconds,
+        "Data store latency by backend and operation"
+    );
+    describe_counter!(
+        "auth_email_sends_total",
+        "Attempts to send an email through a provider, by outcome"
+    );
+    describe_counter!(
+        "auth_email_deliveries_total",
+        "Queued emails sent, retried, dropped or given up on"
+    );
+    describe_counter!(
+        "auth_webhook_deliveries_total",
+        "Webhook deliveries sent, retried or given up on"
+    );
+}
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
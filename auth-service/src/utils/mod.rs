@@ -1,3 +1,13 @@
+pub mod audit;
 pub mod auth;
 pub mod constants;
+pub mod cookies;
+pub mod cors;
+pub mod csrf;
+pub mod email_templates;
+pub mod metrics;
+pub mod request_id;
+pub mod settings;
+pub mod shutdown;
 pub mod tracing;
+pub mod trusted_device;
@@ -0,0 +1,83 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use super::constants::REQUEST_ID_HEADER_NAME;
+
+// Gives every request an `X-Request-Id`, the caller's if it sent a usable one, and echoes it in
+// the response so clients can quote it when reporting a problem. The request span logs it too.
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER_NAME)
+        .filter(|value| is_valid_request_id(value))
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&uuid::Uuid::new_v4().to_string())
+                .expect("A UUID is a valid header value")
+        });
+
+    let header_name = HeaderName::from_static(REQUEST_ID_HEADER_NAME);
+    request
+        .headers_mut()
+        .insert(header_name.clone(), request_id.clone());
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(header_name, request_id);
+    response
+}
+
+// The request ID of a request that went through the `request_id` middleware
+pub fn get_request_id(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+}
+
+// Anything else is replaced, so callers can't flood the logs or break their format
+fn is_valid_request_id(value: &HeaderValue) -> bool {
+    let bytes = value.as_bytes();
+
+    !bytes.is_empty()
+        && bytes.len() <= MAX_REQUEST_ID_LENGTH
+        && bytes.iter().all(u8::is_ascii_graphic)
+}
+
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_request_ids() {
+        for request_id in [
+            "7f3c2a10-5d1e-4f1b-9a55-3d6c8e2b1f00",
+            "req_01HZX3",
+            &"a".repeat(MAX_REQUEST_ID_LENGTH),
+        ] {
+            assert!(
+                is_valid_request_id(&HeaderValue::from_str(request_id).unwrap()),
+                "Failed for request ID: {request_id}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_request_ids() {
+        for request_id in ["", "two words", &"a".repeat(MAX_REQUEST_ID_LENGTH + 1)] {
+            assert!(
+                !is_valid_request_id(&HeaderValue::from_str(request_id).unwrap()),
+                "Failed for request ID: {request_id}"
+            );
+        }
+
+        assert!(!is_valid_request_id(
+            &HeaderValue::from_bytes(b"caf\xc3\xa9").unwrap()
+        ));
+    }
+}
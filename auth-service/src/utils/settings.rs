@@ -0,0 +1,774 @@
+use std::{env as std_env, path::PathBuf, time::Duration};
+
+use color_eyre::eyre::{eyre, Result};
+use config::{Config, File};
+use dotenvy::dotenv;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::{
+    domain::{Email, PhoneNumber, WebhookSubscription},
+    services::{
+        failover_email_client::CircuitBreakerSettings,
+        smtp_email_client::{SmtpCredentials, SmtpSettings, SmtpTls},
+    },
+};
+
+use super::{cookies::CookieSettings, cors::CorsSettings, email_templates::Branding};
+
+// Everything the service can be configured with, loaded once at startup from
+// `configuration/base.yaml`, the overlay for the environment and environment variables
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub application: ApplicationSettings,
+    pub cors: CorsSettings,
+    pub health: HealthSettings,
+    pub telemetry: TelemetrySettings,
+    pub auth: AuthSettings,
+    pub admin: AdminSettings,
+    pub database: DatabaseSettings,
+    pub redis: RedisSettings,
+    pub email: EmailSettings,
+    pub sms: SmsSettings,
+    pub webhooks: WebhookSettings,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApplicationSettings {
+    // Address the server listens on, e.g. 0.0.0.0:3000
+    pub address: String,
+    // Serves the `/dev/*` routes
+    pub dev_mode: bool,
+    pub dev_mailbox_dir: PathBuf,
+    // How long in-flight requests and queued work get to finish on shutdown, all together
+    pub drain_timeout_seconds: u64,
+}
+
+impl ApplicationSettings {
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout_seconds)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HealthSettings {
+    // How long each dependency gets to answer a readiness check
+    pub timeout_milliseconds: u64,
+    // Also check that the email providers can be reached, without affecting readiness
+    pub check_email_providers: bool,
+}
+
+impl HealthSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TelemetrySettings {
+    // What exported traces are attributed to
+    pub service_name: String,
+    // OTLP/HTTP collector, e.g. http://otel-collector:4318. Traces aren't exported without one.
+    pub otlp_endpoint: Option<String>,
+    pub log_format: LogFormat,
+}
+
+// How log lines are written to stdout
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // Compact, human-readable lines
+    #[default]
+    Text,
+    // One JSON object per line, for log collectors
+    Json,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuthSettings {
+    pub jwt_secret: SecretString,
+    pub remember_me_ttl_seconds: i64,
+    pub trusted_device_ttl_seconds: i64,
+    // How recent a login must be for sensitive operations
+    pub step_up_max_age_seconds: i64,
+    pub cookies: CookieSettings,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AdminSettings {
+    // Bearer token for the `/admin/*` routes and `/metrics`, which aren't served without one
+    pub api_token: SecretString,
+}
+
+impl AdminSettings {
+    pub fn is_enabled(&self) -> bool {
+        !self.api_token.expose_secret().is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DatabaseSettings {
+    pub url: SecretString,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RedisSettings {
+    pub host_name: String,
+    // How long connecting, and each command, may take. Commands block the calling thread, so
+    // without it an unresponsive Redis would hang every request and the readiness check.
+    pub timeout_milliseconds: u64,
+}
+
+impl RedisSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EmailSettings {
+    // Tried in the order listed
+    #[serde(deserialize_with = "deserialize_email_providers")]
+    pub providers: Vec<EmailProvider>,
+    #[serde(deserialize_with = "deserialize_email")]
+    pub sender: Email,
+    pub timeout_milliseconds: u64,
+    pub failure_threshold: u32,
+    pub open_duration_seconds: u64,
+    pub branding: Branding,
+    pub postmark: PostmarkSettings,
+    pub smtp: SmtpConfig,
+}
+
+impl EmailSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+
+    pub fn circuit_breaker(&self) -> CircuitBreakerSettings {
+        CircuitBreakerSettings {
+            failure_threshold: self.failure_threshold,
+            open_duration: Duration::from_secs(self.open_duration_seconds),
+        }
+    }
+
+    pub fn smtp(&self) -> SmtpSettings {
+        let credentials = match (&self.smtp.username, &self.smtp.password) {
+            (Some(username), Some(password)) => Some(SmtpCredentials {
+                username: username.clone(),
+                password: password.clone(),
+            }),
+            _ => None,
+        };
+
+        SmtpSettings {
+            host: self.smtp.host.clone(),
+            port: self.smtp.port.unwrap_or(self.smtp.tls.default_port()),
+            tls: self.smtp.tls,
+            credentials,
+            timeout: self.timeout(),
+            max_connections: self.smtp.max_connections,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PostmarkSettings {
+    pub base_url: String,
+    pub auth_token: SecretString,
+    // Postmark sends it with each webhook, see `routes::postmark_webhook`. The webhook isn't
+    // served without one.
+    pub webhook_secret: SecretString,
+}
+
+impl PostmarkSettings {
+    pub fn receives_webhooks(&self) -> bool {
+        !self.webhook_secret.expose_secret().is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    pub max_connections: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmsSettings {
+    // Codes are only emailed without one
+    #[serde(deserialize_with = "deserialize_sms_provider")]
+    pub provider: Option<SmsProvider>,
+    pub base_url: String,
+    #[serde(deserialize_with = "deserialize_phone_number")]
+    pub sender: PhoneNumber,
+    pub timeout_milliseconds: u64,
+    pub twilio: TwilioSettings,
+}
+
+impl SmsSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TwilioSettings {
+    pub account_sid: String,
+    pub auth_token: SecretString,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookSettings {
+    pub timeout_milliseconds: u64,
+    #[serde(deserialize_with = "deserialize_webhook_subscriptions")]
+    pub subscriptions: Vec<WebhookSubscription>,
+}
+
+impl WebhookSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+// A service transactional emails can be sent through
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmailProvider {
+    Postmark,
+    Smtp,
+    // Saves emails to the dev mailbox, only allowed in dev mode
+    File,
+}
+
+impl EmailProvider {
+    pub fn parse(provider: &str) -> Result<Self> {
+        match provider {
+            "postmark" => Ok(Self::Postmark),
+            "smtp" => Ok(Self::Smtp),
+            "file" => Ok(Self::File),
+            _ => Err(eyre!("{provider} is not a valid email provider")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Postmark => "postmark",
+            Self::Smtp => "smtp",
+            Self::File => "file",
+        }
+    }
+}
+
+// A service 2FA codes can be texted through
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SmsProvider {
+    Twilio,
+}
+
+impl SmsProvider {
+    pub fn parse(provider: &str) -> Result<Self> {
+        match provider {
+            "twilio" => Ok(Self::Twilio),
+            _ => Err(eyre!("{provider} is not a valid SMS provider")),
+        }
+    }
+}
+
+// The overlay file applied on top of `base.yaml`, chosen with `APP_ENVIRONMENT`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AppEnvironment {
+    Local,
+    Production,
+    Test,
+}
+
+impl AppEnvironment {
+    pub fn parse(environment: &str) -> Result<Self> {
+        match environment.to_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "production" => Ok(Self::Production),
+            "test" => Ok(Self::Test),
+            _ => Err(eyre!(
+                "{environment} is not a valid environment, use local, production or test"
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Production => "production",
+            Self::Test => "test",
+        }
+    }
+}
+
+// Every problem found while loading the settings, so they can all be fixed in one go
+#[derive(Debug, Error)]
+#[error("Invalid settings:\n  {}", .errors.join("\n  "))]
+pub struct SettingsError {
+    pub errors: Vec<String>,
+}
+
+impl Settings {
+    // Load the settings for the environment named by `APP_ENVIRONMENT`, local by default
+    pub fn load() -> Result<Self, SettingsError> {
+        dotenv().ok();
+
+        let environment = match std_env::var(APP_ENVIRONMENT_ENV_VAR) {
+            Ok(environment) => AppEnvironment::parse(&environment).map_err(|e| SettingsError {
+                errors: vec![format!("{APP_ENVIRONMENT_ENV_VAR}: {e}")],
+            })?,
+            Err(_) => AppEnvironment::Local,
+        };
+
+        Self::load_for(environment)
+    }
+
+    pub fn load_for(environment: AppEnvironment) -> Result<Self, SettingsError> {
+        dotenv().ok();
+
+        let config = build_config(environment).map_err(|e| SettingsError {
+            errors: vec![e.to_string()],
+        })?;
+
+        // Each section is read on its own, so one bad value doesn't hide the others
+        let mut errors = Vec::new();
+        let application = section(&config, "application", &mut errors);
+        let cors = section(&config, "cors", &mut errors);
+        let health = section(&config, "health", &mut errors);
+        let telemetry = section(&config, "telemetry", &mut errors);
+        let auth = section(&config, "auth", &mut errors);
+        let admin = section(&config, "admin", &mut errors);
+        let database = section(&config, "database", &mut errors);
+        let redis = section(&config, "redis", &mut errors);
+        let email = section(&config, "email", &mut errors);
+        let sms = section(&config, "sms", &mut errors);
+        let webhooks = section(&config, "webhooks", &mut errors);
+
+        let (
+            Some(application),
+            Some(cors),
+            Some(health),
+            Some(telemetry),
+            Some(auth),
+            Some(admin),
+            Some(database),
+            Some(redis),
+            Some(email),
+            Some(sms),
+            Some(webhooks),
+        ) = (
+            application,
+            cors,
+            health,
+            telemetry,
+            auth,
+            admin,
+            database,
+            redis,
+            email,
+            sms,
+            webhooks,
+        )
+        else {
+            return Err(SettingsError { errors });
+        };
+
+        let settings = Self {
+            application,
+            cors,
+            health,
+            telemetry,
+            auth,
+            admin,
+            database,
+            redis,
+            email,
+            sms,
+            webhooks,
+        };
+
+        errors.extend(settings.validate());
+
+        if !errors.is_empty() {
+            return Err(SettingsError { errors });
+        }
+
+        Ok(settings)
+    }
+
+    // Checks that span several values, or that serde can't express
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let mut require = |key: &str, value: &str| {
+            if value.is_empty() {
+                errors.push(format!("{key} must be set{}", env_var_hint(key)));
+            }
+        };
+
+        require("auth.jwt_secret", self.auth.jwt_secret.expose_secret());
+        require("database.url", self.database.url.expose_secret());
+
+        if let Some(SmsProvider::Twilio) = self.sms.provider {
+            require("sms.twilio.account_sid", &self.sms.twilio.account_sid);
+            require(
+                "sms.twilio.auth_token",
+                self.sms.twilio.auth_token.expose_secret(),
+            );
+        }
+
+        for provider in &self.email.providers {
+            match provider {
+                EmailProvider::Postmark => {
+                    require(
+                        "email.postmark.auth_token",
+                        self.email.postmark.auth_token.expose_secret(),
+                    );
+                    // Bounces are only recorded through the webhook
+                    require(
+                        "email.postmark.webhook_secret",
+                        self.email.postmark.webhook_secret.expose_secret(),
+                    );
+                }
+                EmailProvider::Smtp => require("email.smtp.host", &self.email.smtp.host),
+                EmailProvider::File => {}
+            }
+        }
+
+        if self.email.providers.is_empty() {
+            errors.push("email.providers must list at least one provider".to_owned());
+        }
+
+        if self.email.providers.contains(&EmailProvider::File) && !self.application.dev_mode {
+            errors.push("email.providers may only include file in dev mode".to_owned());
+        }
+
+        if self.email.smtp.username.is_some() != self.email.smtp.password.is_some() {
+            errors.push(
+                "email.smtp.username and email.smtp.password must be set together".to_owned(),
+            );
+        }
+
+        for (key, ttl) in [
+            (
+                "auth.remember_me_ttl_seconds",
+                self.auth.remember_me_ttl_seconds,
+            ),
+            (
+                "auth.trusted_device_ttl_seconds",
+                self.auth.trusted_device_ttl_seconds,
+            ),
+        ] {
+            if ttl <= 0 {
+                errors.push(format!("{key} must be positive"));
+            }
+        }
+
+        if self.auth.step_up_max_age_seconds < 0 {
+            errors.push("auth.step_up_max_age_seconds must not be negative".to_owned());
+        }
+
+        for (i, subscription) in self.webhooks.subscriptions.iter().enumerate() {
+            let key = format!("webhooks.subscriptions[{i}]");
+
+            let is_http = reqwest::Url::parse(&subscription.url)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !is_http {
+                errors.push(format!("{key}.url must be an http or https URL"));
+            }
+
+            if !subscription.has_secret() {
+                errors.push(format!("{key}.secret must be set"));
+            }
+
+            if subscription.events.is_empty() {
+                errors.push(format!("{key}.events must list at least one event"));
+            }
+        }
+
+        errors
+    }
+}
+
+fn build_config(environment: AppEnvironment) -> Result<Config, config::ConfigError> {
+    let dir = std_env::current_dir()
+        .map_err(|e| config::ConfigError::Foreign(e.into()))?
+        .join(CONFIGURATION_DIR);
+
+    let mut builder = Config::builder()
+        .add_source(File::from(dir.join("base.yaml")))
+        .add_source(File::from(dir.join(format!("{}.yaml", environment.as_str()))).required(false))
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__"),
+        );
+
+    // Compose passes unset variables through as empty strings, which mean "not set"
+    for &(env_var, key) in ENV_VAR_OVERRIDES {
+        let value = std_env::var(env_var).ok().filter(|v| !v.is_empty());
+        builder = builder.set_override_option(key, value)?;
+    }
+
+    builder.build()
+}
+
+fn section<T: DeserializeOwned>(config: &Config, key: &str, errors: &mut Vec<String>) -> Option<T> {
+    config
+        .get(key)
+        .map_err(|e| errors.push(format!("{key}: {e}")))
+        .ok()
+}
+
+fn env_var_hint(key: &str) -> String {
+    ENV_VAR_OVERRIDES
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map(|(env_var, _)| format!(" ({env_var})"))
+        .unwrap_or_default()
+}
+
+// A comma-separated list, e.g. "postmark,smtp"
+fn deserialize_email_providers<'de, D>(deserializer: D) -> Result<Vec<EmailProvider>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .split(',')
+        .map(str::trim)
+        .filter(|provider| !provider.is_empty())
+        .map(|provider| EmailProvider::parse(provider).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn deserialize_sms_provider<'de, D>(deserializer: D) -> Result<Option<SmsProvider>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)?
+        .as_deref()
+        .map(str::trim)
+    {
+        None | Some("") => Ok(None),
+        Some(provider) => SmsProvider::parse(provider)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+// A list, or JSON for setting it with an environment variable, e.g.
+// [{"url": "https://crm.example.com/hooks", "secret": "...", "events": ["user.created"]}]
+fn deserialize_webhook_subscriptions<'de, D>(
+    deserializer: D,
+) -> Result<Vec<WebhookSubscription>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(json) => serde_json::from_str(&json),
+        value => serde_json::from_value(value),
+    }
+    .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_email<'de, D>(deserializer: D) -> Result<Email, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Email::parse(String::deserialize(deserializer)?.into()).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_phone_number<'de, D>(deserializer: D) -> Result<PhoneNumber, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    PhoneNumber::parse(String::deserialize(deserializer)?.into()).map_err(serde::de::Error::custom)
+}
+
+const CONFIGURATION_DIR: &str = "configuration";
+const APP_ENVIRONMENT_ENV_VAR: &str = "APP_ENVIRONMENT";
+
+// Environment variables the service has always been configured with, and the settings they set
+const ENV_VAR_OVERRIDES: &[(&str, &str)] = &[
+    ("DEV_MODE", "application.dev_mode"),
+    ("DEV_MAILBOX_DIR", "application.dev_mailbox_dir"),
+    ("DRAIN_TIMEOUT_SECONDS", "application.drain_timeout_seconds"),
+    ("CORS_ALLOWED_ORIGINS", "cors.allowed_origins"),
+    ("CORS_ALLOWED_METHODS", "cors.allowed_methods"),
+    ("CORS_ALLOWED_HEADERS", "cors.allowed_headers"),
+    ("CORS_MAX_AGE_SECONDS", "cors.max_age_seconds"),
+    (
+        "HEALTH_CHECK_TIMEOUT_MILLISECONDS",
+        "health.timeout_milliseconds",
+    ),
+    (
+        "HEALTH_CHECK_EMAIL_PROVIDERS",
+        "health.check_email_providers",
+    ),
+    ("OTEL_SERVICE_NAME", "telemetry.service_name"),
+    ("OTEL_EXPORTER_OTLP_ENDPOINT", "telemetry.otlp_endpoint"),
+    ("LOG_FORMAT", "telemetry.log_format"),
+    ("JWT_SECRET", "auth.jwt_secret"),
+    ("REMEMBER_ME_TTL_SECONDS", "auth.remember_me_ttl_seconds"),
+    (
+        "TRUSTED_DEVICE_TTL_SECONDS",
+        "auth.trusted_device_ttl_seconds",
+    ),
+    ("STEP_UP_MAX_AGE_SECONDS", "auth.step_up_max_age_seconds"),
+    ("AUTH_COOKIE_SECURE", "auth.cookies.secure"),
+    ("AUTH_COOKIE_DOMAIN", "auth.cookies.domain"),
+    ("AUTH_COOKIE_SAME_SITE", "auth.cookies.same_site"),
+    ("AUTH_COOKIE_HOST_PREFIX", "auth.cookies.host_prefix"),
+    ("ADMIN_API_TOKEN", "admin.api_token"),
+    ("DATABASE_URL", "database.url"),
+    ("REDIS_HOST_NAME", "redis.host_name"),
+    ("EMAIL_PROVIDERS", "email.providers"),
+    ("EMAIL_BRAND_NAME", "email.branding.product_name"),
+    ("EMAIL_BRAND_COLOR", "email.branding.primary_color"),
+    ("EMAIL_LOGO_URL", "email.branding.logo_url"),
+    ("EMAIL_SUPPORT_ADDRESS", "email.branding.support_email"),
+    ("POSTMARK_AUTH_TOKEN", "email.postmark.auth_token"),
+    ("POSTMARK_WEBHOOK_SECRET", "email.postmark.webhook_secret"),
+    ("SMTP_HOST", "email.smtp.host"),
+    ("SMTP_PORT", "email.smtp.port"),
+    ("SMTP_TLS", "email.smtp.tls"),
+    ("SMTP_USERNAME", "email.smtp.username"),
+    ("SMTP_PASSWORD", "email.smtp.password"),
+    ("SMS_PROVIDER", "sms.provider"),
+    ("TWILIO_ACCOUNT_SID", "sms.twilio.account_sid"),
+    ("TWILIO_AUTH_TOKEN", "sms.twilio.auth_token"),
+    ("WEBHOOK_SUBSCRIPTIONS", "webhooks.subscriptions"),
+];
+
+#[cfg(test)]
+impl Settings {
+    // The test environment's settings, for unit tests
+    pub fn test() -> Self {
+        Self::load_for(AppEnvironment::Test).expect("Test settings must be valid.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::WebhookEventKind;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        let mut settings = Settings::test();
+        settings.auth.jwt_secret = SecretString::default();
+        settings.auth.remember_me_ttl_seconds = 0;
+        settings.email.providers = vec![EmailProvider::File];
+
+        let errors = settings.validate();
+
+        assert_eq!(
+            errors,
+            vec![
+                "auth.jwt_secret must be set (JWT_SECRET)",
+                "email.providers may only include file in dev mode",
+                "auth.remember_me_ttl_seconds must be positive",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_requires_settings_of_used_providers_only() {
+        let mut settings = Settings::test();
+        settings.email.postmark.auth_token = SecretString::default();
+        settings.email.postmark.webhook_secret = SecretString::default();
+        settings.email.providers = vec![EmailProvider::Smtp];
+
+        assert_eq!(
+            settings.validate(),
+            vec!["email.smtp.host must be set (SMTP_HOST)"]
+        );
+
+        settings.email.smtp.host = "smtp.example.com".to_owned();
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_requires_twilio_settings_with_sms_only() {
+        let mut settings = Settings::test();
+        settings.sms.twilio.account_sid = String::new();
+        settings.sms.twilio.auth_token = SecretString::default();
+
+        assert_eq!(
+            settings.validate(),
+            vec![
+                "sms.twilio.account_sid must be set (TWILIO_ACCOUNT_SID)",
+                "sms.twilio.auth_token must be set (TWILIO_AUTH_TOKEN)",
+            ]
+        );
+
+        settings.sms.provider = None;
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_webhook_subscriptions() {
+        let mut settings = Settings::test();
+        settings.webhooks.subscriptions = vec![WebhookSubscription {
+            url: "ftp://crm.example.com".to_owned(),
+            secret: SecretString::default(),
+            events: Vec::new(),
+        }];
+
+        assert_eq!(
+            settings.validate(),
+            vec![
+                "webhooks.subscriptions[0].url must be an http or https URL",
+                "webhooks.subscriptions[0].secret must be set",
+                "webhooks.subscriptions[0].events must list at least one event",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_webhook_subscriptions_can_be_json() {
+        let subscriptions = r#"[{
+            "url": "https://crm.example.com/hooks",
+            "secret": "secret",
+            "events": ["user.created", "user.login"]
+        }]"#;
+
+        let settings: WebhookSettings = serde_json::from_value(serde_json::json!({
+            "timeout_milliseconds": 5000,
+            "subscriptions": subscriptions,
+        }))
+        .unwrap();
+
+        assert_eq!(settings.subscriptions.len(), 1);
+        assert_eq!(
+            settings.subscriptions[0].events,
+            [WebhookEventKind::UserCreated, WebhookEventKind::UserLogin]
+        );
+
+        let invalid = serde_json::json!({
+            "timeout_milliseconds": 5000,
+            "subscriptions": r#"[{"url": "https://crm.example.com", "secret": "secret", "events": ["user.deleted"]}]"#,
+        });
+        assert!(serde_json::from_value::<WebhookSettings>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_app_environment() {
+        assert_eq!(
+            AppEnvironment::parse("Production").unwrap(),
+            AppEnvironment::Production
+        );
+        assert!(AppEnvironment::parse("staging").is_err());
+    }
+}
@@ -1,43 +1,188 @@
-use std::time::Duration;
+use std::{collections::HashMap, error::Error, time::Duration};
 
-use axum::{body::Body, extract::Request, response::Response};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::Response,
+};
 use color_eyre::eyre::Result;
+use opentelemetry::{
+    propagation::TextMapPropagator,
+    trace::{TraceContextExt, TracerProvider},
+    Context,
+};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
 use tracing::{Level, Span};
 use tracing_error::ErrorLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-pub fn init_tracing() -> Result<()> {
-    // Create a formatting layer for tracing output with a compact format
-    let fmt_layer = fmt::layer().compact();
+use super::{
+    metrics::record_http_request,
+    request_id::get_request_id,
+    settings::{LogFormat, TelemetrySettings},
+};
+
+// Keeps the tracer provider around, so spans still buffered can be exported on shutdown
+pub struct TracingGuard {
+    tracer_provider: SdkTracerProvider,
+}
+
+impl TracingGuard {
+    // Export the spans still buffered. Call last, after everything that traces has stopped.
+    pub fn shutdown(self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to export remaining spans: {e}");
+        }
+    }
+}
+
+pub fn init_tracing(settings: &TelemetrySettings) -> Result<TracingGuard> {
+    // Create a formatting layer for tracing output, either compact text or JSON lines.
+    // JSON events carry the fields of the spans they're in, e.g. the request ID.
+    let (text_layer, json_layer) = match settings.log_format {
+        LogFormat::Text => (Some(fmt::layer().compact()), None),
+        LogFormat::Json => (
+            None,
+            Some(
+                fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(false)
+                    .with_span_list(true),
+            ),
+        ),
+    };
 
     // Create a filter layer to control the verbosity of logs
     // Try to get the filter configuration from the environment variables
     // If it fails, default to the "info" log level
     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
 
+    let tracer_provider = build_tracer_provider(settings)?;
+
+    // Gives every span an OpenTelemetry trace and span id, exported if a collector is set
+    let otel_layer = tracing_opentelemetry::layer()
+        .with_tracer(tracer_provider.tracer(settings.service_name.clone()));
+
     // Build the tracing subscriber registry with the formatting layer,
     // the filter layer, and the error layer for enhanced error reporting
     tracing_subscriber::registry()
         .with(filter_layer) // Add the filter layer to control log verbosity
-        .with(fmt_layer) // Add the formatting layer for compact log output
+        .with(text_layer) // Add the formatting layer for compact log output...
+        .with(json_layer) // ...or the one for JSON log output
+        .with(otel_layer) // Add the OpenTelemetry layer for distributed tracing
         .with(ErrorLayer::default()) // Add the error layer to capture error contexts
         .init(); // Initialize the tracing subscriber
 
-    Ok(())
+    Ok(TracingGuard { tracer_provider })
+}
+
+// Batches spans to the OTLP collector, if one is configured
+pub fn build_tracer_provider(settings: &TelemetrySettings) -> Result<SdkTracerProvider> {
+    let resource = Resource::builder()
+        .with_service_name(settings.service_name.clone())
+        .build();
+
+    let mut builder = SdkTracerProvider::builder().with_resource(resource);
+
+    if let Some(endpoint) = &settings.otlp_endpoint {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+
+        builder = builder.with_batch_exporter(exporter);
+    }
+
+    Ok(builder.build())
 }
 
-// Creates a new tracing span with a unique request ID for each incoming request.
+// The trace context of the current span as W3C `traceparent` headers, for outgoing requests
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    TraceContextPropagator::new().inject_context(
+        &Span::current().context(),
+        &mut HeaderInjector(&mut headers),
+    );
+    headers
+}
+
+// The current span's `traceparent`, e.g. to store with work that continues the trace later
+pub fn current_traceparent() -> Option<String> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&Span::current().context(), &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+// The trace a `traceparent` belongs to, which an invalid or missing one doesn't continue
+pub fn trace_context_from(traceparent: Option<&str>) -> Context {
+    let mut headers = HeaderMap::new();
+
+    if let Some(value) = traceparent.and_then(|t| HeaderValue::from_str(t).ok()) {
+        headers.insert(HeaderName::from_static(TRACEPARENT), value);
+    }
+
+    TraceContextPropagator::new().extract(&HeaderExtractor(&headers))
+}
+
+// Creates a new tracing span with the request's ID, see `request_id`, for each incoming request.
 // This helps in tracking and correlating logs for individual requests.
+// A request carrying a `traceparent` header continues that trace, and the span logs the trace ID
+// too, so logs line up with the caller's.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
-    tracing::span!(
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
         uri = tracing::field::display(request.uri()),
         version = tracing::field::debug(request.version()),
-        request_id = tracing::field::display(request_id),
-    )
+        request_id = get_request_id(request),
+        trace_id = tracing::field::Empty,
+    );
+
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+
+    // Fails when the OpenTelemetry layer isn't installed
+    let _ = span.set_parent(parent);
+
+    let span_context = span.context().span().span_context().clone();
+    if span_context.is_valid() {
+        span.record("trace_id", tracing::field::display(span_context.trace_id()));
+    }
+
+    span
+}
+
+// Logs an error with each error in its chain of sources as its own field, `error.source.0` being
+// the direct cause, so log collectors can index them. Tracing fields are named at compile time,
+// so sources past the eighth are only counted in `error.source_count`.
+pub fn log_error_chain(e: &(dyn Error + 'static)) {
+    let sources = error_sources(e);
+    let source = |i: usize| sources.get(i).map(String::as_str);
+
+    tracing::error!(
+        error.message = %e,
+        error.source_count = sources.len(),
+        "error.source.0" = source(0),
+        "error.source.1" = source(1),
+        "error.source.2" = source(2),
+        "error.source.3" = source(3),
+        "error.source.4" = source(4),
+        "error.source.5" = source(5),
+        "error.source.6" = source(6),
+        "error.source.7" = source(7),
+        "{e}"
+    );
+}
+
+fn error_sources(e: &(dyn Error + 'static)) -> Vec<String> {
+    std::iter::successors(e.source(), |&cause| cause.source())
+        .map(ToString::to_string)
+        .collect()
 }
 
 // Logs an event indicating the start of a request.
@@ -47,7 +192,10 @@ pub fn on_request(_request: &Request<Body>, _span: &Span) {
 
 // Logs an event indicating the end of a request, including its latency and status code.
 // If the status code indicates an error (4xx or 5xx), it logs at the ERROR level.
+// Also counts the request and records its latency for `/metrics`.
 pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
+    record_http_request(response, latency);
+
     let status = response.status();
     let status_code = status.as_u16();
     let status_code_class = status_code / 100;
@@ -71,3 +219,113 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
         }
     };
 }
+
+const TRACEPARENT: &str = "traceparent";
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use color_eyre::eyre::eyre;
+    use opentelemetry::trace::Tracer;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::domain::AuthAPIError;
+
+    use super::*;
+
+    #[test]
+    fn test_trace_context_from_traceparent() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let cx = trace_context_from(Some(traceparent));
+
+        assert_eq!(
+            cx.span().span_context().trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+
+        for traceparent in [None, Some("not-a-traceparent")] {
+            assert!(!trace_context_from(traceparent)
+                .span()
+                .span_context()
+                .is_valid());
+        }
+    }
+
+    #[test]
+    fn test_error_sources() {
+        let report = eyre!("connection refused").wrap_err("Failed to add user");
+        let e = AuthAPIError::UnexpectedError(report);
+
+        assert_eq!(
+            error_sources(&e),
+            ["Failed to add user", "connection refused"]
+        );
+        assert!(error_sources(&AuthAPIError::InvalidToken).is_empty());
+    }
+
+    #[test]
+    fn test_log_error_chain_logs_each_source_as_a_field() {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_writer(move || LogWriter(writer.clone()))
+            .finish();
+
+        let report = eyre!("connection refused").wrap_err("Failed to add user");
+        tracing::subscriber::with_default(subscriber, || {
+            log_error_chain(&AuthAPIError::UnexpectedError(report));
+        });
+
+        let logs = logs.lock().unwrap();
+        let event: serde_json::Value = serde_json::from_slice(&logs).unwrap();
+
+        assert_eq!(event["error.source_count"], 2);
+        assert_eq!(event["error.source.0"], "Failed to add user");
+        assert_eq!(event["error.source.1"], "connection refused");
+        assert!(event.get("error.source.2").is_none());
+    }
+
+    struct LogWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn should_export_spans_to_the_otlp_endpoint_on_shutdown() {
+        let collector = MockServer::start().await;
+
+        Mock::given(path("/v1/traces"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&collector)
+            .await;
+
+        let tracer_provider = build_tracer_provider(&TelemetrySettings {
+            service_name: "auth-service".to_owned(),
+            otlp_endpoint: Some(format!("{}/", collector.uri())),
+            log_format: LogFormat::Text,
+        })
+        .unwrap();
+
+        tracer_provider.tracer("test").in_span("Test span", |_| {});
+
+        // Exporting blocks until the collector answers
+        tokio::task::spawn_blocking(move || TracingGuard { tracer_provider }.shutdown())
+            .await
+            .unwrap();
+    }
+}
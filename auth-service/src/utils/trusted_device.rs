@@ -0,0 +1,249 @@
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::TrustedDeviceStoreType,
+    domain::{DeviceId, Email, TrustedDevice, TrustedDeviceStore},
+};
+
+use super::{constants::TRUSTED_DEVICE_COOKIE_NAME, settings::AuthSettings};
+
+// Create a cookie holding a signed token that binds the trusted device to the user.
+// The cookie lasts as long as the device stays trusted.
+#[tracing::instrument(name = "Generate trusted device cookie", skip_all)]
+pub fn generate_trusted_device_cookie(
+    device: &TrustedDevice,
+    settings: &AuthSettings,
+) -> Result<Cookie<'static>> {
+    let exp = device.expires_at.timestamp();
+
+    let exp: usize = exp
+        .try_into()
+        .wrap_err_with(|| eyre!("failed to cast exp time to usize. exp time: {}", exp))?;
+
+    let claims = TrustedDeviceClaims {
+        sub: device.email.as_ref().expose_secret().to_owned(),
+        did: device.id.as_ref().expose_secret().to_owned(),
+        aud: TRUSTED_DEVICE_AUDIENCE.to_owned(),
+        exp,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
+    )
+    .wrap_err("failed to create trusted device token")?;
+
+    let max_age = time::Duration::seconds((device.expires_at - device.created_at).num_seconds());
+
+    Ok(settings.cookies.build(
+        TRUSTED_DEVICE_COOKIE_NAME,
+        token,
+        Some(max_age),
+        true, // prevent JavaScript from accessing the cookie
+    ))
+}
+
+// Create a cookie that clears the trusted device cookie, matching the attributes it was set with
+pub fn remove_trusted_device_cookie(settings: &AuthSettings) -> Cookie<'static> {
+    settings.cookies.removal(TRUSTED_DEVICE_COOKIE_NAME)
+}
+
+// Read the device id from the trusted device cookie, if it carries a validly signed token.
+// This doesn't check whether the device has since been revoked.
+pub fn get_trusted_device_id(jar: &CookieJar, settings: &AuthSettings) -> Result<DeviceId> {
+    let claims = decode_trusted_device_cookie(jar, settings)?;
+    DeviceId::parse(claims.did.into())
+}
+
+// Check that the request comes from a device the user has trusted and not revoked since
+#[tracing::instrument(name = "Validate trusted device", skip_all)]
+pub async fn validate_trusted_device<TrustedDeviceStoreImpl>(
+    jar: &CookieJar,
+    email: &Email,
+    trusted_device_store: &TrustedDeviceStoreType<TrustedDeviceStoreImpl>,
+    settings: &AuthSettings,
+) -> Result<TrustedDevice>
+where
+    TrustedDeviceStoreImpl: TrustedDeviceStore,
+{
+    let claims = decode_trusted_device_cookie(jar, settings)?;
+
+    if claims.sub != *email.as_ref().expose_secret() {
+        return Err(eyre!("trusted device belongs to another user"));
+    }
+
+    let device_id = DeviceId::parse(claims.did.into())?;
+    let lock = trusted_device_store.read().await;
+
+    let device = lock
+        .get_device(&device_id)
+        .await
+        .wrap_err("trusted device has been revoked")?;
+
+    drop(lock);
+
+    if device.email != *email {
+        return Err(eyre!("trusted device belongs to another user"));
+    }
+
+    Ok(device)
+}
+
+fn decode_trusted_device_cookie(
+    jar: &CookieJar,
+    settings: &AuthSettings,
+) -> Result<TrustedDeviceClaims> {
+    let cookie = jar
+        .get(&settings.cookies.name(TRUSTED_DEVICE_COOKIE_NAME))
+        .wrap_err("missing trusted device cookie")?;
+
+    // The audience keeps device tokens and auth tokens, signed with the same secret, apart
+    let mut validation = Validation::default();
+    validation.set_audience(&[TRUSTED_DEVICE_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "sub", "aud"]);
+
+    decode::<TrustedDeviceClaims>(
+        cookie.value(),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode trusted device token")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrustedDeviceClaims {
+    sub: String,
+    did: String,
+    aud: String,
+    exp: usize,
+}
+
+const TRUSTED_DEVICE_AUDIENCE: &str = "trusted-device";
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{
+        services::data_stores::{
+            HashmapSessionStore, HashmapTrustedDeviceStore, HashsetBannedTokenStore,
+        },
+        utils::{auth::validate_token, settings::Settings},
+    };
+
+    fn auth_settings() -> AuthSettings {
+        Settings::test().auth
+    }
+
+    fn new_example_device(email: &str) -> TrustedDevice {
+        TrustedDevice::new(
+            Email::parse(email.to_owned().into()).unwrap(),
+            chrono::Duration::days(30),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_validate_trusted_device() {
+        let device = new_example_device("test@example.com");
+        let cookie = generate_trusted_device_cookie(&device, &auth_settings()).unwrap();
+        assert_eq!(cookie.name(), TRUSTED_DEVICE_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.max_age(), Some(time::Duration::days(30)));
+
+        let jar = CookieJar::new().add(cookie);
+        let store = Arc::new(RwLock::new(HashmapTrustedDeviceStore::default()));
+
+        // Not trusted until the device is stored
+        assert!(
+            validate_trusted_device(&jar, &device.email, &store, &auth_settings())
+                .await
+                .is_err()
+        );
+
+        store
+            .write()
+            .await
+            .add_device(device.clone())
+            .await
+            .unwrap();
+
+        let actual = validate_trusted_device(&jar, &device.email, &store, &auth_settings())
+            .await
+            .unwrap();
+
+        assert_eq!(actual, device);
+        assert_eq!(
+            get_trusted_device_id(&jar, &auth_settings()).unwrap(),
+            device.id
+        );
+
+        let other_email = Email::parse("other@example.com".to_owned().into()).unwrap();
+
+        assert!(
+            validate_trusted_device(&jar, &other_email, &store, &auth_settings())
+                .await
+                .is_err()
+        );
+
+        store.write().await.remove_device(&device.id).await.unwrap();
+
+        assert!(
+            validate_trusted_device(&jar, &device.email, &store, &auth_settings())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_trusted_device_with_invalid_cookie() {
+        let device = new_example_device("test@example.com");
+        let store = Arc::new(RwLock::new(HashmapTrustedDeviceStore::default()));
+        store
+            .write()
+            .await
+            .add_device(device.clone())
+            .await
+            .unwrap();
+
+        let jar = CookieJar::new();
+
+        assert!(
+            validate_trusted_device(&jar, &device.email, &store, &auth_settings())
+                .await
+                .is_err()
+        );
+
+        let jar = jar.add(Cookie::new(TRUSTED_DEVICE_COOKIE_NAME, "invalid_token"));
+
+        assert!(
+            validate_trusted_device(&jar, &device.email, &store, &auth_settings())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trusted_device_token_is_not_an_auth_token() {
+        let device = new_example_device("test@example.com");
+        let cookie = generate_trusted_device_cookie(&device, &auth_settings()).unwrap();
+
+        let result = validate_token::<HashsetBannedTokenStore, HashmapSessionStore>(
+            &cookie.value().to_owned().into(),
+            &Default::default(),
+            &Default::default(),
+            &auth_settings(),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use auth_service::{
+    domain::{AuditEvent, AuditEventFilter, AuditEventKind, AuditSink, Email},
+    routes::{AuditEventsResponse, BearerAuthResponse},
+    services::data_stores::PostgresAuditSink,
+    ErrorResponse,
+};
+use test_context::test_context;
+
+use crate::helpers::{get_random_email, test_settings, TestApp};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+const REQUEST_ID: &str = "0b6f7c1e-3a52-4d8e-9f10-2c4b5a6d7e8f";
+
+async fn admin_app() -> TestApp {
+    let mut settings = test_settings();
+    settings.admin.api_token = ADMIN_TOKEN.to_owned().into();
+
+    TestApp::with_settings(settings).await
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+}
+
+async fn events_for(app: &TestApp, email: &str) -> Vec<AuditEvent> {
+    let filter = AuditEventFilter {
+        email: Some(Email::parse(email.to_owned().into()).unwrap()),
+        limit: 100,
+        ..Default::default()
+    };
+
+    app.audit_sink.read().await.query(&filter).await.unwrap()
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_record_logins_with_the_request_context(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    for password in ["wrong-password", "password123"] {
+        let login_body = serde_json::json!({
+            "email": random_email,
+            "password": password,
+        });
+
+        app.http_client
+            .post(format!("{}/login", app.address))
+            .header("User-Agent", "audit-test/1.0")
+            .header("X-Request-Id", REQUEST_ID)
+            .json(&login_body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+    }
+
+    let events = events_for(app, &random_email).await;
+    let kinds = events.iter().map(|event| event.kind).collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        [
+            AuditEventKind::LoginSucceeded,
+            AuditEventKind::LoginFailed,
+            AuditEventKind::Signup
+        ]
+    );
+
+    let failure = &events[1];
+    assert_eq!(failure.reason.as_deref(), Some("incorrect_credentials"));
+    assert_eq!(failure.ip_address, Some([127, 0, 0, 1].into()));
+    assert_eq!(failure.user_agent.as_deref(), Some("audit-test/1.0"));
+    assert_eq!(failure.request_id.as_deref(), Some(REQUEST_ID));
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_record_logout(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_login(&login_body).await;
+    let token = response.json::<BearerAuthResponse>().await.unwrap().token;
+
+    assert_eq!(
+        app.post_logout_with_bearer(&token).await.status().as_u16(),
+        200
+    );
+
+    let events = events_for(app, &random_email).await;
+    assert_eq!(events[0].kind, AuditEventKind::Logout);
+}
+
+#[tokio::test]
+async fn should_not_serve_audit_events_without_an_admin_token_configured() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_audit_events(&[], None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_requests_without_the_admin_token() {
+    let mut app = admin_app().await;
+
+    let response = app.get_audit_events(&[], None).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_audit_events(&[], Some("wrong-token")).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Invalid auth token"
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_filter_audit_events() {
+    let mut app = admin_app().await;
+
+    let random_email = get_random_email();
+    let other_email = get_random_email();
+    signup(&app, &random_email).await;
+    signup(&app, &other_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    app.post_login(&login_body).await;
+
+    let response = app
+        .get_audit_events(&[("email", &random_email)], Some(ADMIN_TOKEN))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response.json::<AuditEventsResponse>().await.unwrap().events;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].kind, "login_succeeded");
+    assert_eq!(events[0].ip_address.as_deref(), Some("127.0.0.1"));
+    assert!(events[0].request_id.is_some());
+    assert!(events
+        .iter()
+        .all(|event| event.email.as_deref() == Some(random_email.as_str())));
+
+    let response = app
+        .get_audit_events(&[("kind", "signup"), ("limit", "1")], Some(ADMIN_TOKEN))
+        .await;
+
+    let events = response.json::<AuditEventsResponse>().await.unwrap().events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "signup");
+    assert_eq!(events[0].email.as_deref(), Some(other_email.as_str()));
+
+    let response = app
+        .get_audit_events(&[("since", "2999-01-01T00:00:00Z")], Some(ADMIN_TOKEN))
+        .await;
+
+    let events = response.json::<AuditEventsResponse>().await.unwrap().events;
+    assert!(events.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_query() {
+    let mut app = admin_app().await;
+
+    let test_cases = [
+        ("limit", "0"),
+        ("limit", "1001"),
+        ("limit", "many"),
+        ("kind", "unknown"),
+        ("email", "not-an-email"),
+        ("since", "yesterday"),
+    ];
+
+    for (key, value) in test_cases {
+        let response = app
+            .get_audit_events(&[(key, value)], Some(ADMIN_TOKEN))
+            .await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for query: {key}={value}"
+        );
+        assert_eq!(
+            response.json::<ErrorResponse>().await.unwrap().error,
+            "Invalid audit event query"
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_store_audit_events_in_postgres(app: &mut TestApp) {
+    let mut audit_sink = PostgresAuditSink::new(app.pg_pool.clone());
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let event = AuditEvent {
+        reason: Some("incorrect_credentials".to_owned()),
+        ip_address: Some([203, 0, 113, 7].into()),
+        user_agent: Some("audit-test/1.0".to_owned()),
+        request_id: Some(REQUEST_ID.to_owned()),
+        ..AuditEvent::new(AuditEventKind::LoginFailed, Some(email.clone()))
+    };
+
+    audit_sink.record(event.clone()).await.unwrap();
+    audit_sink
+        .record(AuditEvent::new(AuditEventKind::Signup, None))
+        .await
+        .unwrap();
+
+    let filter = AuditEventFilter {
+        email: Some(email.clone()),
+        kind: Some(AuditEventKind::LoginFailed),
+        limit: 10,
+        ..Default::default()
+    };
+
+    let events = audit_sink.query(&filter).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id, event.id);
+    assert_eq!(events[0].email, Some(email));
+    assert_eq!(events[0].reason, event.reason);
+    assert_eq!(events[0].ip_address, event.ip_address);
+    assert_eq!(events[0].user_agent, event.user_agent);
+    assert_eq!(events[0].request_id, event.request_id);
+
+    // The log is append-only
+    let updated = sqlx::query("UPDATE audit_events SET reason = NULL")
+        .execute(&app.pg_pool)
+        .await;
+    assert!(updated.is_err());
+
+    let deleted = sqlx::query("DELETE FROM audit_events")
+        .execute(&app.pg_pool)
+        .await;
+    assert!(deleted.is_err());
+}
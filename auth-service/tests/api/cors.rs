@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use auth_service::utils::cors::{CorsSettings, OriginPattern};
+use axum::http::{HeaderName, Method};
+use reqwest::Response;
+use test_context::test_context;
+
+use crate::helpers::{test_settings, TestApp};
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+async fn app_with_cors(allowed_origins: &[&str], methods: Vec<Method>, max_age: u64) -> TestApp {
+    let mut settings = test_settings();
+
+    settings.cors = CorsSettings::new(
+        allowed_origins
+            .iter()
+            .map(|origin| OriginPattern::parse(origin).unwrap())
+            .collect(),
+        methods,
+        vec![
+            HeaderName::from_static("content-type"),
+            HeaderName::from_static("x-csrf-token"),
+        ],
+        Duration::from_secs(max_age),
+    );
+
+    TestApp::with_settings(settings).await
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_allow_preflight_from_allowed_origin(app: &mut TestApp) {
+    let response = app
+        .preflight("/login", "http://localhost:8000", "POST")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("http://localhost:8000")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-credentials"),
+        Some("true")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-methods"),
+        Some("GET,POST,PUT,DELETE")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-headers"),
+        Some("authorization,content-type,x-csrf-token,x-request-id")
+    );
+    assert_eq!(header(&response, "access-control-max-age"), Some("600"));
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_not_allow_preflight_from_other_origins(app: &mut TestApp) {
+    for origin in [
+        "http://evil.com",
+        "http://localhost:8001",
+        "https://localhost:8000",
+        "http://localhost:8000.evil.com",
+        "null",
+    ] {
+        let response = app.preflight("/login", origin, "POST").await;
+
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            None,
+            "Failed for origin: {origin}"
+        );
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_only_allow_actual_requests_from_allowed_origin(app: &mut TestApp) {
+    let body = serde_json::json!({});
+
+    for (origin, allowed) in [("http://localhost:8000", true), ("http://evil.com", false)] {
+        let response = app
+            .http_client
+            .post(format!("{}/verify-token", app.address))
+            .header("Origin", origin)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            allowed.then_some(origin),
+            "Failed for origin: {origin}"
+        );
+        if allowed {
+            assert_eq!(
+                header(&response, "access-control-expose-headers"),
+                Some("x-request-id")
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn should_allow_preflight_from_wildcard_subdomains() {
+    let mut app = app_with_cors(&["https://*.example.com"], vec![Method::POST], 600).await;
+
+    for (origin, allowed) in [
+        ("https://app.example.com", true),
+        ("https://eu.app.example.com", true),
+        ("https://example.com", false),
+        ("https://evilexample.com", false),
+        ("https://app.example.com.evil.com", false),
+        ("http://app.example.com", false),
+    ] {
+        let response = app.preflight("/login", origin, "POST").await;
+
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            allowed.then_some(origin),
+            "Failed for origin: {origin}"
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_use_configured_methods_headers_and_max_age() {
+    let mut app = app_with_cors(&["http://localhost:8000"], vec![Method::GET], 60).await;
+
+    let response = app
+        .preflight("/sessions", "http://localhost:8000", "GET")
+        .await;
+
+    assert_eq!(
+        header(&response, "access-control-allow-methods"),
+        Some("GET")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-headers"),
+        Some("content-type,x-csrf-token")
+    );
+    assert_eq!(header(&response, "access-control-max-age"), Some("60"));
+
+    app.clean_up().await;
+}
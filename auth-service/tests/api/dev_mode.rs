@@ -0,0 +1,40 @@
+use crate::helpers::{test_settings, TestApp};
+
+#[tokio::test]
+async fn should_not_serve_dev_routes_outside_dev_mode() {
+    let mut app = TestApp::new().await;
+
+    for path in ["/dev/emails/two-fa-code", "/dev/mailbox"] {
+        let response = app
+            .http_client
+            .get(format!("{}{}", app.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert_eq!(response.status().as_u16(), 404, "Failed for path: {path}");
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_preview_emails_with_configured_branding_in_dev_mode() {
+    let mut settings = test_settings();
+    settings.application.dev_mode = true;
+    settings.email.branding.product_name = "Acme Auth".to_owned();
+
+    let mut app = TestApp::with_settings(settings).await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/dev/emails/two-fa-code", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Acme Auth"));
+
+    app.clean_up().await;
+}
@@ -0,0 +1,92 @@
+use auth_service::{
+    domain::HealthStatus, routes::ReadinessResponse, utils::settings::EmailProvider,
+};
+
+use crate::helpers::{test_settings, TestApp};
+
+#[tokio::test]
+async fn should_return_200_when_live() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health_live().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({ "status": "live" })
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_when_dependencies_are_up() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health_ready().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.status, "ready");
+
+    let names: Vec<_> = body.checks.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["postgres", "redis"]);
+    assert!(body
+        .checks
+        .iter()
+        .all(|c| c.status == HealthStatus::Up && c.critical && c.error.is_none()));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_503_when_postgres_is_down() {
+    let mut app = TestApp::new().await;
+    app.pg_pool.close().await;
+
+    let response = app.get_health_ready().await;
+    assert_eq!(response.status().as_u16(), 503);
+
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.status, "not_ready");
+
+    let postgres = body.checks.iter().find(|c| c.name == "postgres").unwrap();
+    assert_eq!(postgres.status, HealthStatus::Down);
+    assert!(postgres.error.is_some());
+
+    let redis = body.checks.iter().find(|c| c.name == "redis").unwrap();
+    assert_eq!(redis.status, HealthStatus::Up);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_report_email_providers_without_failing_readiness() {
+    let mut settings = test_settings();
+    settings.health.check_email_providers = true;
+    settings.email.providers = vec![EmailProvider::Postmark, EmailProvider::Smtp];
+    // Nothing listens on port 1
+    settings.email.smtp.host = "127.0.0.1".to_owned();
+    settings.email.smtp.port = Some(1);
+
+    let mut app = TestApp::with_settings(settings).await;
+
+    let response = app.get_health_ready().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+
+    let postmark = body
+        .checks
+        .iter()
+        .find(|c| c.name == "email:postmark")
+        .unwrap();
+    assert_eq!(postmark.status, HealthStatus::Up);
+    assert!(!postmark.critical);
+
+    let smtp = body.checks.iter().find(|c| c.name == "email:smtp").unwrap();
+    assert_eq!(smtp.status, HealthStatus::Down);
+    assert!(!smtp.critical);
+
+    app.clean_up().await;
+}
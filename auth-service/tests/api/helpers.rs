@@ -1,17 +1,47 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    io,
+    str::FromStr,
+    sync::{Arc, Once},
+};
 
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType},
-    domain::Email,
-    get_postgres_pool, get_redis_client,
+    app_state::{
+        AppState, AuditSinkType, BannedTokenStoreType, EmailEventStoreType, SessionStoreType,
+        TrustedDeviceStoreType, TwoFACodeStoreType, UserStoreType, WebhookDeliveryStoreType,
+    },
+    domain::{Email, LoginAttemptId},
+    get_postgres_pool, get_redis_connection,
+    routes::TwoFactorAuthResponse,
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
+        data_stores::{
+            InMemoryAuditSink, PostgresEmailEventStore, PostgresEmailOutboxStore,
+            PostgresUserStore, PostgresWebhookDeliveryStore, RedisBannedTokenStore,
+            RedisSessionStore, RedisTrustedDeviceStore, RedisTwoFACodeStore,
+        },
+        email_outbox_worker::EmailOutboxWorker,
+        health_checks::HealthChecks,
+        outbox_email_client::OutboxEmailClient,
         postmark_email_client::PostmarkEmailClient,
+        twilio_sms_client::TwilioSmsClient,
+        webhook_audit_sink::WebhookAuditSink,
+        webhook_worker::WebhookWorker,
+    },
+    utils::{
+        auth::{AuthMethod, Claims},
+        constants::{CSRF_COOKIE_NAME, CSRF_HEADER_NAME},
+        csrf::generate_csrf_cookie,
+        settings::{AppEnvironment, Settings},
+        tracing::build_tracer_provider,
     },
-    utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME},
     Application,
 };
-use reqwest::{cookie::Jar, Client};
+use chrono::Utc;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use opentelemetry::trace::TracerProvider;
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    Client, Method, RequestBuilder, Url,
+};
 use secrecy::{ExposeSecret, SecretString};
 use serde::Serialize;
 use sqlx::{
@@ -19,7 +49,11 @@ use sqlx::{
     Connection, Executor, PgConnection, PgPool,
 };
 use test_context::AsyncTestContext;
-use tokio::sync::RwLock;
+use tokio::{
+    sync::{oneshot, RwLock},
+    task::JoinHandle,
+};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use uuid::Uuid;
 use wiremock::MockServer;
 
@@ -27,37 +61,100 @@ pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
+    pub user_store: UserStoreType<PostgresUserStore>,
     pub banned_token_store: BannedTokenStoreType<RedisBannedTokenStore>,
     pub two_fa_code_store: TwoFACodeStoreType<RedisTwoFACodeStore>,
+    pub session_store: SessionStoreType<RedisSessionStore>,
+    pub trusted_device_store: TrustedDeviceStoreType<RedisTrustedDeviceStore>,
     pub email_server: MockServer,
+    pub email_event_store: EmailEventStoreType<PostgresEmailEventStore>,
+    pub email_outbox_worker:
+        EmailOutboxWorker<PostgresEmailOutboxStore, PostmarkEmailClient<PostgresEmailEventStore>>,
+    pub sms_server: MockServer,
+    pub audit_sink:
+        AuditSinkType<WebhookAuditSink<InMemoryAuditSink, PostgresWebhookDeliveryStore>>,
+    pub webhook_deliveries: WebhookDeliveryStoreType<PostgresWebhookDeliveryStore>,
+    pub webhook_worker: WebhookWorker<PostgresWebhookDeliveryStore>,
+    pub pg_pool: PgPool,
     pub db_name: String,
+    pub settings: Settings,
+    shutdown: Option<(oneshot::Sender<()>, JoinHandle<io::Result<()>>)>,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
-        let pg_pool = configure_postgresql().await;
+        Self::with_settings(test_settings()).await
+    }
+
+    // Start the app with settings a test has changed, e.g. to turn on dev mode
+    pub async fn with_settings(mut settings: Settings) -> Self {
+        init_tracing(&settings);
+
+        let pg_pool = configure_postgresql(&settings).await;
         let db_name = pg_pool.connect_options().get_database().unwrap().to_owned();
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let redis_conn = Arc::new(RwLock::new(configure_redis()));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+        let email_outbox = Arc::new(RwLock::new(PostgresEmailOutboxStore::new(pg_pool.clone())));
+        let email_event_store =
+            Arc::new(RwLock::new(PostgresEmailEventStore::new(pg_pool.clone())));
+        let redis_conn = Arc::new(RwLock::new(configure_redis(&settings)));
 
         let banned_token_store =
             Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
 
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+
+        let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+
+        let trusted_device_store = Arc::new(RwLock::new(RedisTrustedDeviceStore::new(
+            redis_conn.clone(),
+        )));
 
         // Set up a mock email server
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
-        let email_client = Arc::new(configure_postmark_email_client(base_url));
+        settings.email.postmark.base_url = base_url.clone();
+        let email_client = Arc::new(OutboxEmailClient::new(
+            email_outbox.clone(),
+            email_event_store.clone(),
+        ));
+
+        // The worker isn't spawned, tests deliver queued emails with `deliver_emails`
+        let email_outbox_worker = EmailOutboxWorker::new(
+            email_outbox,
+            configure_postmark_email_client(base_url, &settings, email_event_store.clone()),
+        );
+
+        // Set up a mock SMS server
+        let sms_server = MockServer::start().await;
+        let sms_client = Arc::new(configure_twilio_sms_client(sms_server.uri(), &settings));
+
+        let webhook_deliveries = Arc::new(RwLock::new(PostgresWebhookDeliveryStore::new(
+            pg_pool.clone(),
+        )));
+        let audit_sink = Arc::new(RwLock::new(WebhookAuditSink::new(
+            InMemoryAuditSink::default(),
+            webhook_deliveries.clone(),
+            settings.webhooks.subscriptions.clone(),
+        )));
+
+        // The worker isn't spawned, tests send queued webhooks with `deliver_webhooks`
+        let webhook_worker = configure_webhook_worker(&settings, webhook_deliveries.clone());
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
             email_client,
+            session_store.clone(),
+            trusted_device_store.clone(),
+            sms_client,
+            email_event_store.clone(),
+            audit_sink.clone(),
         );
 
-        let app = Application::build(app_state, test::APP_ADDRESS)
+        let health_checks = HealthChecks::from_settings(&settings, pg_pool.clone(), redis_conn);
+
+        let app = Application::build(app_state, settings.clone(), health_checks)
             .await
             .expect("Failed to build app");
 
@@ -65,8 +162,10 @@ impl TestApp {
 
         // Run the auth service in a separate async task
         // to avoid blocking the main test thread.
-        #[allow(clippy::let_underscore_future)]
-        let _ = tokio::spawn(app.run());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server = tokio::spawn(app.run_until_stopped(async {
+            let _ = shutdown_rx.await;
+        }));
 
         let cookie_jar = Arc::new(Jar::default());
 
@@ -79,15 +178,100 @@ impl TestApp {
             address,
             cookie_jar,
             http_client,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            session_store,
+            trusted_device_store,
             email_server,
+            email_event_store,
+            email_outbox_worker,
+            sms_server,
+            audit_sink,
+            webhook_deliveries,
+            webhook_worker,
+            pg_pool,
             db_name,
+            settings,
+            shutdown: Some((shutdown_tx, server)),
         }
     }
 
     pub async fn clean_up(&mut self) {
-        delete_database(&self.db_name).await;
+        // Deliver what's left so the email server mocks see every email, unless a test
+        // closed the pool to take Postgres down
+        if !self.pg_pool.is_closed() {
+            self.deliver_emails().await;
+        }
+        delete_database(&self.settings.database.url, &self.db_name).await;
+    }
+
+    // Send the app the shutdown signal, returning the task it's running in
+    pub fn stop(&mut self) -> JoinHandle<io::Result<()>> {
+        let (shutdown_tx, server) = self.shutdown.take().expect("App already stopped");
+        shutdown_tx.send(()).expect("App already exited");
+        server
+    }
+
+    // Run the outbox worker until no queued email is due
+    pub async fn deliver_emails(&self) {
+        while self
+            .email_outbox_worker
+            .process_due(Utc::now())
+            .await
+            .expect("Failed to process email outbox")
+            > 0
+        {}
+    }
+
+    // Read the CSRF token the way browser JavaScript would, from the non-HttpOnly cookie
+    pub fn get_csrf_token(&self) -> Option<String> {
+        let url = Url::parse(&self.address).expect("Failed to parse URL");
+        let cookies = self.cookie_jar.cookies(&url)?;
+
+        cookies
+            .to_str()
+            .ok()?
+            .split("; ")
+            .find_map(|cookie| cookie.strip_prefix(&format!("{CSRF_COOKIE_NAME}=")))
+            .map(ToOwned::to_owned)
+    }
+
+    pub fn add_csrf_cookie(&self) {
+        self.cookie_jar.add_cookie_str(
+            &format!(
+                "{}; SameSite=Lax; Path=/",
+                generate_csrf_cookie(None, &self.settings.auth)
+            ),
+            &Url::parse(&self.address).expect("Failed to parse URL"),
+        );
+    }
+
+    // Build a request that echoes the CSRF cookie in the CSRF header, if there is one
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http_client
+            .request(method, format!("{}{}", &self.address, path));
+
+        match self.get_csrf_token() {
+            Some(token) => request.header(CSRF_HEADER_NAME, token),
+            None => request,
+        }
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    // Run the webhook worker until no delivery is due
+    pub async fn deliver_webhooks(&self) {
+        while self
+            .webhook_worker
+            .process_due(Utc::now())
+            .await
+            .expect("Failed to process webhook deliveries")
+            > 0
+        {}
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
@@ -98,12 +282,92 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_health_live(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/live", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_ready(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/ready", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self, admin_token: Option<&str>) -> reqwest::Response {
+        let request = self.http_client.get(format!("{}/metrics", &self.address));
+
+        match admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+        .send()
+        .await
+        .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_events(
+        &self,
+        query: &[(&str, &str)],
+        admin_token: Option<&str>,
+    ) -> reqwest::Response {
+        let request = self
+            .http_client
+            .get(format!("{}/admin/audit-events", &self.address))
+            .query(query);
+
+        match admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+        .send()
+        .await
+        .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_email_suppression(
+        &self,
+        email: &str,
+        admin_token: Option<&str>,
+    ) -> reqwest::Response {
+        let request = self.http_client.delete(format!(
+            "{}/admin/email-suppressions/{email}",
+            &self.address
+        ));
+
+        match admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+        .send()
+        .await
+        .expect("Failed to execute request.")
+    }
+
+    // Send the preflight request a browser makes before a cross-origin request
+    pub async fn preflight(&self, path: &str, origin: &str, method: &str) -> reqwest::Response {
+        self.http_client
+            .request(Method::OPTIONS, format!("{}{}", &self.address, path))
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method)
+            .header(
+                "Access-Control-Request-Headers",
+                "content-type,x-csrf-token",
+            )
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
-        self.http_client
-            .post(format!("{}/signup", &self.address))
+        self.post("/signup")
             .json(body)
             .send()
             .await
@@ -114,8 +378,7 @@ impl TestApp {
     where
         Body: Serialize,
     {
-        self.http_client
-            .post(format!("{}/login", &self.address))
+        self.post("/login")
             .json(body)
             .send()
             .await
@@ -123,8 +386,15 @@ impl TestApp {
     }
 
     pub async fn post_logout(&self) -> reqwest::Response {
-        self.http_client
-            .post(format!("{}/logout", &self.address))
+        self.post("/logout")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.post("/logout")
+            .bearer_auth(token)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -134,8 +404,18 @@ impl TestApp {
     where
         Body: Serialize,
     {
-        self.http_client
-            .post(format!("{}/verify-2fa", &self.address))
+        self.post("/verify-2fa")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/resend-2fa")
             .json(body)
             .send()
             .await
@@ -146,13 +426,223 @@ impl TestApp {
     where
         Body: Serialize,
     {
-        self.http_client
-            .post(format!("{}/verify-token", &self.address))
+        self.post("/verify-token")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.request(Method::GET, "/sessions")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.request(Method::GET, "/sessions")
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, session_id: &str) -> reqwest::Response {
+        self.request(Method::DELETE, &format!("/sessions/{session_id}"))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_trusted_devices(&self) -> reqwest::Response {
+        self.request(Method::GET, "/trusted-devices")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_trusted_device(&self, device_id: &str) -> reqwest::Response {
+        self.request(Method::DELETE, &format!("/trusted-devices/{device_id}"))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reauthenticate<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/reauthenticate")
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn post_reauthenticate_with_bearer<Body>(
+        &self,
+        token: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/reauthenticate")
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.request(Method::PUT, "/password")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_password_with_bearer<Body>(
+        &self,
+        token: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.request(Method::PUT, "/password")
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enable_2fa(&self) -> reqwest::Response {
+        self.post("/2fa/enable")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enable_2fa_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.post("/2fa/enable")
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_enable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/2fa/enable/verify")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/2fa/disable")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_disable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/2fa/disable/verify")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/phone-number")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.post("/phone-number/verify")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_2fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.request(Method::PUT, "/2fa-channel")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Postmark sends the webhook secret as the basic auth password
+    pub async fn post_postmark_webhook<Body>(
+        &self,
+        body: &Body,
+        secret: Option<&str>,
+    ) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        let request = self.post("/webhooks/postmark").json(body);
+
+        match secret {
+            Some(secret) => request.basic_auth("postmark", Some(secret)),
+            None => request,
+        }
+        .send()
+        .await
+        .expect("Failed to execute request.")
+    }
+
+    // Read the code from the most recent text message sent through the mock SMS server
+    pub async fn get_last_sms_code(&self) -> String {
+        let requests = self
+            .sms_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+
+        let request = requests.last().expect("No SMS sent");
+
+        std::str::from_utf8(&request.body)
+            .expect("SMS request body is not UTF-8")
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("Body="))
+            .expect("SMS request has no body")
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect()
+    }
 }
 
 impl AsyncTestContext for TestApp {
@@ -165,17 +655,67 @@ impl AsyncTestContext for TestApp {
     }
 }
 
+// Read the login attempt id from a 206 login response
+pub async fn get_login_attempt_id(response: reqwest::Response) -> LoginAttemptId {
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    LoginAttemptId::parse(json_body.login_attempt_id.into()).expect("Invalid login attempt id")
+}
+
+// Give spans OpenTelemetry trace ids, so `traceparent` headers are propagated. Nothing is
+// exported or logged.
+fn init_tracing(settings: &Settings) {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        let tracer = build_tracer_provider(&settings.telemetry)
+            .expect("Failed to build tracer provider")
+            .tracer("auth-service");
+
+        tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .init();
+    });
+}
+
+pub fn test_settings() -> Settings {
+    Settings::load_for(AppEnvironment::Test).expect("Failed to load test settings")
+}
+
+// Mint a token as if the user had logged in `age_seconds` ago
+pub fn create_auth_token(email: &Email, age_seconds: i64, amr: &[AuthMethod]) -> String {
+    let now = Utc::now().timestamp();
+
+    let claims = Claims {
+        sub: email.as_ref().expose_secret().to_owned(),
+        exp: (now + 600) as usize,
+        sid: None,
+        auth_time: now - age_seconds,
+        amr: amr.to_vec(),
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(test_settings().auth.jwt_secret.expose_secret().as_bytes()),
+    )
+    .expect("Failed to encode token")
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
-async fn configure_postgresql() -> PgPool {
-    let postgresql_conn_url = DATABASE_URL.to_owned();
+async fn configure_postgresql(settings: &Settings) -> PgPool {
+    let postgresql_conn_url = &settings.database.url;
 
     // We are creating a new database for each test case, and we need to ensure each database has a unique name!
     let db_name = Uuid::new_v4().to_string();
 
-    configure_database(&postgresql_conn_url, &db_name).await;
+    configure_database(postgresql_conn_url, &db_name).await;
 
     let postgresql_conn_url_with_db =
         format!("{}/{}", postgresql_conn_url.expose_secret(), db_name);
@@ -214,9 +754,7 @@ async fn configure_database(db_conn_string: &SecretString, db_name: &str) {
         .expect("Failed to migrate the database");
 }
 
-async fn delete_database(db_name: &str) {
-    let postgresql_conn_url: SecretString = DATABASE_URL.to_owned();
-
+async fn delete_database(postgresql_conn_url: &SecretString, db_name: &str) {
     let connection_options = PgConnectOptions::from_str(postgresql_conn_url.expose_secret())
         .expect("Failed to parse PostgreSQL connection string");
 
@@ -248,22 +786,56 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
+fn configure_redis(settings: &Settings) -> redis::Connection {
+    get_redis_connection(&settings.redis).expect("Failed to get Redis connection")
 }
 
-fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
-    let postmark_auth_token = "auth_token".into();
+fn configure_postmark_email_client(
+    base_url: String,
+    settings: &Settings,
+    email_event_store: EmailEventStoreType<PostgresEmailEventStore>,
+) -> PostmarkEmailClient<PostgresEmailEventStore> {
+    let http_client = Client::builder()
+        .timeout(settings.email.timeout())
+        .build()
+        .expect("Failed to build HTTP client");
 
-    let sender = Email::parse(test::email_client::SENDER.into()).unwrap();
+    PostmarkEmailClient::new(
+        base_url,
+        settings.email.sender.clone(),
+        settings.email.postmark.auth_token.clone(),
+        http_client,
+        email_event_store,
+    )
+}
+
+fn configure_twilio_sms_client(base_url: String, settings: &Settings) -> TwilioSmsClient {
+    let http_client = Client::builder()
+        .timeout(settings.sms.timeout())
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        base_url,
+        settings.sms.sender.clone(),
+        settings.sms.twilio.account_sid.clone(),
+        settings.sms.twilio.auth_token.clone(),
+        http_client,
+    )
+}
 
+fn configure_webhook_worker(
+    settings: &Settings,
+    webhook_deliveries: WebhookDeliveryStoreType<PostgresWebhookDeliveryStore>,
+) -> WebhookWorker<PostgresWebhookDeliveryStore> {
     let http_client = Client::builder()
-        .timeout(test::email_client::TIMEOUT)
+        .timeout(settings.webhooks.timeout())
         .build()
         .expect("Failed to build HTTP client");
 
-    PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
+    WebhookWorker::new(
+        webhook_deliveries,
+        settings.webhooks.subscriptions.clone(),
+        http_client,
+    )
 }
@@ -1,13 +1,13 @@
 use auth_service::{
-    domain::{Email, TwoFACodeStore},
-    routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    domain::{Email, LoginAttemptId, TwoFACodeStore},
+    routes::{BearerAuthResponse, TwoFactorAuthResponse},
+    utils::constants::{CSRF_COOKIE_NAME, JWT_COOKIE_NAME},
     ErrorResponse,
 };
 use secrecy::ExposeSecret;
 use test_context::test_context;
 use wiremock::{
-    matchers::{method, path},
+    matchers::{body_string_contains, method, path},
     Mock, ResponseTemplate,
 };
 
@@ -43,6 +43,57 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled(app: &mut TestA
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+
+    let csrf_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == CSRF_COOKIE_NAME)
+        .expect("No CSRF cookie found");
+
+    assert!(!csrf_cookie.value().is_empty());
+    assert!(!csrf_cookie.http_only());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_token_in_body_if_token_delivery_is_body(app: &mut TestApp) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let json_body = response
+        .json::<BearerAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerAuthResponse");
+
+    assert_eq!(json_body.token_type, "Bearer");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": json_body.token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
 }
 
 #[test_context(TestApp)]
@@ -83,19 +134,141 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled(app: &mut TestAp
 
     assert_eq!(json_body.message, "2FA required".to_owned());
 
+    let login_attempt_id =
+        LoginAttemptId::parse(json_body.login_attempt_id.into()).expect("Invalid login attempt id");
+
     let two_fa_code_store = app.two_fa_code_store.read().await;
 
-    let (login_attempt_id, _) = two_fa_code_store
-        .get_code(&random_email)
+    let (email, _, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
         .await
         .expect("should get code");
 
     drop(two_fa_code_store);
 
-    assert_eq!(
-        json_body.login_attempt_id,
-        login_attempt_id.as_ref().expose_secret()
-    );
+    assert_eq!(email, random_email);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_send_2fa_code_email_in_users_locale(app: &mut TestApp) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true,
+        "locale": "es",
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_string_contains("Tu código de verificación"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_206_and_retry_email_if_email_provider_is_down(app: &mut TestApp) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // The code is queued, so login doesn't wait on the provider
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.deliver_emails().await;
+    drop(guard);
+
+    // The failed email is retried later rather than delivered again right away
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.deliver_emails().await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_not_keep_2fa_codes_once_emailed(app: &mut TestApp) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.deliver_emails().await;
+
+    let bodies: Vec<(Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT html_body, text_body FROM email_outbox WHERE status = 'sent'")
+            .fetch_all(&app.pg_pool)
+            .await
+            .unwrap();
+
+    assert_eq!(bodies, [(None, None)]);
 }
 
 #[test_context(TestApp)]
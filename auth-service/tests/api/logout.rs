@@ -1,13 +1,14 @@
 use auth_service::{
     domain::{BannedTokenStore, Email},
     utils::{
-        auth::{create_auth_cookie, generate_auth_cookie, generate_auth_token},
-        constants::JWT_COOKIE_NAME,
+        auth::{create_auth_cookie, generate_auth_cookie, generate_auth_token, AuthMethod},
+        constants::{CSRF_HEADER_NAME, JWT_COOKIE_NAME},
     },
     ErrorResponse,
 };
 use fake::{faker::internet::en::FreeEmail, Fake};
 use reqwest::Url;
+use secrecy::ExposeSecret;
 use test_context::test_context;
 
 use crate::helpers::TestApp;
@@ -15,33 +16,80 @@ use crate::helpers::TestApp;
 #[test_context(TestApp)]
 #[tokio::test]
 async fn should_return_200_if_valid_jwt_cookie(app: &mut TestApp) {
-    let token =
-        generate_auth_token(&Email::parse(FreeEmail().fake::<String>().into()).unwrap()).unwrap();
+    let token = generate_auth_token(
+        &Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+        &[AuthMethod::Pwd],
+        &app.settings.auth,
+    )
+    .unwrap();
 
-    let cookie = create_auth_cookie(token.clone());
+    let cookie = create_auth_cookie(token.clone(), &app.settings.auth);
 
     app.cookie_jar.add_cookie_str(
         &format!("{cookie}; HttpOnly; SameSite=Lax; Secure; Path=/"),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
 
+    app.add_csrf_cookie();
+
     let response = app.post_logout().await;
     assert_eq!(response.status().as_u16(), 200);
+
+    let removal_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie removal found");
+
+    assert!(removal_cookie.value().is_empty());
+    assert_eq!(removal_cookie.path(), Some("/"));
+    assert_eq!(removal_cookie.max_age(), Some(std::time::Duration::ZERO));
+
     let banned_token_store = app.banned_token_store.read().await;
     assert!(banned_token_store.contains_token(&token).await.unwrap());
 }
 
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token(app: &mut TestApp) {
+    let token = generate_auth_token(
+        &Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+        &[AuthMethod::Pwd],
+        &app.settings.auth,
+    )
+    .unwrap();
+
+    let response = app.post_logout_with_bearer(token.expose_secret()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let banned_token_store = app.banned_token_store.read().await;
+    assert!(banned_token_store.contains_token(&token).await.unwrap());
+    drop(banned_token_store);
+
+    let response = app.post_logout_with_bearer(token.expose_secret()).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
 #[test_context(TestApp)]
 #[tokio::test]
 async fn should_return_400_if_logout_called_twice_in_a_row(app: &mut TestApp) {
-    let cookie =
-        generate_auth_cookie(&Email::parse(FreeEmail().fake::<String>().into()).unwrap()).unwrap();
+    let cookie = generate_auth_cookie(
+        &Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+        &[AuthMethod::Pwd],
+        &app.settings.auth,
+    )
+    .unwrap();
 
     app.cookie_jar.add_cookie_str(
         &format!("{cookie}; HttpOnly; SameSite=Lax; Secure; Path=/"),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
 
+    app.add_csrf_cookie();
+
     app.post_logout().await;
     let response = app.post_logout().await;
     assert_eq!(response.status().as_u16(), 400);
@@ -80,6 +128,8 @@ async fn should_return_401_if_invalid_token(app: &mut TestApp) {
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
 
+    app.add_csrf_cookie();
+
     let response = app.post_logout().await;
     assert_eq!(response.status().as_u16(), 401);
 
@@ -92,3 +142,66 @@ async fn should_return_401_if_invalid_token(app: &mut TestApp) {
         "Invalid auth token".to_owned()
     );
 }
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_invalid_bearer_token(app: &mut TestApp) {
+    let response = app.post_logout_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_403_if_csrf_token_missing_or_mismatched(app: &mut TestApp) {
+    let token = generate_auth_token(
+        &Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+        &[AuthMethod::Pwd],
+        &app.settings.auth,
+    )
+    .unwrap();
+
+    let cookie = create_auth_cookie(token.clone(), &app.settings.auth);
+
+    app.cookie_jar.add_cookie_str(
+        &format!("{cookie}; HttpOnly; SameSite=Lax; Secure; Path=/"),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    app.add_csrf_cookie();
+
+    for csrf_token in [None, Some(""), Some("mismatched")] {
+        let mut request = app.http_client.post(format!("{}/logout", &app.address));
+
+        if let Some(csrf_token) = csrf_token {
+            request = request.header(CSRF_HEADER_NAME, csrf_token);
+        }
+
+        let response = request.send().await.expect("Failed to execute request.");
+        assert_eq!(
+            response.status().as_u16(),
+            403,
+            "Failed for CSRF token: {csrf_token:?}"
+        );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid CSRF token".to_owned()
+        );
+    }
+
+    let banned_token_store = app.banned_token_store.read().await;
+    assert!(!banned_token_store.contains_token(&token).await.unwrap());
+}
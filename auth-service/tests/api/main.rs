@@ -1,7 +1,23 @@
+mod audit_events;
+mod cors;
+mod dev_mode;
+mod health;
 mod helpers;
 mod login;
 mod logout;
+mod metrics;
+mod phone_number;
+mod postmark_webhook;
+mod reauthenticate;
+mod request_id;
+mod resend_2fa;
 mod root;
+mod sessions;
+mod shutdown;
 mod signup;
+mod trace_context;
+mod trusted_devices;
+mod two_fa_settings;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
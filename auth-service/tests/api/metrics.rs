@@ -0,0 +1,231 @@
+use auth_service::domain::TwoFACodeStore;
+use secrecy::ExposeSecret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_login_attempt_id, get_random_email, test_settings, TestApp};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+async fn admin_app() -> TestApp {
+    let mut settings = test_settings();
+    settings.admin.api_token = ADMIN_TOKEN.to_owned().into();
+
+    TestApp::with_settings(settings).await
+}
+
+// Every test app in the process records into the same registry, so tests only check that
+// what they did shows up, never exact counts
+fn metric_value(metrics: &str, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    metrics.lines().find_map(|line| {
+        let (series, value) = line.rsplit_once(' ')?;
+        let (series_name, series_labels) = series.split_once('{').unwrap_or((series, ""));
+
+        let matches = series_name == name
+            && labels
+                .iter()
+                .all(|(key, value)| series_labels.contains(&format!("{key}=\"{value}\"")));
+
+        matches.then(|| value.parse().ok()).flatten()
+    })
+}
+
+async fn get_metrics(app: &TestApp) -> String {
+    let response = app.get_metrics(Some(ADMIN_TOKEN)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+
+    response.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn should_record_requests_per_route() {
+    let mut app = admin_app().await;
+
+    let response = app.delete_session("not-a-session").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let metrics = get_metrics(&app).await;
+
+    // Labelled with the route template, not the path
+    let requests = metric_value(
+        &metrics,
+        "http_requests_total",
+        &[
+            ("method", "DELETE"),
+            ("route", "/sessions/{id}"),
+            ("status", "400"),
+        ],
+    );
+    assert!(requests.is_some_and(|count| count >= 1.0));
+
+    let latencies = metric_value(
+        &metrics,
+        "http_request_duration_seconds_count",
+        &[("method", "DELETE"), ("route", "/sessions/{id}")],
+    );
+    assert!(latencies.is_some_and(|count| count >= 1.0));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_login_outcomes_and_store_latencies() {
+    let mut app = admin_app().await;
+
+    let email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({ "email": email, "password": "wrong-password" });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 401);
+
+    let metrics = get_metrics(&app).await;
+
+    for labels in [
+        &[("outcome", "success")][..],
+        &[("outcome", "failure"), ("reason", "incorrect_credentials")][..],
+    ] {
+        let logins = metric_value(&metrics, "auth_logins_total", labels);
+        assert!(
+            logins.is_some_and(|count| count >= 1.0),
+            "Failed for {labels:?}"
+        );
+    }
+
+    for operation in ["hash", "verify"] {
+        let hashes = metric_value(
+            &metrics,
+            "auth_password_hash_duration_seconds_count",
+            &[("operation", operation)],
+        );
+        assert!(hashes.is_some(), "Failed for operation: {operation}");
+    }
+
+    let store_operations = metric_value(
+        &metrics,
+        "auth_store_operation_duration_seconds_count",
+        &[
+            ("backend", "postgres"),
+            ("store", "user"),
+            ("operation", "add_user"),
+        ],
+    );
+    assert!(store_operations.is_some_and(|count| count >= 1.0));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_2fa_codes_and_email_deliveries() {
+    let mut app = admin_app().await;
+
+    let email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    app.deliver_emails().await;
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": "000000",
+    });
+    assert_eq!(
+        app.post_verify_2fa(&verify_body).await.status().as_u16(),
+        401
+    );
+
+    let (_, two_fa_code, _) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await
+        .unwrap();
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+    });
+    assert_eq!(
+        app.post_verify_2fa(&verify_body).await.status().as_u16(),
+        200
+    );
+
+    let metrics = get_metrics(&app).await;
+
+    assert!(metric_value(
+        &metrics,
+        "auth_logins_total",
+        &[("outcome", "2fa_required")]
+    )
+    .is_some());
+
+    for event in ["issued", "failed", "verified"] {
+        let codes = metric_value(&metrics, "auth_2fa_codes_total", &[("event", event)]);
+        assert!(
+            codes.is_some_and(|count| count >= 1.0),
+            "Failed for event: {event}"
+        );
+    }
+
+    let deliveries = metric_value(
+        &metrics,
+        "auth_email_deliveries_total",
+        &[("outcome", "sent")],
+    );
+    assert!(deliveries.is_some_and(|count| count >= 1.0));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_serve_metrics_with_the_admin_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_metrics(None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+
+    let mut app = admin_app().await;
+
+    for (token, status) in [(None, 400), (Some("wrong-token"), 401)] {
+        let response = app.get_metrics(token).await;
+        assert_eq!(response.status().as_u16(), status);
+    }
+
+    app.clean_up().await;
+}
@@ -0,0 +1,464 @@
+use auth_service::{
+    domain::{Email, PhoneNumber, TwoFAChannel, TwoFACodeStore, UserStore},
+    routes::TwoFAChannelResponse,
+    utils::constants::MAX_PHONE_VERIFICATION_ATTEMPTS,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_context::test_context;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_login_attempt_id, get_random_email, test_settings, TestApp};
+
+const PHONE_NUMBER: &str = "+1 415 555 2671";
+
+async fn signup_and_login(app: &TestApp) -> Email {
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let input = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+    });
+
+    let response = app.post_verify_2fa(&input).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &Email) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    app.post_login(&login_body).await
+}
+
+fn sms_path(app: &TestApp) -> String {
+    format!(
+        "/2010-04-01/Accounts/{}/Messages.json",
+        app.settings.sms.twilio.account_sid
+    )
+}
+
+async fn verify_phone_number(app: &TestApp) {
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": app.get_last_sms_code().await }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_send_2fa_code_by_sms_once_phone_number_verified(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // One text to verify the number, one for the 2FA code
+    Mock::given(path(sms_path(app)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(2)
+        .mount(&app.sms_server)
+        .await;
+
+    let email = signup_and_login(app).await;
+    verify_phone_number(app).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    assert_eq!(
+        app.get_last_sms_code().await,
+        two_fa_code.as_ref().expose_secret().to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_send_2fa_code_by_email_after_switching_back(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    Mock::given(path(sms_path(app)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    let email = signup_and_login(app).await;
+    verify_phone_number(app).await;
+
+    let response = app
+        .put_2fa_channel(&serde_json::json!({ "channel": "email" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<TwoFAChannelResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFAChannelResponse")
+            .channel,
+        TwoFAChannel::Email
+    );
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_by_email_if_sms_is_off() {
+    let mut settings = test_settings();
+    settings.sms.provider = None;
+    let mut app = TestApp::with_settings(settings).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    Mock::given(path(sms_path(&app)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&app.sms_server)
+        .await;
+
+    let email = signup_and_login(&app).await;
+
+    // Enrolled while SMS was on
+    let mut user_store = app.user_store.write().await;
+    let phone_number = PhoneNumber::parse(PHONE_NUMBER.to_owned().into()).unwrap();
+
+    user_store
+        .update_phone_number(&email, phone_number)
+        .await
+        .unwrap();
+
+    user_store
+        .update_two_fa_channel(&email, TwoFAChannel::Sms)
+        .await
+        .unwrap();
+
+    drop(user_store);
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    // Not served, so it falls through to the static files
+    assert_eq!(response.status().as_u16(), 405);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_switching_to_sms_without_verified_phone_number(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_and_login(app).await;
+
+    let response = app
+        .put_2fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+
+    assert_error(response, 400, "Phone number not verified").await;
+
+    // Codes are still sent by email
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_invalid_phone_number(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    Mock::given(path(sms_path(app)))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&app.sms_server)
+        .await;
+
+    signup_and_login(app).await;
+
+    for phone_number in ["", "4155552671", "+1 415 CALL NOW", "+0123456789"] {
+        let response = app
+            .post_phone_number(&serde_json::json!({ "phoneNumber": phone_number }))
+            .await;
+
+        assert_error(response, 400, "Invalid phone number").await;
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_incorrect_verification_code(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    Mock::given(path(sms_path(app)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    signup_and_login(app).await;
+
+    // Nothing to verify yet
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": "123456" }))
+        .await;
+
+    assert_error(response, 400, "Invalid verification code").await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let code = app.get_last_sms_code().await;
+    let incorrect_code = if code == "000000" { "111111" } else { "000000" };
+
+    for code in [incorrect_code, "12345", "abcdef"] {
+        let response = app
+            .post_verify_phone_number(&serde_json::json!({ "code": code }))
+            .await;
+
+        assert_error(response, 400, "Invalid verification code").await;
+    }
+
+    // The number isn't enrolled until it has been verified
+    let response = app
+        .put_2fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+
+    assert_error(response, 400, "Phone number not verified").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_invalidate_verification_code_after_too_many_incorrect_codes(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    Mock::given(path(sms_path(app)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    signup_and_login(app).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let code = app.get_last_sms_code().await;
+    let incorrect_code = if code == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..MAX_PHONE_VERIFICATION_ATTEMPTS {
+        let response = app
+            .post_verify_phone_number(&serde_json::json!({ "code": incorrect_code }))
+            .await;
+
+        assert_error(response, 400, "Invalid verification code").await;
+    }
+
+    // Guessing further is pointless, even the right code is rejected now
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+
+    assert_error(response, 400, "Invalid verification code").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_429_if_verification_code_requested_too_soon(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Only the first request is texted
+    Mock::given(path(sms_path(app)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    signup_and_login(app).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    // Switching numbers doesn't get around the cooldown
+    for phone_number in [PHONE_NUMBER, "+44 20 7946 0958"] {
+        let response = app
+            .post_phone_number(&serde_json::json!({ "phoneNumber": phone_number }))
+            .await;
+
+        assert_error(response, 429, "Please wait before requesting another code").await;
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_not_logged_in(app: &mut TestApp) {
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+
+    let response = app
+        .put_2fa_channel(&serde_json::json!({ "channel": "email" }))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_422_if_malformed_input(app: &mut TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    signup_and_login(app).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phone": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    let test_cases = [
+        serde_json::json!({ "channel": "voice" }),
+        serde_json::json!({ "twoFAChannel": "sms" }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.put_2fa_channel(&test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
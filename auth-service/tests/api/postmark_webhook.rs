@@ -0,0 +1,326 @@
+use auth_service::{
+    domain::{Email, EmailEventKind, EmailEventStore},
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, SecretString};
+use test_context::test_context;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, test_settings, TestApp};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+fn bounce(email: &str, bounce_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "MessageStream": "outbound",
+        "ID": 4323372036854775807u64,
+        "Type": bounce_type,
+        "TypeCode": 1,
+        "Name": "Hard bounce",
+        "Email": email,
+        "BouncedAt": "2026-10-19T16:33:54.9070259Z",
+        "Description": "The server was unable to deliver your message.",
+        "Inactive": true,
+        "CanActivate": true,
+    })
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn events(app: &TestApp, email: &str) -> Vec<EmailEventKind> {
+    let email = Email::parse(email.to_owned().into()).unwrap();
+
+    app.email_event_store
+        .read()
+        .await
+        .get_events(&email)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| event.kind)
+        .collect()
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_webhook_credentials_are_missing_or_wrong(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    for secret in [None, Some("wrong-secret")] {
+        let response = app
+            .post_postmark_webhook(&bounce(&random_email, "HardBounce"), secret)
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid webhook credentials".to_owned()
+        );
+    }
+
+    assert!(events(app, &random_email).await.is_empty());
+}
+
+#[tokio::test]
+async fn should_not_serve_webhook_without_secret() {
+    let mut settings = test_settings();
+    settings.email.postmark.webhook_secret = SecretString::default();
+    let mut app = TestApp::with_settings(settings).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let response = app
+        .post_postmark_webhook(&bounce(&random_email, "HardBounce"), None)
+        .await;
+
+    // Not served, so it falls through to the static files
+    assert_eq!(response.status().as_u16(), 405);
+    assert!(events(&app, &random_email).await.is_empty());
+
+    app.clean_up().await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_accept_secret_header(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/webhooks/postmark", app.address))
+        .header(
+            "X-Postmark-Webhook-Secret",
+            app.settings.email.postmark.webhook_secret.expose_secret(),
+        )
+        .json(&bounce(&random_email, "SoftBounce"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        events(app, &random_email).await,
+        [EmailEventKind::SoftBounce]
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_record_events_against_user(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    let secret = Some(app.settings.email.postmark.webhook_secret.expose_secret());
+
+    let delivery = serde_json::json!({
+        "RecordType": "Delivery",
+        "MessageID": "00000000-0000-0000-0000-000000000000",
+        "Recipient": random_email,
+        "DeliveredAt": "2026-10-19T16:30:00Z",
+        "Details": "Test delivery webhook details",
+    });
+
+    let complaint = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Email": random_email,
+        "BouncedAt": "2026-10-19T16:40:00Z",
+    });
+
+    for body in [delivery, complaint] {
+        let response = app.post_postmark_webhook(&body, secret).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    assert_eq!(
+        events(app, &random_email).await,
+        [EmailEventKind::Delivery, EmailEventKind::SpamComplaint]
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_ignore_unsupported_events_and_unknown_users(app: &mut TestApp) {
+    let secret = Some(app.settings.email.postmark.webhook_secret.expose_secret());
+
+    let open = serde_json::json!({
+        "RecordType": "Open",
+        "Recipient": get_random_email(),
+        "ReceivedAt": "2026-10-19T16:30:00Z",
+    });
+
+    let response = app.post_postmark_webhook(&open, secret).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let unknown_email = get_random_email();
+
+    let response = app
+        .post_postmark_webhook(&bounce(&unknown_email, "HardBounce"), secret)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(events(app, &unknown_email).await.is_empty());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_payload_is_malformed(app: &mut TestApp) {
+    let secret = Some(app.settings.email.postmark.webhook_secret.expose_secret());
+    let body = serde_json::json!({ "RecordType": "Bounce" });
+
+    let response = app.post_postmark_webhook(&body, secret).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_suppress_2fa_emails_after_hard_bounce(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    let response = app
+        .post_postmark_webhook(
+            &bounce(&random_email, "HardBounce"),
+            Some(app.settings.email.postmark.webhook_secret.expose_secret()),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email address is undeliverable".to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_keep_sending_after_soft_bounce(app: &mut TestApp) {
+    let random_email = get_random_email();
+    signup(app, &random_email).await;
+
+    let response = app
+        .post_postmark_webhook(
+            &bounce(&random_email, "SoftBounce"),
+            Some(app.settings.email.postmark.webhook_secret.expose_secret()),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[tokio::test]
+async fn should_send_again_once_admin_clears_suppression() {
+    let mut settings = test_settings();
+    settings.admin.api_token = ADMIN_TOKEN.to_owned().into();
+    let mut app = TestApp::with_settings(settings).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let mut body = bounce(&random_email, "HardBounce");
+    body["BouncedAt"] = "2026-01-01T00:00:00Z".into();
+
+    let response = app
+        .post_postmark_webhook(
+            &body,
+            Some(app.settings.email.postmark.webhook_secret.expose_secret()),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    for (token, status) in [(None, 400), (Some("wrong-token"), 401)] {
+        let response = app.delete_email_suppression(&random_email, token).await;
+        assert_eq!(response.status().as_u16(), status);
+    }
+
+    let response = app
+        .delete_email_suppression(&random_email, Some(ADMIN_TOKEN))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(
+        events(&app, &random_email).await,
+        [
+            EmailEventKind::HardBounce,
+            EmailEventKind::SuppressionCleared
+        ]
+    );
+
+    // Clearing again has nothing to do
+    let response = app
+        .delete_email_suppression(&random_email, Some(ADMIN_TOKEN))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(events(&app, &random_email).await.len(), 2);
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,463 @@
+use auth_service::{
+    domain::{Email, SessionStore, TrustedDevice, TrustedDeviceStore, TwoFACodeStore},
+    routes::{BearerAuthResponse, ChangePasswordResponse, SessionResponse},
+    utils::{
+        auth::{AuthMethod, Claims},
+        constants::JWT_COOKIE_NAME,
+    },
+    ErrorResponse,
+};
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+use test_context::test_context;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{create_auth_token, get_login_attempt_id, get_random_email, TestApp};
+
+async fn signup(app: &TestApp, requires_2fa: bool) -> Email {
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": requires_2fa,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn get_bearer_token(response: reqwest::Response) -> String {
+    response
+        .json::<BearerAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerAuthResponse")
+        .token
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_change_password_after_fresh_login(app: &mut TestApp) {
+    let email = signup(app, false).await;
+
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .put_password(&serde_json::json!({ "newPassword": "new password" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The revoked cookie is replaced, so this client stays signed in
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "password": "new password",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_sign_out_elsewhere_after_password_change(app: &mut TestApp) {
+    let email = signup(app, false).await;
+
+    // Signed in elsewhere, with a token in the body rather than this client's cookie
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "password": "password123",
+            "rememberMe": true,
+            "tokenDelivery": "body",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let other_token = get_bearer_token(response).await;
+
+    app.trusted_device_store
+        .write()
+        .await
+        .add_device(TrustedDevice::new(email.clone(), Duration::days(30)))
+        .await
+        .unwrap();
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "password": "password123",
+            "rememberMe": true,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .put_password(&serde_json::json!({ "newPassword": "new password" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // This session carries on
+    let sessions = app
+        .get_sessions()
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+    assert_eq!(
+        app.session_store
+            .read()
+            .await
+            .get_sessions(&email)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let devices = app
+        .trusted_device_store
+        .read()
+        .await
+        .get_devices(&email)
+        .await
+        .unwrap();
+
+    assert!(devices.is_empty());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_revoke_tokens_without_session_after_password_change(app: &mut TestApp) {
+    let email = signup(app, false).await;
+
+    // Signed in elsewhere, without "remember me"
+    let other_token = create_auth_token(&email, 10, &[AuthMethod::Pwd]);
+    let token = create_auth_token(&email, 5, &[AuthMethod::Pwd]);
+
+    let response = app
+        .put_password_with_bearer(
+            &token,
+            &serde_json::json!({ "newPassword": "new password" }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_token = response
+        .json::<ChangePasswordResponse>()
+        .await
+        .expect("Could not deserialize response body to ChangePasswordResponse")
+        .token
+        .expect("No replacement token");
+
+    for (token, status) in [(other_token, 401), (token, 401), (new_token, 200)] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), status);
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_replace_session_when_reauthenticating_with_remember_me(app: &mut TestApp) {
+    let email = signup(app, false).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "password": "password123",
+            "rememberMe": true,
+            "tokenDelivery": "body",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let old_token = get_bearer_token(response).await;
+
+    let response = app
+        .post_reauthenticate_with_bearer(
+            &old_token,
+            &serde_json::json!({
+                "password": "password123",
+                "rememberMe": true,
+                "tokenDelivery": "body",
+            }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let new_token = get_bearer_token(response).await;
+
+    assert_eq!(
+        app.session_store
+            .read()
+            .await
+            .get_sessions(&email)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    for (token, status) in [(old_token, 401), (new_token, 200)] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), status);
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_require_reauthentication_if_login_is_stale(app: &mut TestApp) {
+    let email = signup(app, false).await;
+    let stale_token = create_auth_token(&email, 3600, &[AuthMethod::Pwd]);
+    let body = serde_json::json!({ "newPassword": "new password" });
+
+    let response = app.put_password_with_bearer(&stale_token, &body).await;
+    assert_error(response, 401, "Re-authentication required").await;
+
+    let response = app
+        .post_reauthenticate_with_bearer(
+            &stale_token,
+            &serde_json::json!({
+                "password": "password123",
+                "tokenDelivery": "body",
+            }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let fresh_token = get_bearer_token(response).await;
+
+    let response = app.put_password_with_bearer(&fresh_token, &body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_require_2fa_to_reauthenticate_if_enabled(app: &mut TestApp) {
+    let email = signup(app, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // A fresh token without 2FA, as issued on a trusted device, isn't enough
+    let token = create_auth_token(&email, 0, &[AuthMethod::Pwd]);
+    let body = serde_json::json!({ "newPassword": "new password" });
+
+    let response = app.put_password_with_bearer(&token, &body).await;
+    assert_error(response, 401, "Re-authentication required").await;
+
+    let response = app
+        .post_reauthenticate_with_bearer(&token, &serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "tokenDelivery": "body",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let fresh_token = get_bearer_token(response).await;
+
+    let response = app.put_password_with_bearer(&fresh_token, &body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_reauthenticating_with_incorrect_password(app: &mut TestApp) {
+    let email = signup(app, true).await;
+    let token = create_auth_token(&email, 3600, &[AuthMethod::Pwd, AuthMethod::Otp]);
+
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_reauthenticate_with_bearer(&token, &serde_json::json!({ "password": "password321" }))
+        .await;
+
+    assert_error(response, 401, "Incorrect credentials").await;
+
+    // The password must belong to the signed-in user
+    let other_email = signup(app, false).await;
+    let other_token = create_auth_token(&other_email, 3600, &[AuthMethod::Pwd]);
+
+    let response = app
+        .post_reauthenticate_with_bearer(
+            &other_token,
+            &serde_json::json!({ "password": "not the password" }),
+        )
+        .await;
+
+    assert_error(response, 401, "Incorrect credentials").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_not_logged_in(app: &mut TestApp) {
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+
+    let response = app
+        .put_password(&serde_json::json!({ "newPassword": "new password" }))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_invalid_new_password(app: &mut TestApp) {
+    let email = signup(app, false).await;
+    let token = create_auth_token(&email, 0, &[AuthMethod::Pwd]);
+
+    let response = app
+        .put_password_with_bearer(&token, &serde_json::json!({ "newPassword": "short" }))
+        .await;
+
+    assert_error(response, 400, "Invalid credentials").await;
+
+    let response = app
+        .put_password_with_bearer(&token, &serde_json::json!({ "password": "new password" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_record_2fa_in_token_claims(app: &mut TestApp) {
+    let email = signup(app, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "tokenDelivery": "body",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_bearer_token(response).await;
+
+    let claims = jsonwebtoken::decode::<Claims>(
+        &token,
+        &jsonwebtoken::DecodingKey::from_secret(
+            app.settings.auth.jwt_secret.expose_secret().as_bytes(),
+        ),
+        &jsonwebtoken::Validation::default(),
+    )
+    .unwrap()
+    .claims;
+
+    assert_eq!(claims.amr, vec![AuthMethod::Pwd, AuthMethod::Otp]);
+    assert!(Utc::now().timestamp() - claims.auth_time <= 5);
+}
@@ -0,0 +1,80 @@
+use test_context::test_context;
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const REQUEST_ID: &str = "7f3c2a10-5d1e-4f1b-9a55-3d6c8e2b1f00";
+
+fn request_id(response: &reqwest::Response) -> &str {
+    response
+        .headers()
+        .get("x-request-id")
+        .expect("No X-Request-Id in response")
+        .to_str()
+        .unwrap()
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_echo_the_caller_request_id(app: &mut TestApp) {
+    let response = app
+        .http_client
+        .get(format!("{}/health/live", app.address))
+        .header("X-Request-Id", REQUEST_ID)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(request_id(&response), REQUEST_ID);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_generate_a_request_id_without_one(app: &mut TestApp) {
+    let first = app.get_health_live().await;
+    let second = app.get_health_live().await;
+
+    assert!(Uuid::parse_str(request_id(&first)).is_ok());
+    assert_ne!(request_id(&first), request_id(&second));
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_replace_an_unusable_request_id(app: &mut TestApp) {
+    for invalid in ["", "two words", &"a".repeat(129)] {
+        let response = app
+            .http_client
+            .get(format!("{}/health/live", app.address))
+            .header("X-Request-Id", invalid)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert!(
+            Uuid::parse_str(request_id(&response)).is_ok(),
+            "Failed for request ID: {invalid}"
+        );
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_include_the_request_id_in_error_responses(app: &mut TestApp) {
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", app.address))
+        .header("X-Request-Id", REQUEST_ID)
+        .json(&login_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(request_id(&response), REQUEST_ID);
+}
@@ -0,0 +1,139 @@
+use auth_service::{
+    domain::{Email, LoginAttemptId, TwoFACodePurpose, TwoFACodeStore},
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_context::test_context;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_login_attempt_id, get_random_email, TestApp};
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_429_if_resent_within_cooldown(app: &mut TestApp) {
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Please wait before requesting another code".to_owned()
+    );
+
+    // The original code is still valid
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    assert_eq!(
+        two_fa_code_store.get_code(&login_attempt_id).await,
+        Ok((email, two_fa_code, TwoFACodePurpose::Login))
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_unknown_login_attempt(app: &mut TestApp) {
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "loginAttemptId": LoginAttemptId::default().as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_invalid_input(app: &mut TestApp) {
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "loginAttemptId": "not-a-uuid",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_422_if_malformed_input(app: &mut TestApp) {
+    let test_cases = [
+        serde_json::json!({}),
+        serde_json::json!({ "login_attempt_id": LoginAttemptId::default().as_ref().expose_secret() }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_resend_2fa(&test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
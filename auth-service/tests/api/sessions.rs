@@ -0,0 +1,241 @@
+use auth_service::{
+    domain::{SessionId, SessionStore},
+    routes::{BearerAuthResponse, SessionResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use test_context::test_context;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, body: serde_json::Value) -> reqwest::Response {
+    let signup_body = serde_json::json!({
+        "email": body["email"],
+        "password": body["password"],
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.post_login(&body).await
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_set_persistent_cookie_if_remember_me(app: &mut TestApp) {
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "rememberMe": true,
+    });
+
+    let response = signup_and_login(app, login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(std::time::Duration::from_secs(
+            app.settings.auth.remember_me_ttl_seconds as u64
+        ))
+    );
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    let session_store = app.session_store.read().await;
+    let session_id = SessionId::parse(sessions[0].session_id.clone().into()).unwrap();
+    assert!(session_store.get_session(&session_id).await.is_ok());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_set_session_cookie_if_not_remember_me(app: &mut TestApp) {
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    let response = signup_and_login(app, login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert_eq!(auth_cookie.max_age(), None);
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    assert!(sessions.is_empty());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_reject_token_after_session_revoked(app: &mut TestApp) {
+    let random_email = get_random_email();
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "rememberMe": true,
+        "tokenDelivery": "body",
+    });
+
+    let response = signup_and_login(app, login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<BearerAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerAuthResponse")
+        .token;
+
+    let sessions = app
+        .get_sessions_with_bearer(&token)
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    assert_eq!(sessions.len(), 1);
+
+    // Revoke the session from a second, cookie-based login of the same user
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "rememberMe": true,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.delete_session(&sessions[0].session_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_sessions().await;
+
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_404_if_session_belongs_to_another_user(app: &mut TestApp) {
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "rememberMe": true,
+        "tokenDelivery": "body",
+    });
+
+    let response = signup_and_login(app, login_body).await;
+
+    let token = response
+        .json::<BearerAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerAuthResponse")
+        .token;
+
+    let sessions = app
+        .get_sessions_with_bearer(&token)
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "rememberMe": true,
+    });
+
+    let response = signup_and_login(app, login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for session_id in [sessions[0].session_id.as_str(), "not-a-uuid"] {
+        let response = app.delete_session(session_id).await;
+        assert_eq!(response.status().as_u16(), 404);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Session not found".to_owned()
+        );
+    }
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_remove_session_on_logout(app: &mut TestApp) {
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "rememberMe": true,
+    });
+
+    let response = signup_and_login(app, login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = app
+        .get_sessions()
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let session_store = app.session_store.read().await;
+    let session_id = SessionId::parse(sessions[0].session_id.clone().into()).unwrap();
+    assert!(session_store.get_session(&session_id).await.is_err());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_token_missing(app: &mut TestApp) {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::helpers::{test_settings, TestApp};
+
+// Start a request whose body hasn't fully arrived, so it's still in flight
+async fn start_request(app: &TestApp, body: &str) -> TcpStream {
+    let url = Url::parse(&app.address).unwrap();
+    let mut stream = TcpStream::connect((url.host_str().unwrap(), url.port().unwrap()))
+        .await
+        .unwrap();
+
+    let head = format!(
+        "POST /verify-token HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    );
+
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(&body.as_bytes()[..1]).await.unwrap();
+
+    // Give the server time to start handling the request
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    stream
+}
+
+#[tokio::test]
+async fn should_finish_in_flight_requests_before_stopping() {
+    let mut app = TestApp::new().await;
+    let body = r#"{"token":"invalid"}"#;
+    let mut stream = start_request(&app, body).await;
+
+    let server = app.stop();
+
+    // New connections are refused once shutdown starts
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let result = app
+        .http_client
+        .post(format!("{}/verify-token", app.address))
+        .json(&serde_json::json!({ "token": "invalid" }))
+        .send()
+        .await;
+    assert!(result.is_err());
+    assert!(!server.is_finished());
+
+    stream.write_all(&body.as_bytes()[1..]).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 401"),
+        "Unexpected response: {response}"
+    );
+
+    tokio::time::timeout(Duration::from_secs(1), server)
+        .await
+        .expect("server didn't stop")
+        .unwrap()
+        .unwrap();
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_stop_after_drain_timeout() {
+    let mut settings = test_settings();
+    settings.application.drain_timeout_seconds = 1;
+
+    let mut app = TestApp::with_settings(settings).await;
+
+    // Never finished by the client
+    let _stream = start_request(&app, r#"{"token":"invalid"}"#).await;
+
+    let server = app.stop();
+
+    tokio::time::timeout(Duration::from_secs(3), server)
+        .await
+        .expect("server didn't stop")
+        .unwrap()
+        .unwrap();
+
+    app.clean_up().await;
+}
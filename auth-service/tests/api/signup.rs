@@ -119,6 +119,12 @@ async fn should_return_422_if_malformed_input(app: &mut TestApp) {
             "email": random_email,
             "requires2FA": "true",
         }),
+        serde_json::json!({
+            "password": "password123",
+            "email": random_email,
+            "requires2FA": true,
+            "locale": "xx",
+        }),
     ];
 
     for test_case in test_cases.iter() {
@@ -0,0 +1,84 @@
+use test_context::test_context;
+use wiremock::{
+    matchers::{header_exists, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn trace_id(traceparent: &str) -> &str {
+    traceparent.split('-').nth(1).expect("Invalid traceparent")
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_continue_the_request_trace_when_sending_queued_emails(app: &mut TestApp) {
+    let email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(header_exists("traceparent"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", app.address))
+        .header("traceparent", TRACEPARENT)
+        .json(&serde_json::json!({ "email": email, "password": "password123" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.deliver_emails().await;
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let traceparent = requests[0].headers["traceparent"].to_str().unwrap();
+
+    assert_eq!(trace_id(traceparent), TRACE_ID);
+    // The Postmark call is a span of its own, not the caller's
+    assert_ne!(traceparent, TRACEPARENT);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_start_a_new_trace_without_traceparent(app: &mut TestApp) {
+    let email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 206);
+
+    app.deliver_emails().await;
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let traceparent = requests[0].headers["traceparent"].to_str().unwrap();
+
+    assert_ne!(trace_id(traceparent), TRACE_ID);
+}
@@ -0,0 +1,279 @@
+use auth_service::{
+    domain::{Email, TrustedDeviceStore, TwoFACodeStore},
+    routes::TrustedDeviceResponse,
+    utils::constants::TRUSTED_DEVICE_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_context::test_context;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_login_attempt_id, get_random_email, TestApp};
+
+async fn signup_with_2fa(app: &TestApp) -> Email {
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &Email) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    app.post_login(&login_body).await
+}
+
+// Log in with the emailed 2FA code, asking to trust the device
+async fn login_and_trust_device(app: &TestApp, email: &Email) -> reqwest::Response {
+    let response = login(app, email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let input = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+        "trustDevice": true,
+    });
+
+    app.post_verify_2fa(&input).await
+}
+
+async fn get_trusted_devices(app: &TestApp) -> Vec<TrustedDeviceResponse> {
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<Vec<TrustedDeviceResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<TrustedDeviceResponse>")
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_skip_2fa_on_trusted_device(app: &mut TestApp) {
+    let email = signup_with_2fa(app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login_and_trust_device(app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let device_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == TRUSTED_DEVICE_COOKIE_NAME)
+        .expect("No trusted device cookie found");
+
+    assert!(device_cookie.http_only());
+
+    assert_eq!(
+        device_cookie.max_age(),
+        Some(std::time::Duration::from_secs(
+            app.settings.auth.trusted_device_ttl_seconds as u64
+        ))
+    );
+
+    let devices = get_trusted_devices(app).await;
+    assert_eq!(devices.len(), 1);
+    assert!(devices[0].current);
+
+    // The trusted device outlives the login
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_require_2fa_if_device_not_trusted(app: &mut TestApp) {
+    let email = signup_with_2fa(app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let input = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+    });
+
+    let response = app.post_verify_2fa(&input).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != TRUSTED_DEVICE_COOKIE_NAME));
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_require_2fa_for_another_user_on_trusted_device(app: &mut TestApp) {
+    let email = signup_with_2fa(app).await;
+    let other_email = signup_with_2fa(app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login_and_trust_device(app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(app, &other_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_require_2fa_after_device_revoked(app: &mut TestApp) {
+    let email = signup_with_2fa(app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login_and_trust_device(app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let devices = get_trusted_devices(app).await;
+
+    let response = app.delete_trusted_device(&devices[0].device_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let removal_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == TRUSTED_DEVICE_COOKIE_NAME)
+        .expect("No trusted device cookie removal found");
+
+    assert!(removal_cookie.value().is_empty());
+
+    let trusted_device_store = app.trusted_device_store.read().await;
+    assert!(trusted_device_store
+        .get_devices(&email)
+        .await
+        .unwrap()
+        .is_empty());
+    drop(trusted_device_store);
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_404_if_device_belongs_to_another_user(app: &mut TestApp) {
+    let email = signup_with_2fa(app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login_and_trust_device(app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let devices = get_trusted_devices(app).await;
+
+    // Log in as another user without 2FA, from the same browser
+    let other_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": other_email,
+        "password": "password123",
+        "requires2FA": false,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": other_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(get_trusted_devices(app).await.is_empty());
+
+    for device_id in [devices[0].device_id.as_str(), "not-a-uuid"] {
+        let response = app.delete_trusted_device(device_id).await;
+        assert_eq!(response.status().as_u16(), 404);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Device not found".to_owned()
+        );
+    }
+
+    let trusted_device_store = app.trusted_device_store.read().await;
+    assert_eq!(
+        trusted_device_store
+            .get_devices(&email)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
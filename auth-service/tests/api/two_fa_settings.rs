@@ -0,0 +1,424 @@
+use auth_service::{
+    domain::{Email, LoginAttemptId, TwoFACodeStore},
+    routes::TwoFASettingsResponse,
+    utils::auth::AuthMethod,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_context::test_context;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{create_auth_token, get_login_attempt_id, get_random_email, TestApp};
+
+async fn signup(app: &TestApp, requires_2fa: bool) -> Email {
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": requires_2fa,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn signup_and_login(app: &TestApp, requires_2fa: bool) -> Email {
+    let email = signup(app, requires_2fa).await;
+    let response = login(app, &email).await;
+
+    if requires_2fa {
+        assert_eq!(response.status().as_u16(), 206);
+
+        let login_attempt_id = get_login_attempt_id(response).await;
+
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": email.as_ref().expose_secret(),
+                "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+                "2FACode": get_code(app, &login_attempt_id).await,
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    } else {
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    email
+}
+
+async fn login(app: &TestApp, email: &Email) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    app.post_login(&login_body).await
+}
+
+async fn get_code(app: &TestApp, login_attempt_id: &LoginAttemptId) -> String {
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(login_attempt_id)
+        .await
+        .expect("should get code");
+
+    two_fa_code.as_ref().expose_secret().to_owned()
+}
+
+async fn mount_email_mocks(app: &TestApp, codes: u64, notification: Option<&str>) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_string_contains("Your verification code"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(codes)
+        .mount(&app.email_server)
+        .await;
+
+    if let Some(subject) = notification {
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_string_contains(subject))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&app.email_server)
+            .await;
+    }
+}
+
+async fn assert_requires_2fa(response: reqwest::Response, requires_2fa: bool) {
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<TwoFASettingsResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFASettingsResponse"),
+        TwoFASettingsResponse { requires_2fa }
+    );
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_enable_2fa_once_code_verified(app: &mut TestApp) {
+    // One code to enable 2FA, one on the next login
+    mount_email_mocks(app, 2, Some("Two-factor authentication enabled")).await;
+
+    let email = signup_and_login(app, false).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+
+    let response = app
+        .post_verify_enable_2fa(&serde_json::json!({
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": get_code(app, &login_attempt_id).await,
+        }))
+        .await;
+
+    assert_requires_2fa(response, true).await;
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_disable_2fa_with_password_and_code(app: &mut TestApp) {
+    // One code to log in, one to disable 2FA
+    mount_email_mocks(app, 2, Some("Two-factor authentication disabled")).await;
+
+    let email = signup_and_login(app, true).await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+
+    let response = app
+        .post_verify_disable_2fa(&serde_json::json!({
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": get_code(app, &login_attempt_id).await,
+        }))
+        .await;
+
+    assert_requires_2fa(response, false).await;
+
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_incorrect_password_or_code(app: &mut TestApp) {
+    // Codes to log in, to disable 2FA, and to log in again
+    mount_email_mocks(app, 3, None).await;
+
+    let email = signup_and_login(app, true).await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password321" }))
+        .await;
+
+    assert_error(response, 401, "Incorrect credentials").await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let code = get_code(app, &login_attempt_id).await;
+    let incorrect_code = if code == "000000" { "111111" } else { "000000" };
+
+    let test_cases = [
+        (
+            login_attempt_id.as_ref().expose_secret().to_owned(),
+            incorrect_code.to_owned(),
+        ),
+        (
+            LoginAttemptId::default()
+                .as_ref()
+                .expose_secret()
+                .to_owned(),
+            code,
+        ),
+    ];
+
+    for (login_attempt_id, code) in test_cases {
+        let response = app
+            .post_verify_disable_2fa(&serde_json::json!({
+                "loginAttemptId": login_attempt_id,
+                "2FACode": code,
+            }))
+            .await;
+
+        assert_error(response, 401, "Incorrect credentials").await;
+    }
+
+    // 2FA is still required
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_code_belongs_to_another_user(app: &mut TestApp) {
+    mount_email_mocks(app, 2, None).await;
+
+    signup_and_login(app, true).await;
+    let other_email = signup(app, true).await;
+
+    let response = login(app, &other_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+
+    let response = app
+        .post_verify_disable_2fa(&serde_json::json!({
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": get_code(app, &login_attempt_id).await,
+        }))
+        .await;
+
+    assert_error(response, 401, "Incorrect credentials").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_settings_code_used_to_log_in(app: &mut TestApp) {
+    // Codes to log in, to disable 2FA, to enable 2FA, and to log in again
+    mount_email_mocks(app, 4, None).await;
+
+    let email = signup_and_login(app, true).await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let disable_attempt_id = get_login_attempt_id(response).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    signup_and_login(app, false).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let enable_attempt_id = get_login_attempt_id(response).await;
+
+    for login_attempt_id in [disable_attempt_id, enable_attempt_id] {
+        let code_email = app
+            .two_fa_code_store
+            .read()
+            .await
+            .get_code(&login_attempt_id)
+            .await
+            .expect("should get code")
+            .0;
+
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": code_email.as_ref().expose_secret(),
+                "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+                "2FACode": get_code(app, &login_attempt_id).await,
+            }))
+            .await;
+
+        assert_error(response, 401, "Incorrect credentials").await;
+    }
+
+    // 2FA is still required
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_login_code_used_to_change_2fa(app: &mut TestApp) {
+    // Codes to log in twice and to disable 2FA
+    mount_email_mocks(app, 3, Some("Two-factor authentication disabled")).await;
+
+    let email = signup_and_login(app, true).await;
+
+    // A login that's never completed
+    let response = login(app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let login_code = get_code(app, &login_attempt_id).await;
+
+    let verify_body = serde_json::json!({
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": login_code,
+    });
+
+    let response = app.post_verify_disable_2fa(&verify_body).await;
+    assert_error(response, 401, "Incorrect credentials").await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let disable_attempt_id = get_login_attempt_id(response).await;
+
+    let response = app
+        .post_verify_disable_2fa(&serde_json::json!({
+            "loginAttemptId": disable_attempt_id.as_ref().expose_secret(),
+            "2FACode": get_code(app, &disable_attempt_id).await,
+        }))
+        .await;
+
+    assert_requires_2fa(response, false).await;
+
+    let response = app.post_verify_enable_2fa(&verify_body).await;
+    assert_error(response, 401, "Incorrect credentials").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_enabling_with_stale_login(app: &mut TestApp) {
+    mount_email_mocks(app, 0, None).await;
+
+    let email = signup(app, false).await;
+    let token = create_auth_token(&email, 3600, &[AuthMethod::Pwd]);
+
+    let response = app.post_enable_2fa_with_bearer(&token).await;
+    assert_error(response, 401, "Re-authentication required").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_409_if_2fa_already_in_requested_state(app: &mut TestApp) {
+    mount_email_mocks(app, 1, None).await;
+
+    signup_and_login(app, true).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_error(response, 409, "2FA is already enabled").await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    signup_and_login(app, false).await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_error(response, 409, "2FA is not enabled").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_400_if_not_logged_in(app: &mut TestApp) {
+    let response = app.post_enable_2fa().await;
+    assert_error(response, 400, "Missing auth token").await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_422_if_malformed_input(app: &mut TestApp) {
+    mount_email_mocks(app, 1, None).await;
+
+    signup_and_login(app, true).await;
+
+    let response = app
+        .post_disable_2fa(&serde_json::json!({ "pass": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    let test_cases = [
+        serde_json::json!({ "loginAttemptId": LoginAttemptId::default().as_ref().expose_secret() }),
+        serde_json::json!({ "2FACode": "123456" }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_verify_disable_2fa(&test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
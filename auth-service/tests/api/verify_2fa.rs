@@ -1,6 +1,7 @@
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore},
-    utils::constants::JWT_COOKIE_NAME,
+    domain::{Email, LoginAttemptId, SessionStore, TwoFACode, TwoFACodeStore},
+    routes::BearerAuthResponse,
+    utils::constants::{JWT_COOKIE_NAME, MAX_PENDING_LOGIN_ATTEMPTS, MAX_TWO_FA_CODE_ATTEMPTS},
     ErrorResponse,
 };
 use secrecy::ExposeSecret;
@@ -10,7 +11,7 @@ use wiremock::{
     Mock, ResponseTemplate,
 };
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_login_attempt_id, get_random_email, TestApp};
 
 #[test_context(TestApp)]
 #[tokio::test]
@@ -42,10 +43,11 @@ async fn should_return_200_if_correct_code(app: &mut TestApp) {
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
 
+    let login_attempt_id = get_login_attempt_id(response).await;
     let two_fa_code_store = app.two_fa_code_store.read().await;
 
-    let (login_attempt_id, two_fa_code) = two_fa_code_store
-        .get_code(&email)
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
         .await
         .expect("should get code");
 
@@ -69,6 +71,135 @@ async fn should_return_200_if_correct_code(app: &mut TestApp) {
     assert!(!auth_cookie.value().is_empty());
 }
 
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_token_in_body_if_token_delivery_is_body(app: &mut TestApp) {
+    let email = Email::parse(get_random_email().into()).unwrap();
+    let password = "password123";
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": password,
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": password,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let input = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_verify_2fa(&input).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let json_body = response
+        .json::<BearerAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerAuthResponse");
+
+    assert!(!json_body.token.is_empty());
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_set_persistent_cookie_if_remember_me(app: &mut TestApp) {
+    let email = Email::parse(get_random_email().into()).unwrap();
+    let password = "password123";
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": password,
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": password,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let two_fa_code_store = app.two_fa_code_store.read().await;
+
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    drop(two_fa_code_store);
+
+    let input = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+        "rememberMe": true,
+    });
+
+    let response = app.post_verify_2fa(&input).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(std::time::Duration::from_secs(
+            app.settings.auth.remember_me_ttl_seconds as u64
+        ))
+    );
+
+    let session_store = app.session_store.read().await;
+    assert_eq!(session_store.get_sessions(&email).await.unwrap().len(), 1);
+}
+
 #[test_context(TestApp)]
 #[tokio::test]
 async fn should_return_400_if_invalid_input(app: &mut TestApp) {
@@ -142,7 +273,76 @@ async fn should_return_401_if_incorrect_credentials(app: &mut TestApp) {
 
 #[test_context(TestApp)]
 #[tokio::test]
-async fn should_return_401_if_old_code(app: &mut TestApp) {
+async fn should_return_401_for_correct_code_after_too_many_incorrect_ones(app: &mut TestApp) {
+    let email = Email::parse(get_random_email().into()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+
+    let (_, two_fa_code, _) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await
+        .expect("should get code");
+
+    let two_fa_code = two_fa_code.as_ref().expose_secret().to_owned();
+    let incorrect_code = if two_fa_code == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    for _ in 0..MAX_TWO_FA_CODE_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": email.as_ref().expose_secret(),
+                "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+                "2FACode": incorrect_code,
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_200_for_concurrent_login_attempts(app: &mut TestApp) {
     let email = Email::parse(get_random_email().into()).unwrap();
     let password = "password123";
 
@@ -162,6 +362,65 @@ async fn should_return_401_if_old_code(app: &mut TestApp) {
         .mount(&app.email_server)
         .await;
 
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": password,
+        "tokenDelivery": "body",
+    });
+
+    // Start a login on two devices before completing either
+    let mut login_attempts = Vec::new();
+
+    for _ in 0..2 {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 206);
+        login_attempts.push(get_login_attempt_id(response).await);
+    }
+
+    for login_attempt_id in login_attempts.iter().rev() {
+        let two_fa_code_store = app.two_fa_code_store.read().await;
+
+        let (_, two_fa_code, _) = two_fa_code_store
+            .get_code(login_attempt_id)
+            .await
+            .expect("should get code");
+
+        drop(two_fa_code_store);
+
+        let input = serde_json::json!({
+            "email": email.as_ref().expose_secret(),
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "tokenDelivery": "body",
+        });
+
+        let response = app.post_verify_2fa(&input).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[test_context(TestApp)]
+#[tokio::test]
+async fn should_return_401_if_login_attempt_evicted(app: &mut TestApp) {
+    let email = Email::parse(get_random_email().into()).unwrap();
+    let password = "password123";
+
+    let signup_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": password,
+        "requires2FA": true,
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(MAX_PENDING_LOGIN_ATTEMPTS as u64 + 1)
+        .mount(&app.email_server)
+        .await;
+
     let login_body = serde_json::json!({
         "email": email.as_ref().expose_secret(),
         "password": password,
@@ -170,17 +429,21 @@ async fn should_return_401_if_old_code(app: &mut TestApp) {
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
 
+    let login_attempt_id = get_login_attempt_id(response).await;
     let two_fa_code_store = app.two_fa_code_store.read().await;
 
-    let (login_attempt_id, two_fa_code) = two_fa_code_store
-        .get_code(&email)
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
         .await
         .expect("should get code");
 
     drop(two_fa_code_store);
 
-    let response = app.post_login(&login_body).await;
-    assert_eq!(response.status().as_u16(), 206);
+    // Starting more logins than allowed drops the oldest pending attempt
+    for _ in 0..MAX_PENDING_LOGIN_ATTEMPTS {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 206);
+    }
 
     let input = serde_json::json!({
         "email": email.as_ref().expose_secret(),
@@ -188,7 +451,6 @@ async fn should_return_401_if_old_code(app: &mut TestApp) {
         "2FACode": two_fa_code.as_ref().expose_secret(),
     });
 
-    app.post_verify_2fa(&input).await;
     let response = app.post_verify_2fa(&input).await;
 
     assert_eq!(response.status().as_u16(), 401);
@@ -233,10 +495,11 @@ async fn should_return_401_if_same_code_twice(app: &mut TestApp) {
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
 
+    let login_attempt_id = get_login_attempt_id(response).await;
     let two_fa_code_store = app.two_fa_code_store.read().await;
 
-    let (login_attempt_id, two_fa_code) = two_fa_code_store
-        .get_code(&email)
+    let (_, two_fa_code, _) = two_fa_code_store
+        .get_code(&login_attempt_id)
         .await
         .expect("should get code");
 
@@ -1,6 +1,6 @@
 use auth_service::{
     domain::{BannedTokenStore, Email},
-    utils::auth::generate_auth_token,
+    utils::auth::{generate_auth_token, AuthMethod},
     ErrorResponse,
 };
 use fake::{faker::internet::en::FreeEmail, Fake};
@@ -12,8 +12,12 @@ use crate::helpers::TestApp;
 #[test_context(TestApp)]
 #[tokio::test]
 async fn should_return_200_valid_token(app: &mut TestApp) {
-    let token =
-        generate_auth_token(&Email::parse(FreeEmail().fake::<String>().into()).unwrap()).unwrap();
+    let token = generate_auth_token(
+        &Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+        &[AuthMethod::Pwd],
+        &app.settings.auth,
+    )
+    .unwrap();
 
     let verify_token_body = serde_json::json!({
         "token": token.expose_secret(),
@@ -26,8 +30,12 @@ async fn should_return_200_valid_token(app: &mut TestApp) {
 #[test_context(TestApp)]
 #[tokio::test]
 async fn should_return_401_if_banned_token(app: &mut TestApp) {
-    let token =
-        generate_auth_token(&Email::parse(FreeEmail().fake::<String>().into()).unwrap()).unwrap();
+    let token = generate_auth_token(
+        &Email::parse(FreeEmail().fake::<String>().into()).unwrap(),
+        &[AuthMethod::Pwd],
+        &app.settings.auth,
+    )
+    .unwrap();
 
     let mut banned_token_store = app.banned_token_store.write().await;
 
@@ -0,0 +1,194 @@
+use auth_service::{
+    domain::{OutboxStatus, WebhookDeliveryStore, WebhookEventKind, WebhookSubscription},
+    services::webhook_worker::sign_webhook,
+};
+use chrono::{Duration, Utc};
+use secrecy::SecretString;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, test_settings, TestApp};
+
+const SECRET: &str = "webhook-secret";
+
+// Start the app with a subscription to `events` at the mock server
+async fn subscribed_app(subscriber: &MockServer, events: &[WebhookEventKind]) -> TestApp {
+    let mut settings = test_settings();
+    settings.webhooks.subscriptions = vec![WebhookSubscription {
+        url: format!("{}/hooks", subscriber.uri()),
+        secret: SECRET.to_owned().into(),
+        events: events.to_vec(),
+    }];
+
+    TestApp::with_settings(settings).await
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+}
+
+// The events the subscriber received, after checking each one's signature
+async fn received_events(subscriber: &MockServer) -> Vec<serde_json::Value> {
+    let secret: SecretString = SECRET.to_owned().into();
+
+    subscriber
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let signature = request.headers["X-Webhook-Signature"].to_str().unwrap();
+            let (timestamp, signature) = signature
+                .strip_prefix("t=")
+                .and_then(|rest| rest.split_once(",v1="))
+                .expect("Malformed signature header");
+
+            let body = std::str::from_utf8(&request.body).unwrap();
+            assert_eq!(
+                signature,
+                sign_webhook(&secret, timestamp.parse().unwrap(), body)
+            );
+
+            serde_json::from_str(body).unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn should_post_signed_event_to_subscriber() {
+    let subscriber = MockServer::start().await;
+    let mut app = subscribed_app(&subscriber, &[WebhookEventKind::UserCreated]).await;
+
+    Mock::given(method("POST"))
+        .and(path("/hooks"))
+        .and(header("X-Webhook-Event", "user.created"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&subscriber)
+        .await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    // Sent in the background, not by the request
+    assert!(subscriber.received_requests().await.unwrap().is_empty());
+
+    app.deliver_webhooks().await;
+
+    let events = received_events(&subscriber).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["type"], "user.created");
+    assert_eq!(events[0]["data"]["email"], random_email);
+    assert_eq!(events[0]["data"]["ipAddress"], "127.0.0.1");
+
+    // The delivery log records the subscriber's response
+    let event_id = Uuid::parse_str(events[0]["id"].as_str().unwrap()).unwrap();
+    let webhook_deliveries = app.webhook_deliveries.read().await;
+    let deliveries = webhook_deliveries.get_deliveries(event_id).await.unwrap();
+    assert_eq!(deliveries.len(), 1);
+
+    let status = webhook_deliveries
+        .get_status(deliveries[0].id)
+        .await
+        .unwrap();
+    assert_eq!(status, OutboxStatus::Sent);
+
+    let attempts = webhook_deliveries
+        .get_attempts(deliveries[0].id)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0].status_code, Some(200));
+    drop(webhook_deliveries);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_post_subscribed_events() {
+    let subscriber = MockServer::start().await;
+    let mut app = subscribed_app(&subscriber, &[WebhookEventKind::UserLogin]).await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&subscriber)
+        .await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    app.deliver_webhooks().await;
+
+    let events = received_events(&subscriber).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["type"], "user.login");
+    assert_eq!(events[0]["data"]["email"], random_email);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_retry_until_subscriber_accepts() {
+    let subscriber = MockServer::start().await;
+    let mut app = subscribed_app(&subscriber, &[WebhookEventKind::UserCreated]).await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&subscriber)
+        .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&subscriber)
+        .await;
+
+    signup(&app, &get_random_email()).await;
+    app.deliver_webhooks().await;
+
+    let events = received_events(&subscriber).await;
+    assert_eq!(events.len(), 1);
+
+    // Retried with backoff, with the same event
+    let later = Utc::now() + Duration::hours(1);
+    assert_eq!(app.webhook_worker.process_due(later).await.unwrap(), 1);
+
+    let retried = received_events(&subscriber).await;
+    assert_eq!(retried.len(), 2);
+    assert_eq!(retried[1], events[0]);
+
+    let event_id = Uuid::parse_str(events[0]["id"].as_str().unwrap()).unwrap();
+    let webhook_deliveries = app.webhook_deliveries.read().await;
+    let delivery = &webhook_deliveries.get_deliveries(event_id).await.unwrap()[0];
+
+    let status_codes = webhook_deliveries
+        .get_attempts(delivery.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|attempt| attempt.status_code)
+        .collect::<Vec<_>>();
+    assert_eq!(status_codes, [Some(503), Some(200)]);
+    assert_eq!(
+        webhook_deliveries.get_status(delivery.id).await.unwrap(),
+        OutboxStatus::Sent
+    );
+    drop(webhook_deliveries);
+
+    app.clean_up().await;
+}